@@ -0,0 +1,26 @@
+//! Run the mock VivaSpot API for offline testing of the full flow.
+//!
+//!     cargo run --example mock_api --features mock -- 127.0.0.1:3000
+//!
+//! Then select the `local` settings profile (or set `VIVASPOT_API_URL`)
+//! and use setup code `VS-TEST` (valid) or `VS-EXPD` (expired).
+use app_lib::mock_api::{MockApi, EXPIRED_CODE, VALID_CODE};
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:3000".to_string());
+
+    let mock = MockApi::start_on(&addr).await?.with_default_routes();
+    println!("Mock VivaSpot API running at {}", mock.base_url());
+    println!("  valid code:   {}", VALID_CODE);
+    println!("  expired code: {}", EXPIRED_CODE);
+    println!("Press Ctrl+C to stop.");
+
+    tokio::signal::ctrl_c().await?;
+    for request in mock.requests() {
+        println!("{} {}?{}", request.method, request.path, request.query);
+    }
+    Ok(())
+}
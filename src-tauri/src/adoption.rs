@@ -0,0 +1,129 @@
+/// Device-side adoption status, read from the AP's `info` command.
+///
+/// Older firmware often needs `set-inform` a second time once the
+/// controller has accepted the device, or it drops back to "Adopting" or
+/// "Disconnected". Two-phase adoption sends `set-inform`, watches `info`
+/// until the device reaches the adopting or provisioning stage and then
+/// sends it again. This module parses `info` and classifies its `Status`
+/// line; the SSH round trips are the caller's.
+///
+/// Typical output:
+///
+/// ```text
+/// Model:       UAP-AC-Lite
+/// Version:     6.5.28.14491
+/// MAC Address: fc:ec:da:11:22:33
+/// IP Address:  192.168.1.20
+/// Hostname:    UAP-AC-Lite
+/// Uptime:      1234 seconds
+///
+/// Status:      Adopting (http://unifi.example.com:8080/inform)
+/// ```
+use serde::Serialize;
+
+/// Shell command that prints device info on any firmware.
+pub const INFO_COMMAND: &str = "info 2>/dev/null || mca-cli-op info";
+
+/// How far along adoption the device thinks it is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AdoptionStage {
+    NotAdopted,
+    Adopting,
+    Provisioning,
+    Connected,
+    /// The controller refused the device or never answered
+    Rejected,
+    /// Anything else, e.g. `Unknown[11]` or `Timeout`
+    Other,
+}
+
+impl AdoptionStage {
+    fn from_status(status: &str) -> Self {
+        let status = status.to_lowercase();
+        if status.starts_with("not adopted") {
+            AdoptionStage::NotAdopted
+        } else if status.starts_with("adopting") {
+            AdoptionStage::Adopting
+        } else if status.starts_with("provision") {
+            AdoptionStage::Provisioning
+        } else if status.starts_with("connected") {
+            AdoptionStage::Connected
+        } else if status.starts_with("server reject") {
+            AdoptionStage::Rejected
+        } else {
+            AdoptionStage::Other
+        }
+    }
+
+    /// The controller has accepted the device, so a second `set-inform`
+    /// makes it stick.
+    pub fn is_accepted(&self) -> bool {
+        matches!(
+            self,
+            AdoptionStage::Adopting | AdoptionStage::Provisioning | AdoptionStage::Connected
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    pub model: Option<String>,
+    pub version: Option<String>,
+    pub mac: Option<String>,
+    pub ip: Option<String>,
+    pub hostname: Option<String>,
+    /// Raw status text, e.g. `Adopting`
+    pub status: String,
+    pub stage: AdoptionStage,
+    /// Inform URL shown next to the status, if any
+    pub inform_url: Option<String>,
+}
+
+/// Parse the output of `info`. Returns `None` if there's no `Status` line.
+pub fn parse_info(output: &str) -> Option<DeviceInfo> {
+    let field = |name: &str| {
+        output.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim().eq_ignore_ascii_case(name) && !value.trim().is_empty())
+                .then(|| value.trim().to_string())
+        })
+    };
+
+    let status_line = field("Status")?;
+    // "Adopting (http://host:8080/inform)" — the URL itself contains ':'
+    let (status, inform_url) = match status_line.split_once(" (") {
+        Some((status, rest)) => (
+            status.trim().to_string(),
+            Some(rest.trim_end_matches(')').trim().to_string()).filter(|u| !u.is_empty()),
+        ),
+        None => (status_line.clone(), None),
+    };
+
+    Some(DeviceInfo {
+        model: field("Model"),
+        version: field("Version"),
+        mac: field("MAC Address"),
+        ip: field("IP Address"),
+        hostname: field("Hostname"),
+        stage: AdoptionStage::from_status(&status),
+        status,
+        inform_url,
+    })
+}
+
+/// One step of an adoption, reported to the UI as it completes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdoptionPhase {
+    /// In the order they can appear: `preflight` (only when the inform URL
+    /// check from this computer warned), `backup-config`, `set-inform`,
+    /// `wait-for-controller` and `set-inform-again`
+    pub name: String,
+    pub success: bool,
+    /// Went ahead despite a problem the technician should know about
+    pub warning: bool,
+    pub detail: String,
+    pub duration_ms: u64,
+}
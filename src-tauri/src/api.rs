@@ -1,11 +1,17 @@
-/// HTTP client for the /api/setup-code endpoint.
+/// HTTP client for the VivaSpot API.
 ///
-/// The companion app calls this to validate a setup code and retrieve
-/// the inform URL and site metadata (see design doc §4.6.2).
+/// The companion app calls /api/setup-code to validate a setup code and
+/// retrieve the inform URL and site metadata (see design doc §4.6.2),
+/// /api/sites/{id}/devices for the site's pre-provisioned inventory, and
+/// /api/adoption-report to tell the back office about each adoption attempt.
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-// Default to the production wizard URL — can be overridden for dev
-const DEFAULT_API_BASE: &str = "https://ubiquitywizard.onrender.com";
+use crate::settings::NetworkSettings;
+use crate::setup_code;
+
+// Production wizard URL — other environments come from settings profiles
+pub const DEFAULT_API_BASE: &str = "https://ubiquitywizard.onrender.com";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -13,6 +19,9 @@ pub struct SetupCodeResponse {
     pub inform_url: String,
     pub site_id: String,
     pub site_name: String,
+    /// When the code stops being valid; older API versions omit it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,72 +31,407 @@ pub struct SetupCodeError {
     pub expired: bool,
 }
 
+/// A device the site was planned with. Installers may provision by exact
+/// MAC or only by model, so every field is optional.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpectedDevice {
+    #[serde(default)]
+    pub mac: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Planned device name, e.g. "Lobby AP"
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Floor or location label, e.g. "2nd floor — east corridor"
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteInventory {
+    pub site_id: String,
+    pub devices: Vec<ExpectedDevice>,
+}
+
+/// One adoption attempt, as reported to the back office.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdoptionReport {
+    /// Client-generated ID so a retried report can be de-duplicated server-side
+    pub id: String,
+    pub setup_code: String,
+    pub site_id: String,
+    pub mac: String,
+    pub model: String,
+    pub firmware: String,
+    /// "success" or "failed"
+    pub result: String,
+    pub error_code: Option<String>,
+    pub attempted_at: String,
+    pub app_version: String,
+}
+
 #[derive(Debug)]
 pub enum ApiError {
     InvalidCode(String),
     ExpiredCode(String),
     NetworkError(String),
+    /// 429 (or 503 with Retry-After) that persisted through all retries
+    RateLimited {
+        status: u16,
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    /// 5xx that persisted through all retries
+    ServerError {
+        status: u16,
+        message: String,
+    },
     Other(String),
 }
 
+impl ApiError {
+    /// Whether the same request might succeed later: VivaSpot couldn't be
+    /// reached, was busy or failed itself. A 4xx or an unreadable answer
+    /// will fail the same way every time.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ApiError::NetworkError(_) | ApiError::RateLimited { .. } | ApiError::ServerError { .. }
+        )
+    }
+}
+
 impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ApiError::InvalidCode(msg) => write!(f, "{}", msg),
             ApiError::ExpiredCode(msg) => write!(f, "{}", msg),
             ApiError::NetworkError(msg) => write!(f, "{}", msg),
+            ApiError::RateLimited {
+                status, message, ..
+            } => {
+                write!(f, "VivaSpot is busy ({}): {}", status, message)
+            }
+            ApiError::ServerError { status, message } => {
+                write!(f, "VivaSpot server error ({}): {}", status, message)
+            }
             ApiError::Other(msg) => write!(f, "{}", msg),
         }
     }
 }
 
-/// Validate a setup code against the VivaSpot API.
-/// Returns the inform URL and site metadata on success.
-pub async fn validate_setup_code(code: &str) -> Result<SetupCodeResponse, ApiError> {
-    let api_base = std::env::var("VIVASPOT_API_URL").unwrap_or_else(|_| DEFAULT_API_BASE.to_string());
-    let url = format!("{}/api/setup-code?code={}", api_base, code);
+/// How idempotent (GET) requests are retried on transient failures.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    /// Backoff before the first retry; doubles on each subsequent retry
+    pub base_delay: Duration,
+    /// Upper bound for any single wait, including a server's Retry-After
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        // Render cold starts usually clear within 10-20 seconds
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with full jitter for retry number `retry` (1-based).
+    fn backoff(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1u32 << (retry - 1).min(16))
+            .min(self.max_delay);
+        exp.mul_f64(rand::random::<f64>())
+    }
+}
+
+/// Shared client for the VivaSpot API.
+///
+/// Built once from the network settings and kept in managed state so
+/// connections are pooled and proxy/CA configuration is applied to every
+/// request. Rebuilt whenever the active profile or network settings change.
+pub struct ApiClient {
+    http: reqwest::Client,
+    api_base: String,
+    retry: RetryPolicy,
+}
+
+/// HTTP client builder with the network settings applied: user agent,
+/// timeouts, proxy and extra root CAs. Everything this computer sends to
+/// the internet goes through one of these, so a site's proxy only has to
+/// be configured once.
+pub fn http_client_builder(network: &NetworkSettings) -> Result<reqwest::ClientBuilder, ApiError> {
+    let user_agent = format!(
+        "VivaSpotSetupAssistant/{} ({}; {})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(Duration::from_secs(network.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(network.read_timeout_secs));
+
+    // System and environment proxies are used automatically; an explicit
+    // proxy from settings takes precedence over them.
+    if let Some(proxy_url) = network.proxy_url.as_deref().filter(|p| !p.is_empty()) {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| ApiError::Other(format!("Invalid proxy URL {}: {}", proxy_url, e)))?;
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ca_path) = network.ca_bundle_path.as_deref().filter(|p| !p.is_empty()) {
+        let pem = std::fs::read(ca_path)
+            .map_err(|e| ApiError::Other(format!("Failed to read CA bundle {}: {}", ca_path, e)))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| ApiError::Other(format!("Invalid CA bundle {}: {}", ca_path, e)))?;
+        log::info!("Loaded {} extra root CA(s) from {}", certs.len(), ca_path);
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    Ok(builder)
+}
+
+impl ApiClient {
+    pub fn new(api_base: &str, network: &NetworkSettings) -> Result<Self, ApiError> {
+        let http = http_client_builder(network)?
+            .build()
+            .map_err(|e| ApiError::Other(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(ApiClient {
+            http,
+            api_base: api_base.trim_end_matches('/').to_string(),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn api_base(&self) -> &str {
+        &self.api_base
+    }
+
+    /// Send an idempotent request, retrying connection failures, timeouts,
+    /// 429 and 5xx responses with jittered backoff. A `Retry-After` on 429
+    /// or 503 is honoured if it fits within the policy's `max_delay`.
+    /// Returns the last response once retries are exhausted so the caller
+    /// can turn it into a specific error.
+    async fn send_idempotent(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ApiError> {
+        let mut attempt = 1;
+        loop {
+            let result = request().send().await;
+
+            let delay = match &result {
+                Err(e) if e.is_timeout() || e.is_connect() => self.retry.backoff(attempt),
+                Ok(response) if is_retryable_status(response.status().as_u16()) => {
+                    match retry_after(response) {
+                        Some(wait) if wait > self.retry.max_delay => {
+                            log::warn!("Server asked to retry after {:?}, giving up", wait);
+                            return result.map_err(send_error);
+                        }
+                        Some(wait) => wait,
+                        None => self.retry.backoff(attempt),
+                    }
+                }
+                _ => return result.map_err(send_error),
+            };
+
+            if attempt >= self.retry.max_attempts {
+                return result.map_err(send_error);
+            }
+
+            match &result {
+                Ok(response) => log::warn!(
+                    "Attempt {} got {}, retrying in {:?}",
+                    attempt,
+                    response.status(),
+                    delay
+                ),
+                Err(e) => log::warn!(
+                    "Attempt {} failed ({}), retrying in {:?}",
+                    attempt,
+                    e,
+                    delay
+                ),
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Validate a setup code against the VivaSpot API.
+    /// Returns the inform URL and site metadata on success.
+    /// Malformed codes are rejected locally before any request is made.
+    pub async fn validate_setup_code(&self, code: &str) -> Result<SetupCodeResponse, ApiError> {
+        let code = setup_code::normalize(code)?;
+        let url = format!("{}/api/setup-code", self.api_base);
+
+        log::info!("Validating setup code: {}", code);
 
-    log::info!("Validating setup code: {}", code);
+        let response = self
+            .send_idempotent(|| self.http.get(&url).query(&[("code", &code)]))
+            .await?;
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-        .map_err(|e| {
-            if e.is_timeout() || e.is_connect() {
-                ApiError::NetworkError(
-                    "Can't connect to VivaSpot. Check your internet connection.".to_string(),
-                )
+        if response.status().is_success() {
+            let data: SetupCodeResponse = response
+                .json()
+                .await
+                .map_err(|e| ApiError::Other(format!("Failed to parse response: {}", e)))?;
+
+            log::info!(
+                "Setup code valid — site: {}, inform URL: {}",
+                data.site_name,
+                data.inform_url
+            );
+            Ok(data)
+        } else if response.status().as_u16() == 404 {
+            let err: SetupCodeError = response
+                .json()
+                .await
+                .map_err(|e| ApiError::Other(format!("Failed to parse error: {}", e)))?;
+
+            if err.expired {
+                Err(ApiError::ExpiredCode(err.error))
             } else {
-                ApiError::Other(format!("Request failed: {}", e))
+                Err(ApiError::InvalidCode(err.error))
             }
-        })?;
+        } else {
+            Err(status_error(response).await)
+        }
+    }
 
-    if response.status().is_success() {
-        let data: SetupCodeResponse = response
-            .json()
-            .await
-            .map_err(|e| ApiError::Other(format!("Failed to parse response: {}", e)))?;
+    /// Fetch the devices the site is expected to have.
+    pub async fn fetch_site_inventory(&self, site_id: &str) -> Result<SiteInventory, ApiError> {
+        // Site IDs come from the API but are still escaped as one path segment
+        let mut url = reqwest::Url::parse(&self.api_base)
+            .map_err(|e| ApiError::Other(format!("Invalid API URL {}: {}", self.api_base, e)))?;
+        url.path_segments_mut()
+            .map_err(|_| ApiError::Other(format!("Invalid API URL {}", self.api_base)))?
+            .pop_if_empty()
+            .extend(["api", "sites", site_id, "devices"]);
+
+        log::info!("Fetching device inventory for site {}", site_id);
+
+        let response = self.send_idempotent(|| self.http.get(url.clone())).await?;
 
-        log::info!("Setup code valid — site: {}, inform URL: {}", data.site_name, data.inform_url);
-        Ok(data)
-    } else if response.status().as_u16() == 404 {
-        let err: SetupCodeError = response
-            .json()
+        if response.status().is_success() {
+            let inventory: SiteInventory = response
+                .json()
+                .await
+                .map_err(|e| ApiError::Other(format!("Failed to parse inventory: {}", e)))?;
+
+            log::info!(
+                "Site {} expects {} device(s)",
+                site_id,
+                inventory.devices.len()
+            );
+            Ok(inventory)
+        } else {
+            Err(status_error(response).await)
+        }
+    }
+
+    /// Post one adoption outcome to the back office.
+    pub async fn report_adoption(&self, report: &AdoptionReport) -> Result<(), ApiError> {
+        let url = format!("{}/api/adoption-report", self.api_base);
+
+        let response = self
+            .http
+            .post(&url)
+            .json(report)
+            .send()
             .await
-            .map_err(|e| ApiError::Other(format!("Failed to parse error: {}", e)))?;
+            .map_err(send_error)?;
 
-        if err.expired {
-            Err(ApiError::ExpiredCode(err.error))
+        if response.status().is_success() {
+            log::info!("Reported adoption of {} ({})", report.mac, report.result);
+            Ok(())
         } else {
-            Err(ApiError::InvalidCode(err.error))
+            Err(status_error(response).await)
         }
+    }
+}
+
+fn send_error(e: reqwest::Error) -> ApiError {
+    // is_request covers connections dropped while the request was sent
+    if e.is_timeout() || e.is_connect() || e.is_request() {
+        ApiError::NetworkError(
+            "Can't connect to VivaSpot. Check your internet connection.".to_string(),
+        )
     } else {
-        Err(ApiError::Other(format!(
-            "Unexpected response: {}",
-            response.status()
-        )))
+        ApiError::Other(format!("Request failed: {}", e))
+    }
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..=599).contains(&status)
+}
+
+/// Parse `Retry-After` (delta-seconds or HTTP-date) on 429 and 503 responses.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let status = response.status().as_u16();
+    if status != 429 && status != 503 {
+        return None;
+    }
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+        .or(Some(Duration::ZERO))
+}
+
+/// Turn a non-success response into an error, keeping the server's message.
+async fn status_error(response: reqwest::Response) -> ApiError {
+    let status = response.status().as_u16();
+    let wait = retry_after(&response);
+    let body = response.text().await.unwrap_or_default();
+    // The API returns {"error": "..."}; fall back to the raw body
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| body.trim().chars().take(200).collect());
+
+    match status {
+        429 => ApiError::RateLimited {
+            status,
+            retry_after: wait,
+            message,
+        },
+        503 if wait.is_some() => ApiError::RateLimited {
+            status,
+            retry_after: wait,
+            message,
+        },
+        500..=599 => ApiError::ServerError { status, message },
+        _ => ApiError::Other(format!("Unexpected response: {} {}", status, message)),
     }
 }
@@ -0,0 +1,163 @@
+/// Offline setup bundles signed by the VivaSpot backend.
+///
+/// New-build sites often have no WAN on install day, so a setup code can't
+/// be resolved online. Instead the wizard can export a bundle — the same
+/// inform URL and site metadata `/api/setup-code` returns, plus an expiry —
+/// signed with Ed25519. The app verifies it against an embedded public key
+/// and feeds the result into the normal adoption flow.
+///
+/// Wire format, as a `.vivaspot` file:
+///
+/// ```json
+/// { "payload": "<base64url JSON>", "signature": "<base64url Ed25519 signature>" }
+/// ```
+///
+/// or as a deep link: `vivaspot://bundle?payload=...&signature=...`.
+/// The signature covers the decoded payload bytes exactly.
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::Deserialize;
+
+use crate::api::SetupCodeResponse;
+
+/// Base64 Ed25519 public key the backend signs bundles with, injected at
+/// build time via `VIVASPOT_BUNDLE_PUBLIC_KEY`. A release build without it
+/// fails to compile rather than ship a key the backend doesn't hold (see
+/// "Release builds" in the README).
+#[cfg(not(debug_assertions))]
+const BUNDLE_PUBLIC_KEY: &str = match option_env!("VIVASPOT_BUNDLE_PUBLIC_KEY") {
+    Some(key) => key,
+    None => panic!("Set VIVASPOT_BUNDLE_PUBLIC_KEY to the backend's bundle signing key"),
+};
+/// Development builds fall back to the test key, whose private half is 32
+/// bytes of 7 (see tests/bundle.rs), so bundles can be signed locally.
+#[cfg(debug_assertions)]
+const BUNDLE_PUBLIC_KEY: &str = match option_env!("VIVASPOT_BUNDLE_PUBLIC_KEY") {
+    Some(key) => key,
+    None => "6kpsY+KcUgq+9VB7Ey7F+ZVHdq6+vnuSQh7qaRRG0iw=",
+};
+
+#[derive(Debug)]
+pub enum BundleError {
+    Malformed(String),
+    BadSignature,
+    Expired(DateTime<Utc>),
+}
+
+impl std::fmt::Display for BundleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BundleError::Malformed(msg) => write!(f, "Invalid setup bundle: {}", msg),
+            BundleError::BadSignature => {
+                write!(
+                    f,
+                    "This setup bundle wasn't issued by VivaSpot or has been altered"
+                )
+            }
+            BundleError::Expired(at) => write!(
+                f,
+                "This setup bundle expired on {}",
+                at.format("%Y-%m-%d %H:%M UTC")
+            ),
+        }
+    }
+}
+
+/// A bundle as received, not yet verified.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignedBundle {
+    pub payload: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundlePayload {
+    inform_url: String,
+    site_id: String,
+    site_name: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl SignedBundle {
+    /// Parse the JSON contents of a bundle file.
+    pub fn from_json(text: &str) -> Result<Self, BundleError> {
+        serde_json::from_str(text.trim()).map_err(|e| BundleError::Malformed(e.to_string()))
+    }
+
+    /// Parse a `vivaspot://bundle?payload=...&signature=...` deep link.
+    pub fn from_deep_link(url: &url::Url) -> Result<Self, BundleError> {
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.into_owned())
+                .ok_or_else(|| BundleError::Malformed(format!("link is missing `{}`", name)))
+        };
+        Ok(SignedBundle {
+            payload: param("payload")?,
+            signature: param("signature")?,
+        })
+    }
+
+    /// Verify against the embedded VivaSpot key and check expiry.
+    pub fn open(&self) -> Result<SetupCodeResponse, BundleError> {
+        let key_bytes: [u8; 32] = STANDARD
+            .decode(BUNDLE_PUBLIC_KEY)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| BundleError::Malformed("embedded public key is invalid".to_string()))?;
+        self.open_with_key(&key_bytes, Utc::now())
+    }
+
+    /// Verify against `public_key` as of `now`.
+    pub fn open_with_key(
+        &self,
+        public_key: &[u8; 32],
+        now: DateTime<Utc>,
+    ) -> Result<SetupCodeResponse, BundleError> {
+        let key = VerifyingKey::from_bytes(public_key)
+            .map_err(|e| BundleError::Malformed(format!("bad public key: {}", e)))?;
+
+        let payload = decode_base64(&self.payload)?;
+        let signature_bytes: [u8; 64] = decode_base64(&self.signature)?
+            .try_into()
+            .map_err(|_| BundleError::Malformed("signature must be 64 bytes".to_string()))?;
+        let signature = Signature::from_bytes(&signature_bytes);
+
+        // Strict verification also rejects malleable and small-order signatures
+        key.verify_strict(&payload, &signature)
+            .map_err(|_| BundleError::BadSignature)?;
+
+        // Only parse what the signature vouches for
+        let payload: BundlePayload = serde_json::from_slice(&payload)
+            .map_err(|e| BundleError::Malformed(format!("bad payload: {}", e)))?;
+
+        if payload.expires_at <= now {
+            return Err(BundleError::Expired(payload.expires_at));
+        }
+
+        log::info!(
+            "Setup bundle verified — site: {}, expires {}",
+            payload.site_name,
+            payload.expires_at
+        );
+
+        Ok(SetupCodeResponse {
+            inform_url: payload.inform_url,
+            site_id: payload.site_id,
+            site_name: payload.site_name,
+            expires_at: Some(payload.expires_at),
+        })
+    }
+}
+
+/// Accept base64url (as used in links) or standard base64, padded or not.
+fn decode_base64(value: &str) -> Result<Vec<u8>, BundleError> {
+    let trimmed = value.trim().trim_end_matches('=');
+    URL_SAFE_NO_PAD
+        .decode(trimmed)
+        .or_else(|_| STANDARD.decode(value.trim()))
+        .map_err(|e| BundleError::Malformed(format!("bad base64: {}", e)))
+}
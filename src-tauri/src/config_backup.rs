@@ -0,0 +1,266 @@
+/// Configuration snapshots taken before the assistant changes an AP.
+///
+/// set-inform, factory resets, upgrades and restores all change device
+/// state, and a customer's working setup is easy to lose by pointing the
+/// wrong AP at the wrong controller. Before each of them the running
+/// config (`/tmp/system.cfg`) and the management config that holds the
+/// inform URL and auth key are pulled over SSH and stored in the app data
+/// directory, one JSON file per snapshot under the device's MAC.
+///
+/// Restoring writes the files back, saves them to flash with `cfgmtd` and
+/// reboots the AP so the restored config takes effect.
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::discovery::normalize_mac;
+use crate::ssh::{background_after, REBOOT_DELAY_SECS};
+
+/// Subdirectory of the app data directory the snapshots go in.
+pub const BACKUP_DIR_NAME: &str = "config-backups";
+/// Running config, rebuilt from flash at boot.
+pub const SYSTEM_CFG_PATH: &str = "/tmp/system.cfg";
+/// Management (mca) config: inform URL, auth key, controller settings.
+pub const MGMT_CFG_PATH: &str = "/var/etc/persistent/cfg/mgmt";
+pub const FILES: &[&str] = &[SYSTEM_CFG_PATH, MGMT_CFG_PATH];
+
+const FILE_MARKER: &str = "VS_FILE=";
+const END_MARKER: &str = "VS_END";
+const MISSING_MARKER: &str = "VS_MISSING";
+const MAC_MARKER: &str = "VS_MAC=";
+const RESTORE_MARKER: &str = "VS_RESTORE=";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigFile {
+    /// Path on the device
+    pub path: String,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigBackup {
+    /// Unique per device, e.g. `20260118-142501-set-inform`
+    pub id: String,
+    pub mac: String,
+    pub ip: String,
+    /// Operation the snapshot was taken before
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<ConfigFile>,
+}
+
+/// A snapshot without the file contents, for listing.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub id: String,
+    pub mac: String,
+    pub ip: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+    /// Device paths included in the snapshot
+    pub files: Vec<String>,
+}
+
+impl ConfigBackup {
+    pub fn new(mac: &str, ip: &str, reason: &str, files: Vec<ConfigFile>) -> Self {
+        let created_at = Utc::now();
+        ConfigBackup {
+            id: format!("{}-{}", created_at.format("%Y%m%d-%H%M%S"), reason),
+            mac: normalize_mac(mac),
+            ip: ip.to_string(),
+            reason: reason.to_string(),
+            created_at,
+            files,
+        }
+    }
+
+    pub fn summary(&self) -> BackupSummary {
+        BackupSummary {
+            id: self.id.clone(),
+            mac: self.mac.clone(),
+            ip: self.ip.clone(),
+            reason: self.reason.clone(),
+            created_at: self.created_at,
+            files: self.files.iter().map(|f| f.path.clone()).collect(),
+        }
+    }
+}
+
+/// Shell command that prints the device MAC and every file in [`FILES`],
+/// each framed by markers.
+pub fn backup_command() -> String {
+    let mut command = format!("echo {MAC_MARKER}$(cat /sys/class/net/eth0/address 2>/dev/null)");
+    for path in FILES {
+        command.push_str(&format!(
+            "; echo {FILE_MARKER}{path}; if [ -f {path} ]; then cat {path}; echo; echo {END_MARKER}; \
+             else echo {MISSING_MARKER}; fi"
+        ));
+    }
+    command
+}
+
+/// The MAC the device reported and the files it printed, from
+/// [`backup_command`]'s output. Missing files are skipped, but a snapshot
+/// without the running config is useless, so that's an error.
+pub fn parse_backup(output: &str) -> Result<(Option<String>, Vec<ConfigFile>), String> {
+    let mut mac = None;
+    let mut files = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    for line in output.lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some((path, lines)) = current.as_mut() {
+            if line == END_MARKER {
+                // The newline echoed after the file ends the last line, so
+                // joining gives back exactly what `cat` printed, blank lines
+                // and a missing final newline included
+                files.push(ConfigFile {
+                    path: path.clone(),
+                    contents: lines.join("\n"),
+                });
+                current = None;
+            } else if line == MISSING_MARKER && lines.is_empty() {
+                current = None;
+            } else {
+                lines.push(line);
+            }
+        } else if let Some(value) = line.trim().strip_prefix(MAC_MARKER) {
+            mac = Some(value.trim().to_string()).filter(|m| !m.is_empty());
+        } else if let Some(path) = line.trim().strip_prefix(FILE_MARKER) {
+            current = Some((path.to_string(), Vec::new()));
+        }
+    }
+
+    if !files.iter().any(|f| f.path == SYSTEM_CFG_PATH) {
+        return Err(format!("{} wasn't found on the device", SYSTEM_CFG_PATH));
+    }
+    Ok((mac, files))
+}
+
+/// `contents` as a `printf` format that reproduces it exactly, on one line
+/// and safe inside single quotes.
+fn printf_literal(contents: &str) -> String {
+    let mut escaped = String::with_capacity(contents.len());
+    for c in contents.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\\' => escaped.push_str("\\\\"),
+            '%' => escaped.push_str("%%"),
+            '\'' => escaped.push_str("\\047"),
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\{:03o}", c as u8)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Shell command that writes the snapshot's files back, saves them to
+/// flash and schedules a reboot. Only files in [`FILES`] are written.
+pub fn restore_command(backup: &ConfigBackup) -> Result<String, String> {
+    let mut steps = Vec::new();
+    for file in &backup.files {
+        if !FILES.contains(&file.path.as_str()) {
+            return Err(format!(
+                "{} isn't a config file that can be restored",
+                file.path
+            ));
+        }
+        let dir = Path::new(&file.path)
+            .parent()
+            .map(|d| d.display().to_string())
+            .unwrap_or_else(|| "/".to_string());
+        steps.push(format!(
+            "mkdir -p {dir} && printf '{}' > {}",
+            printf_literal(&file.contents),
+            file.path
+        ));
+    }
+    if steps.is_empty() {
+        return Err("The snapshot has no files".to_string());
+    }
+    Ok(format!(
+        "if {} && cfgmtd -w -p /etc/; then echo {RESTORE_MARKER}saved; {} \
+         else echo {RESTORE_MARKER}failed; fi",
+        steps.join(" && "),
+        background_after(REBOOT_DELAY_SECS, "reboot")
+    ))
+}
+
+/// Whether [`restore_command`] wrote and saved the config.
+pub fn parse_restore(output: &str) -> Result<(), String> {
+    let saved = output
+        .lines()
+        .any(|line| line.trim() == format!("{RESTORE_MARKER}saved"));
+    if saved {
+        Ok(())
+    } else {
+        Err(format!("Restoring the config failed: {}", output.trim()))
+    }
+}
+
+/// Snapshots on disk, one directory per device.
+pub struct BackupStore {
+    dir: PathBuf,
+}
+
+impl BackupStore {
+    pub fn new(dir: PathBuf) -> Self {
+        BackupStore { dir }
+    }
+
+    fn device_dir(&self, mac: &str) -> PathBuf {
+        self.dir.join(normalize_mac(mac).replace(':', ""))
+    }
+
+    pub fn save(&self, backup: &ConfigBackup) -> Result<PathBuf, String> {
+        let dir = self.device_dir(&backup.mac);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let path = dir.join(format!("{}.json", backup.id));
+        let json = serde_json::to_string_pretty(backup)
+            .map_err(|e| format!("Failed to serialize config backup: {}", e))?;
+        std::fs::write(&path, json)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Snapshots of `mac`, newest first. Unreadable files are skipped.
+    pub fn list(&self, mac: &str) -> Vec<BackupSummary> {
+        let Ok(entries) = std::fs::read_dir(self.device_dir(mac)) else {
+            return Vec::new();
+        };
+        let mut backups: Vec<BackupSummary> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| match read_backup(&path) {
+                Ok(backup) => Some(backup.summary()),
+                Err(e) => {
+                    log::warn!("Skipping config backup: {}", e);
+                    None
+                }
+            })
+            .collect();
+        backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+        backups
+    }
+
+    pub fn load(&self, mac: &str, id: &str) -> Result<ConfigBackup, String> {
+        if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+            return Err(format!("\"{}\" isn't a backup id", id));
+        }
+        read_backup(&self.device_dir(mac).join(format!("{}.json", id)))
+    }
+}
+
+fn read_backup(path: &Path) -> Result<ConfigBackup, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("{} is unreadable: {}", path.display(), e))
+}
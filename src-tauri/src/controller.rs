@@ -0,0 +1,363 @@
+/// Client for self-hosted UniFi Network controllers.
+///
+/// Customers who run their own controller rather than VivaSpot's hosted one
+/// can sign in with a local controller account. `set-inform` only gets the
+/// AP to knock on the controller's door; this client lists the devices
+/// waiting for adoption, issues the adopt command and polls until the
+/// device reports as connected.
+///
+/// Two login flavours exist:
+/// - classic controllers (software installs, Cloud Key gen1): `POST
+///   /api/login`, API under `/api/s/{site}/...`
+/// - UniFi OS consoles (UDM, Cloud Key gen2+, UniFi OS Server): `POST
+///   /api/auth/login`, API under `/proxy/network/api/s/{site}/...`, and
+///   every mutating request must echo the `X-CSRF-Token` from login.
+///
+/// Which one is running is told from `GET /`: UniFi OS serves its console
+/// there with an `X-CSRF-Token` header, classic controllers redirect to
+/// `/manage`. The login endpoints can't tell them apart — classic
+/// controllers answer `/api/auth/login` with 401 `api.err.LoginRequired`,
+/// the same as a wrong password.
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::api::http_client_builder;
+use crate::settings::NetworkSettings;
+
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+/// UniFi device `state` values
+pub const STATE_DISCONNECTED: u32 = 0;
+pub const STATE_CONNECTED: u32 = 1;
+pub const STATE_PENDING: u32 = 2;
+
+#[derive(Debug)]
+pub enum ControllerError {
+    AuthFailed(String),
+    NetworkError(String),
+    /// The controller answered but rejected the request
+    Api(String),
+    Timeout(String),
+}
+
+impl std::fmt::Display for ControllerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ControllerError::AuthFailed(msg) => write!(f, "Controller login failed: {}", msg),
+            ControllerError::NetworkError(msg) => write!(f, "Controller unreachable: {}", msg),
+            ControllerError::Api(msg) => write!(f, "Controller error: {}", msg),
+            ControllerError::Timeout(msg) => write!(f, "Timed out: {}", msg),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ControllerKind {
+    Classic,
+    UnifiOs,
+}
+
+/// A device as listed by `stat/device`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControllerDevice {
+    pub mac: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub ip: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub state: u32,
+    #[serde(default)]
+    pub adopted: bool,
+}
+
+impl ControllerDevice {
+    pub fn state_label(&self) -> &'static str {
+        state_label(self.state)
+    }
+}
+
+pub fn state_label(state: u32) -> &'static str {
+    match state {
+        STATE_DISCONNECTED => "disconnected",
+        STATE_CONNECTED => "connected",
+        STATE_PENDING => "pending adoption",
+        4 => "upgrading",
+        5 => "provisioning",
+        6 => "heartbeat missed",
+        7 => "adopting",
+        9 => "adoption error",
+        10 => "adoption failed",
+        11 => "isolated",
+        _ => "unknown",
+    }
+}
+
+/// `{ "meta": { "rc": "ok" | "error", "msg": ... }, "data": [...] }`
+#[derive(Debug, Deserialize)]
+struct Envelope<T> {
+    meta: Meta,
+    #[serde(default = "Vec::new")]
+    data: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    rc: String,
+    #[serde(default)]
+    msg: Option<String>,
+}
+
+pub struct ControllerClient {
+    http: reqwest::Client,
+    base_url: String,
+    site: String,
+    kind: ControllerKind,
+    csrf_token: Mutex<Option<String>>,
+}
+
+impl ControllerClient {
+    /// Sign in to the controller at `base_url` (e.g. `https://10.0.0.2:8443`
+    /// or `https://udm.local`) through the same proxy and CAs as the API.
+    /// Self-hosted controllers often use self-signed certificates;
+    /// `accept_invalid_certs` lets the user trust one explicitly.
+    pub async fn login(
+        base_url: &str,
+        username: &str,
+        password: &str,
+        site: &str,
+        network: &NetworkSettings,
+        accept_invalid_certs: bool,
+    ) -> Result<Self, ControllerError> {
+        // Site names are short ids like `default`; they go into URL paths
+        if site.is_empty()
+            || !site
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(ControllerError::Api(format!(
+                "\"{}\" isn't a site name. Use the short name from the controller's URL, e.g. default.",
+                site
+            )));
+        }
+        let http = http_client_builder(network)
+            .map_err(|e| ControllerError::NetworkError(e.to_string()))?
+            .cookie_store(true)
+            .danger_accept_invalid_certs(accept_invalid_certs)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| ControllerError::NetworkError(e.to_string()))?;
+        let base_url = base_url.trim_end_matches('/').to_string();
+        let credentials = json!({ "username": username, "password": password, "remember": true });
+
+        log::info!("Logging in to UniFi controller at {}", base_url);
+
+        let probe = http
+            .get(format!("{}/", base_url))
+            .send()
+            .await
+            .map_err(send_error)?;
+        let kind = if probe.status().is_success() && probe.headers().contains_key("x-csrf-token") {
+            ControllerKind::UnifiOs
+        } else {
+            ControllerKind::Classic
+        };
+        let login_path = match kind {
+            ControllerKind::Classic => "/api/login",
+            ControllerKind::UnifiOs => "/api/auth/login",
+        };
+
+        let response = http
+            .post(format!("{}{}", base_url, login_path))
+            .json(&credentials)
+            .send()
+            .await
+            .map_err(send_error)?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED
+            || status == reqwest::StatusCode::FORBIDDEN
+            || status == reqwest::StatusCode::BAD_REQUEST
+        {
+            return Err(ControllerError::AuthFailed(
+                "Check the controller username and password".to_string(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(ControllerError::Api(format!("Login returned {}", status)));
+        }
+
+        let csrf_token = response
+            .headers()
+            .get("x-csrf-token")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        log::info!("Logged in to {:?} controller, site {}", kind, site);
+
+        Ok(ControllerClient {
+            http,
+            base_url,
+            site: site.to_string(),
+            kind,
+            csrf_token: Mutex::new(csrf_token),
+        })
+    }
+
+    pub fn kind(&self) -> ControllerKind {
+        self.kind
+    }
+
+    pub fn site(&self) -> &str {
+        &self.site
+    }
+
+    /// URL of a site-scoped Network API endpoint, e.g. `stat/device`.
+    fn site_url(&self, endpoint: &str) -> String {
+        let prefix = match self.kind {
+            ControllerKind::Classic => "",
+            ControllerKind::UnifiOs => "/proxy/network",
+        };
+        format!(
+            "{}{}/api/s/{}/{}",
+            self.base_url, prefix, self.site, endpoint
+        )
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        endpoint: &str,
+    ) -> Result<Vec<T>, ControllerError> {
+        let response = self
+            .http
+            .get(self.site_url(endpoint))
+            .send()
+            .await
+            .map_err(send_error)?;
+        self.parse(response).await
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: serde_json::Value,
+    ) -> Result<Vec<T>, ControllerError> {
+        let mut request = self.http.post(self.site_url(endpoint)).json(&body);
+        if let Some(token) = self.csrf_token.lock().unwrap().as_deref() {
+            request = request.header("X-CSRF-Token", token);
+        }
+        let response = request.send().await.map_err(send_error)?;
+        self.parse(response).await
+    }
+
+    async fn parse<T: serde::de::DeserializeOwned>(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<Vec<T>, ControllerError> {
+        // UniFi OS rotates the CSRF token on some responses
+        if let Some(token) = response
+            .headers()
+            .get("x-updated-csrf-token")
+            .and_then(|v| v.to_str().ok())
+        {
+            *self.csrf_token.lock().unwrap() = Some(token.to_string());
+        }
+
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(ControllerError::AuthFailed(
+                "Session expired, sign in again".to_string(),
+            ));
+        }
+
+        let envelope: Envelope<T> = response.json().await.map_err(|e| {
+            ControllerError::Api(format!("Unexpected response ({}): {}", status, e))
+        })?;
+        if envelope.meta.rc != "ok" {
+            return Err(ControllerError::Api(
+                envelope.meta.msg.unwrap_or_else(|| status.to_string()),
+            ));
+        }
+        Ok(envelope.data)
+    }
+
+    /// Every device the site knows about, adopted or not.
+    pub async fn devices(&self) -> Result<Vec<ControllerDevice>, ControllerError> {
+        self.get("stat/device").await
+    }
+
+    /// Devices that have informed but haven't been adopted yet.
+    pub async fn pending_devices(&self) -> Result<Vec<ControllerDevice>, ControllerError> {
+        let devices = self.devices().await?;
+        Ok(devices
+            .into_iter()
+            .filter(|d| !d.adopted || d.state == STATE_PENDING)
+            .collect())
+    }
+
+    pub async fn device(&self, mac: &str) -> Result<Option<ControllerDevice>, ControllerError> {
+        let mac = mac.to_lowercase();
+        let devices: Vec<ControllerDevice> = self.get(&format!("stat/device/{}", mac)).await?;
+        Ok(devices
+            .into_iter()
+            .find(|d| d.mac.eq_ignore_ascii_case(&mac)))
+    }
+
+    /// Ask the controller to adopt `mac`.
+    pub async fn adopt(&self, mac: &str) -> Result<(), ControllerError> {
+        log::info!("Requesting controller adoption of {}", mac);
+        self.post::<serde_json::Value>(
+            "cmd/devmgr",
+            json!({ "cmd": "adopt", "mac": mac.to_lowercase() }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Poll until `mac` reports as connected or `timeout` passes.
+    pub async fn wait_until_connected(
+        &self,
+        mac: &str,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<ControllerDevice, ControllerError> {
+        let started = Instant::now();
+        let mut last_state = None;
+        loop {
+            if let Some(device) = self.device(mac).await? {
+                if last_state != Some(device.state) {
+                    log::info!("{} is {}", mac, device.state_label());
+                    last_state = Some(device.state);
+                }
+                if device.state == STATE_CONNECTED {
+                    return Ok(device);
+                }
+            }
+            if started.elapsed() >= timeout {
+                return Err(ControllerError::Timeout(format!(
+                    "{} didn't connect within {}s (last state: {})",
+                    mac,
+                    timeout.as_secs(),
+                    last_state.map_or("not seen", state_label)
+                )));
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
+
+fn send_error(e: reqwest::Error) -> ControllerError {
+    if e.is_timeout() {
+        ControllerError::Timeout(e.to_string())
+    } else {
+        ControllerError::NetworkError(e.to_string())
+    }
+}
@@ -0,0 +1,182 @@
+/// Diagnostics collected from an AP over SSH.
+///
+/// Support's first question after a failed adoption is always "what do the
+/// device logs say". This runs a fixed set of read-only commands in one
+/// SSH session, splits the output back into one file per command and zips
+/// it with the discovery record, so a field tech can send a single file
+/// without knowing any of the commands.
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::discovery::DiscoveredDevice;
+
+/// Subdirectory of the app data directory the archives go in.
+pub const ARCHIVE_DIR_NAME: &str = "device-diagnostics";
+
+/// (file name, command) pairs, all read-only.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("info", "info"),
+    ("messages", "cat /var/log/messages"),
+    ("ifconfig", "ifconfig"),
+    ("route", "route -n"),
+    ("resolv.conf", "cat /etc/resolv.conf"),
+    ("mca-cli-op-info", "mca-cli-op info"),
+    ("uptime", "uptime"),
+];
+
+const SECTION_MARKER: &str = "VS_SECTION=";
+const EXIT_MARKER: &str = "VS_EXIT=";
+
+/// Output of one diagnostic command.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Section {
+    pub name: String,
+    pub command: String,
+    pub output: String,
+    /// `None` if the session ended before the command finished
+    pub exit_code: Option<i32>,
+}
+
+/// Shell script that runs every command in [`COMMANDS`], each framed by
+/// markers so [`parse_output`] can split them apart again.
+pub fn collect_command() -> String {
+    COMMANDS
+        .iter()
+        .map(|(name, command)| {
+            format!("echo {SECTION_MARKER}{name}; {command} 2>&1; echo \"{EXIT_MARKER}$?\"")
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Split the output of [`collect_command`] into sections, in [`COMMANDS`]
+/// order. Commands with no output at all (session cut short) are still
+/// listed so the archive shows what's missing.
+pub fn parse_output(output: &str) -> Vec<Section> {
+    let mut collected: Vec<(String, Vec<&str>, Option<i32>)> = Vec::new();
+    for line in output.lines() {
+        let trimmed = line.trim_end_matches('\r');
+        if let Some(name) = trimmed.trim().strip_prefix(SECTION_MARKER) {
+            collected.push((name.to_string(), Vec::new(), None));
+        } else if let Some(code) = trimmed.trim().strip_prefix(EXIT_MARKER) {
+            if let Some(current) = collected.last_mut() {
+                current.2 = code.parse().ok();
+            }
+        } else if let Some(current) = collected.last_mut() {
+            current.1.push(trimmed);
+        }
+    }
+
+    COMMANDS
+        .iter()
+        .map(|(name, command)| {
+            let found = collected.iter().find(|(n, _, _)| n == name);
+            Section {
+                name: name.to_string(),
+                command: command.to_string(),
+                output: found
+                    .map(|(_, lines, _)| lines.join("\n"))
+                    .unwrap_or_default(),
+                exit_code: found.and_then(|(_, _, code)| *code),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub ip: String,
+    pub mac: Option<String>,
+    pub collected_at: String,
+    pub app_version: String,
+    pub commands: Vec<CommandSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandSummary {
+    pub name: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub bytes: usize,
+}
+
+impl ArchiveManifest {
+    pub fn new(
+        ip: &str,
+        device: Option<&DiscoveredDevice>,
+        sections: &[Section],
+        collected_at: &str,
+    ) -> Self {
+        ArchiveManifest {
+            ip: ip.to_string(),
+            mac: device.map(|d| d.mac.clone()),
+            collected_at: collected_at.to_string(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            commands: sections
+                .iter()
+                .map(|s| CommandSummary {
+                    name: s.name.clone(),
+                    command: s.command.clone(),
+                    exit_code: s.exit_code,
+                    bytes: s.output.len(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// File name for an archive, e.g. `FCECDA112233-20260118-142501.zip`.
+pub fn archive_name(ip: &str, device: Option<&DiscoveredDevice>, timestamp: &str) -> String {
+    let id = device
+        .map(|d| d.mac.replace(':', ""))
+        .unwrap_or_else(|| ip.replace([':', '.'], "-"));
+    format!("{}-{}.zip", id, timestamp)
+}
+
+/// Write `manifest.json`, `discovery.json` and one `commands/<name>.txt`
+/// per section to a zip at `out_path`.
+pub fn write_archive(
+    out_path: &Path,
+    manifest: &ArchiveManifest,
+    device: Option<&DiscoveredDevice>,
+    sections: &[Section],
+) -> Result<(), String> {
+    if let Some(dir) = out_path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let file = File::create(out_path)
+        .map_err(|e| format!("Failed to create {}: {}", out_path.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let mut add = |name: &str, contents: &[u8]| {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(contents).map_err(Into::into))
+            .map_err(|e| format!("Failed to add {}: {}", name, e))
+    };
+    let manifest_json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    add("manifest.json", &manifest_json)?;
+    let device_json = serde_json::to_vec_pretty(&device)
+        .map_err(|e| format!("Failed to serialize discovery record: {}", e))?;
+    add("discovery.json", &device_json)?;
+    for section in sections {
+        add(
+            &format!("commands/{}.txt", section.name),
+            section.output.as_bytes(),
+        )?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish device diagnostics: {}", e))?;
+    log::info!("Device diagnostics written to {}", out_path.display());
+    Ok(())
+}
@@ -0,0 +1,176 @@
+/// Persistent file logging and the diagnostics bundle technicians send to support.
+///
+/// Logs are written to the OS log directory in every build (see
+/// `log_plugin`), rotated by size, and passed through `redact` so that
+/// passwords, tokens and setup codes never reach disk. `export_bundle`
+/// zips the log files together with a manifest (app version, platform,
+/// last scan results) so a field tech can email a single file.
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use regex::Regex;
+use tauri::plugin::TauriPlugin;
+use tauri::Runtime;
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+use crate::discovery::DiscoveredDevice;
+
+/// Base name of the log file inside the app log directory.
+pub const LOG_FILE_NAME: &str = "vivaspot-setup-assistant";
+const MAX_LOG_FILE_BYTES: u128 = 2 * 1024 * 1024;
+const KEEP_LOG_FILES: usize = 5;
+const DEFAULT_RECENT_LINES: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsManifest {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub created_at: String,
+    pub last_scan: Vec<DiscoveredDevice>,
+}
+
+/// Build the log plugin used in both debug and release builds.
+/// Writes to stdout and a size-rotated file in the app log directory.
+pub fn log_plugin<R: Runtime>() -> TauriPlugin<R> {
+    tauri_plugin_log::Builder::default()
+        .level(log::LevelFilter::Info)
+        .targets([
+            Target::new(TargetKind::Stdout),
+            Target::new(TargetKind::LogDir {
+                file_name: Some(LOG_FILE_NAME.to_string()),
+            }),
+        ])
+        .rotation_strategy(RotationStrategy::KeepSome(KEEP_LOG_FILES))
+        .max_file_size(MAX_LOG_FILE_BYTES)
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}[{}][{}] {}",
+                chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                record.level(),
+                record.target(),
+                redact(&message.to_string())
+            ))
+        })
+        .build()
+}
+
+fn redaction_rules() -> &'static [(Regex, &'static str)] {
+    static RULES: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        vec![
+            // key=value / "key": "value" pairs for anything credential-like,
+            // including the token after an `Authorization: Bearer` scheme and
+            // the WPA keys and admin hashes in a device's system.cfg
+            (
+                Regex::new(
+                    r#"(?i)\b(password|passwd|pass|psk|hash|authkey|x_authkey|token|secret|cookie|authorization)(["']?\s*[:=]\s*["']?(?:(?:bearer|basic)\s+)?)([^"'\s&,;]+)"#,
+                )
+                .unwrap(),
+                "${1}${2}[REDACTED]",
+            ),
+            // Setup codes grant access to a site — keep only the last two characters
+            (
+                Regex::new(r"(?i)\bVS-[A-Z0-9]*([A-Z0-9]{2})\b").unwrap(),
+                "VS-**${1}",
+            ),
+        ]
+    })
+}
+
+/// Strip secrets from a log message before it is written anywhere.
+pub fn redact(message: &str) -> String {
+    let mut redacted = message.to_string();
+    for (pattern, replacement) in redaction_rules() {
+        redacted = pattern.replace_all(&redacted, *replacement).into_owned();
+    }
+    redacted
+}
+
+/// All log files in `log_dir` that belong to this app, oldest first.
+fn log_files(log_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = match std::fs::read_dir(log_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read log directory: {}", e)),
+    };
+
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "log")
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(LOG_FILE_NAME))
+        })
+        .map(|path| {
+            let modified = std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .unwrap_or(std::time::UNIX_EPOCH);
+            (modified, path)
+        })
+        .collect();
+
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Return the last `max_lines` log lines across all rotated log files.
+pub fn recent_log_lines(log_dir: &Path, max_lines: Option<usize>) -> Result<Vec<String>, String> {
+    let max_lines = max_lines.unwrap_or(DEFAULT_RECENT_LINES);
+    let mut lines = std::collections::VecDeque::with_capacity(max_lines);
+
+    for path in log_files(log_dir)? {
+        let file =
+            File::open(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            if lines.len() == max_lines {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        }
+    }
+
+    Ok(lines.into_iter().collect())
+}
+
+/// Write a zip bundle with every log file plus `manifest.json` to `out_path`.
+pub fn export_bundle(
+    out_path: &Path,
+    log_dir: &Path,
+    manifest: &DiagnosticsManifest,
+) -> Result<(), String> {
+    let file = File::create(out_path)
+        .map_err(|e| format!("Failed to create {}: {}", out_path.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let manifest_json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    zip.start_file("manifest.json", options)
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+    zip.write_all(&manifest_json)
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    for path in log_files(log_dir)? {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let contents = std::fs::read(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        zip.start_file(format!("logs/{}", name), options)
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        zip.write_all(&contents)
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish diagnostics bundle: {}", e))?;
+
+    log::info!("Diagnostics bundle written to {}", out_path.display());
+    Ok(())
+}
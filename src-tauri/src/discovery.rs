@@ -21,6 +21,8 @@ const TLV_IP_INFO: u8 = 0x02;
 const TLV_FIRMWARE: u8 = 0x03;
 const TLV_MODEL: u8 = 0x14;
 const TLV_PLATFORM: u8 = 0x0B;
+const TLV_DEFAULT: u8 = 0x17;
+const TLV_LOCATING: u8 = 0x18;
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -34,6 +36,10 @@ pub struct DiscoveredDevice {
     pub firmware: String,
     pub hostname: String,
     pub is_managed: bool,
+    /// Device is on factory settings
+    pub is_default: bool,
+    /// Locate LED is blinking
+    pub is_locating: bool,
 }
 
 /// Scan the local network for UniFi devices.
@@ -66,8 +72,7 @@ pub fn scan_network() -> Result<Vec<DiscoveredDevice>, String> {
 
     // Collect responses
     let mut devices = Vec::new();
-    let mut buf: [MaybeUninit<u8>; RECV_BUF_SIZE] =
-        unsafe { MaybeUninit::uninit().assume_init() };
+    let mut buf: [MaybeUninit<u8>; RECV_BUF_SIZE] = unsafe { MaybeUninit::uninit().assume_init() };
     let deadline = std::time::Instant::now() + Duration::from_millis(RECV_TIMEOUT_MS);
 
     while std::time::Instant::now() < deadline {
@@ -86,7 +91,10 @@ pub fn scan_network() -> Result<Vec<DiscoveredDevice>, String> {
 
                 if let Some(device) = parse_tlv_response(received, &source_ip) {
                     // Deduplicate by MAC
-                    if !devices.iter().any(|d: &DiscoveredDevice| d.mac == device.mac) {
+                    if !devices
+                        .iter()
+                        .any(|d: &DiscoveredDevice| d.mac == device.mac)
+                    {
                         devices.push(device);
                     }
                 }
@@ -120,6 +128,8 @@ fn parse_tlv_response(data: &[u8], source_ip: &str) -> Option<DiscoveredDevice>
     let mut firmware = String::new();
     let mut hostname = String::new();
     let mut is_managed = false;
+    let mut is_default = false;
+    let mut is_locating = false;
 
     // Skip first 4 bytes (response header)
     let mut pos = 4;
@@ -136,22 +146,18 @@ fn parse_tlv_response(data: &[u8], source_ip: &str) -> Option<DiscoveredDevice>
         let field_data = &data[pos..pos + field_len];
 
         match field_type {
-            TLV_MAC_ADDRESS => {
-                if field_len == 6 {
-                    mac = field_data
-                        .iter()
-                        .map(|b| format!("{:02X}", b))
-                        .collect::<Vec<_>>()
-                        .join(":");
-                }
+            TLV_MAC_ADDRESS if field_len == 6 => {
+                mac = field_data
+                    .iter()
+                    .map(|b| format!("{:02X}", b))
+                    .collect::<Vec<_>>()
+                    .join(":");
             }
-            TLV_IP_INFO => {
-                if field_len >= 4 {
-                    reported_ip = format!(
-                        "{}.{}.{}.{}",
-                        field_data[0], field_data[1], field_data[2], field_data[3]
-                    );
-                }
+            TLV_IP_INFO if field_len >= 4 => {
+                reported_ip = format!(
+                    "{}.{}.{}.{}",
+                    field_data[0], field_data[1], field_data[2], field_data[3]
+                );
             }
             TLV_FIRMWARE => {
                 firmware = String::from_utf8_lossy(field_data).to_string();
@@ -162,6 +168,12 @@ fn parse_tlv_response(data: &[u8], source_ip: &str) -> Option<DiscoveredDevice>
             TLV_PLATFORM => {
                 hostname = String::from_utf8_lossy(field_data).to_string();
             }
+            TLV_DEFAULT => {
+                is_default = field_data.iter().any(|&b| b != 0);
+            }
+            TLV_LOCATING => {
+                is_locating = field_data.iter().any(|&b| b != 0);
+            }
             0x06 => {
                 // Managed status / essid — presence suggests managed
                 is_managed = true;
@@ -186,5 +198,28 @@ fn parse_tlv_response(data: &[u8], source_ip: &str) -> Option<DiscoveredDevice>
         firmware,
         hostname,
         is_managed,
+        is_default,
+        is_locating,
     })
 }
+
+/// `FC:EC:DA:11:22:33`, the form discovery reports, from any common
+/// spelling of a MAC: any case, with colons, dashes, dots or nothing.
+pub fn normalize_mac(mac: &str) -> String {
+    let hex: Vec<char> = mac
+        .chars()
+        .filter(char::is_ascii_hexdigit)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    hex.chunks(2)
+        .map(|pair| pair.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Whether two MACs are the same regardless of case and separators, so
+/// `FC:EC:DA:11:22:33`, `fc-ec-da-11-22-33` and `fcecda112233` all match.
+pub fn same_mac(a: &str, b: &str) -> bool {
+    let a = normalize_mac(a);
+    a.len() == 17 && a == normalize_mac(b)
+}
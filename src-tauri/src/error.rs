@@ -0,0 +1,268 @@
+/// Error type returned by every Tauri command.
+///
+/// Module-level errors (`ApiError`, both `SshError` enums, discovery
+/// failures) are converted into an `AppError` at the command boundary.
+/// It serializes to `{ code, message, hint, retryable, retryAfterSecs }` so
+/// the frontend can branch on a stable `code` instead of matching on
+/// message text.
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::{api, bundle, controller, recovery, ssh, ssh_process};
+
+#[derive(Debug, Clone)]
+pub enum AppError {
+    InvalidCode(String),
+    CodeExpired(String),
+    InvalidBundle(String),
+    NetworkUnavailable(String),
+    RateLimited {
+        message: String,
+        /// From the API's `Retry-After` header, when it sent one
+        retry_after_secs: Option<u64>,
+    },
+    ServerError(String),
+    Api(String),
+    AuthFailed(String),
+    ConnectionRefused(String),
+    ConnectionTimeout(String),
+    CommandFailed(String),
+    CommandTimeout(String),
+    PreflightFailed(String),
+    AdoptionStalled(String),
+    ControllerAuthFailed(String),
+    Controller(String),
+    DiscoveryFailed(String),
+    InvalidInput(String),
+    Internal(String),
+}
+
+impl AppError {
+    /// Stable machine-readable identifier. Never change an existing value —
+    /// the frontend branches on these.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::InvalidCode(_) => "invalid_code",
+            AppError::CodeExpired(_) => "code_expired",
+            AppError::InvalidBundle(_) => "invalid_bundle",
+            AppError::NetworkUnavailable(_) => "network_unavailable",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::ServerError(_) => "server_error",
+            AppError::Api(_) => "api_error",
+            AppError::AuthFailed(_) => "auth_failed",
+            AppError::ConnectionRefused(_) => "connection_refused",
+            AppError::ConnectionTimeout(_) => "connection_timeout",
+            AppError::CommandFailed(_) => "command_failed",
+            AppError::CommandTimeout(_) => "command_timeout",
+            AppError::PreflightFailed(_) => "preflight_failed",
+            AppError::AdoptionStalled(_) => "adoption_stalled",
+            AppError::ControllerAuthFailed(_) => "controller_auth_failed",
+            AppError::Controller(_) => "controller_error",
+            AppError::DiscoveryFailed(_) => "discovery_failed",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    /// Human-readable description of what went wrong.
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::InvalidCode(msg)
+            | AppError::CodeExpired(msg)
+            | AppError::InvalidBundle(msg)
+            | AppError::NetworkUnavailable(msg)
+            | AppError::RateLimited { message: msg, .. }
+            | AppError::ServerError(msg)
+            | AppError::Api(msg)
+            | AppError::AuthFailed(msg)
+            | AppError::ConnectionRefused(msg)
+            | AppError::ConnectionTimeout(msg)
+            | AppError::CommandFailed(msg)
+            | AppError::CommandTimeout(msg)
+            | AppError::PreflightFailed(msg)
+            | AppError::AdoptionStalled(msg)
+            | AppError::ControllerAuthFailed(msg)
+            | AppError::Controller(msg)
+            | AppError::DiscoveryFailed(msg)
+            | AppError::InvalidInput(msg)
+            | AppError::Internal(msg) => msg,
+        }
+    }
+
+    /// What the technician can do about it.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            AppError::InvalidCode(_) => {
+                "Double-check the code shown in the VivaSpot setup wizard."
+            }
+            AppError::CodeExpired(_) => {
+                "Generate a new setup code in the VivaSpot setup wizard."
+            }
+            AppError::InvalidBundle(_) => {
+                "Export a fresh setup bundle from the VivaSpot setup wizard."
+            }
+            AppError::NetworkUnavailable(_) => {
+                "Check this computer's internet connection and try again."
+            }
+            AppError::RateLimited { .. } => {
+                "VivaSpot is handling a lot of requests. Wait a minute and try again."
+            }
+            AppError::ServerError(_) => {
+                "VivaSpot is having trouble right now. Try again in a few minutes."
+            }
+            AppError::Api(_) => "VivaSpot had a problem handling the request. Try again shortly.",
+            AppError::AuthFailed(_) => {
+                "Factory reset the access point or enter its current SSH password."
+            }
+            AppError::ConnectionRefused(_) => {
+                "Wait for the access point to finish booting, then try again."
+            }
+            AppError::ConnectionTimeout(_) => {
+                "Check the access point is powered on and on the same network as this computer."
+            }
+            AppError::CommandFailed(_) => {
+                "The access point rejected the command. Export diagnostics and contact support."
+            }
+            AppError::CommandTimeout(_) => {
+                "The access point may be busy or rebooting. Wait a minute and try again."
+            }
+            AppError::PreflightFailed(_) => {
+                "Check DNS and firewall rules for the inform URL, or adopt anyway if you know the AP can reach it."
+            }
+            AppError::AdoptionStalled(_) => {
+                "Check the device shows as pending in the controller and adopt it there, then try again."
+            }
+            AppError::ControllerAuthFailed(_) => {
+                "Sign in with a local account on the UniFi controller, not a UI.com account."
+            }
+            AppError::Controller(_) => {
+                "Check the controller address and that the UniFi Network application is running."
+            }
+            AppError::DiscoveryFailed(_) => {
+                "Check this computer is connected to the same network as the access points."
+            }
+            AppError::InvalidInput(_) => "Check the value you entered and try again.",
+            AppError::Internal(_) => {
+                "Try again. If it keeps happening, export diagnostics and contact support."
+            }
+        }
+    }
+
+    /// Whether repeating the same request unchanged might succeed.
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            AppError::NetworkUnavailable(_)
+                | AppError::RateLimited { .. }
+                | AppError::ServerError(_)
+                | AppError::Api(_)
+                | AppError::ConnectionRefused(_)
+                | AppError::ConnectionTimeout(_)
+                | AppError::CommandTimeout(_)
+                | AppError::AdoptionStalled(_)
+                | AppError::Controller(_)
+                | AppError::DiscoveryFailed(_)
+        )
+    }
+
+    /// How long the server asked us to wait before retrying, if it said.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            AppError::RateLimited {
+                retry_after_secs, ..
+            } => *retry_after_secs,
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("AppError", 5)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", self.message())?;
+        s.serialize_field("hint", self.hint())?;
+        s.serialize_field("retryable", &self.retryable())?;
+        s.serialize_field("retryAfterSecs", &self.retry_after_secs())?;
+        s.end()
+    }
+}
+
+impl From<api::ApiError> for AppError {
+    fn from(e: api::ApiError) -> Self {
+        match e {
+            api::ApiError::InvalidCode(msg) => AppError::InvalidCode(msg),
+            api::ApiError::ExpiredCode(msg) => AppError::CodeExpired(msg),
+            api::ApiError::NetworkError(msg) => AppError::NetworkUnavailable(msg),
+            api::ApiError::RateLimited { retry_after, .. } => AppError::RateLimited {
+                retry_after_secs: retry_after.map(|wait| wait.as_secs()),
+                message: e.to_string(),
+            },
+            e @ api::ApiError::ServerError { .. } => AppError::ServerError(e.to_string()),
+            api::ApiError::Other(msg) => AppError::Api(msg),
+        }
+    }
+}
+
+impl From<ssh::SshError> for AppError {
+    fn from(e: ssh::SshError) -> Self {
+        match e {
+            ssh::SshError::ConnectionRefused(msg) => AppError::ConnectionRefused(msg),
+            ssh::SshError::ConnectionTimeout(msg) => AppError::ConnectionTimeout(msg),
+            ssh::SshError::AuthFailed(msg) => AppError::AuthFailed(msg),
+            ssh::SshError::CommandFailed(msg) => AppError::CommandFailed(msg),
+            ssh::SshError::CommandTimeout(msg) => AppError::CommandTimeout(msg),
+            ssh::SshError::Other(msg) => AppError::Internal(msg),
+        }
+    }
+}
+
+impl From<ssh_process::SshError> for AppError {
+    fn from(e: ssh_process::SshError) -> Self {
+        match e {
+            ssh_process::SshError::ConnectionRefused(msg) => AppError::ConnectionRefused(msg),
+            ssh_process::SshError::ConnectionTimeout(msg) => AppError::ConnectionTimeout(msg),
+            ssh_process::SshError::AuthFailed(msg) => AppError::AuthFailed(msg),
+            ssh_process::SshError::CommandFailed(msg) => AppError::CommandFailed(msg),
+            ssh_process::SshError::CommandTimeout(msg) => AppError::CommandTimeout(msg),
+            ssh_process::SshError::Other(msg) => AppError::Internal(msg),
+        }
+    }
+}
+
+impl From<controller::ControllerError> for AppError {
+    fn from(e: controller::ControllerError) -> Self {
+        match e {
+            controller::ControllerError::AuthFailed(_) => {
+                AppError::ControllerAuthFailed(e.to_string())
+            }
+            _ => AppError::Controller(e.to_string()),
+        }
+    }
+}
+
+impl From<bundle::BundleError> for AppError {
+    fn from(e: bundle::BundleError) -> Self {
+        match e {
+            bundle::BundleError::Expired(_) => AppError::CodeExpired(e.to_string()),
+            _ => AppError::InvalidBundle(e.to_string()),
+        }
+    }
+}
+
+impl From<recovery::TftpError> for AppError {
+    fn from(e: recovery::TftpError) -> Self {
+        match e {
+            recovery::TftpError::Timeout(_) => AppError::ConnectionTimeout(e.to_string()),
+            recovery::TftpError::Io(_) => AppError::NetworkUnavailable(e.to_string()),
+            recovery::TftpError::TooLarge(_) => AppError::InvalidInput(e.to_string()),
+            _ => AppError::CommandFailed(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+/// Firmware upgrades served from the laptop.
+///
+/// The AP's `upgrade <url>` command downloads an image over HTTP, flashes
+/// it and reboots. Sites being installed often have no internet yet, so
+/// the image is served from a small HTTP server on the laptop instead of
+/// Ubiquiti's CDN. The download is watched through the server, and the
+/// result is verified by waiting for the device to reappear in discovery
+/// with the new version.
+///
+/// Firmware file names carry the version, e.g.
+/// `BZ.qca956x.v6.5.28.14491.230126.0034.bin`, and discovery reports the
+/// firmware string without the extension, so the two can be compared.
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::local_http::{self, HttpResponse};
+use crate::ssh::background_after;
+
+/// Smallest file accepted as a firmware image; anything less is a mistake.
+const MIN_IMAGE_BYTES: usize = 1024 * 1024;
+const MAX_IMAGE_BYTES: usize = 256 * 1024 * 1024;
+
+/// How long the AP gets to start downloading the image.
+pub const DOWNLOAD_TIMEOUT_SECS: u64 = 120;
+/// Flashing and rebooting can take several minutes on older APs.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 600;
+pub const POLL_INTERVAL_SECS: u64 = 5;
+/// Where the upgrade's output goes on the AP, for diagnosing failures.
+pub const LOG_PATH: &str = "/tmp/vivaspot-upgrade.log";
+/// How long to wait for the AP to close the connection after the last
+/// byte of the image, before giving up on counting the download.
+const CLOSE_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct FirmwareImage {
+    /// File name as served, e.g. `BZ.qca956x.v6.5.28.14491.230126.0034.bin`
+    pub name: String,
+    /// Version from the file name, e.g. `6.5.28.14491`
+    pub version: Option<String>,
+    pub bytes: Arc<Vec<u8>>,
+}
+
+impl FirmwareImage {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| format!("{} isn't a file", path.display()))?;
+        if !file_name.to_lowercase().ends_with(".bin") {
+            return Err(format!("{} isn't a .bin firmware image", file_name));
+        }
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(file_name, bytes)
+    }
+
+    pub fn from_bytes(file_name: &str, bytes: Vec<u8>) -> Result<Self, String> {
+        if bytes.len() < MIN_IMAGE_BYTES || bytes.len() > MAX_IMAGE_BYTES {
+            return Err(format!(
+                "{} is {} bytes, which isn't a firmware image",
+                file_name,
+                bytes.len()
+            ));
+        }
+        // Keep the URL path free of anything that needs escaping
+        let name: String = file_name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        Ok(FirmwareImage {
+            version: version_from_name(&name),
+            name,
+            bytes: Arc::new(bytes),
+        })
+    }
+}
+
+/// `6.5.28.14491` from `BZ.qca956x.v6.5.28.14491.230126.0034.bin` or from
+/// the discovery string `BZ.qca956x.v6.5.28.14491.230126.0034`.
+pub fn version_from_name(name: &str) -> Option<String> {
+    let parts: Vec<&str> = name.split('.').collect();
+    let start = parts.iter().position(|p| {
+        p.strip_prefix('v')
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    })?;
+    let version: Vec<&str> = std::iter::once(&parts[start][1..])
+        .chain(
+            parts[start + 1..]
+                .iter()
+                .copied()
+                .take_while(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())),
+        )
+        .take(4)
+        .collect();
+    (version.len() >= 3).then(|| version.join("."))
+}
+
+/// Whether a discovered firmware string is the expected version.
+pub fn is_version(firmware: &str, expected: &str) -> bool {
+    version_from_name(firmware).as_deref() == Some(expected) || firmware.trim() == expected
+}
+
+/// Shell command that starts the upgrade in the background, so the SSH
+/// session can close before the AP reboots. Output goes to [`LOG_PATH`].
+pub fn upgrade_command(url: &str) -> String {
+    let upgrade = format!(
+        "upgrade '{}' >{} 2>&1",
+        url.replace('\'', "'\\''"),
+        LOG_PATH
+    );
+    format!("{} echo VS_UPGRADE=started", background_after(0, &upgrade))
+}
+
+/// Upgrade progress, reported to the UI as `firmware-progress` events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpgradeStage {
+    /// Image is being served, waiting for the AP to fetch it
+    Serving,
+    /// AP has fetched the image and is flashing and rebooting
+    Flashing,
+    /// AP is back with the new firmware
+    Upgraded,
+}
+
+/// What the server has seen of the image being fetched.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadStatus {
+    /// Times the image has been requested
+    pub requests: u32,
+    /// Downloads the AP read to the end
+    pub completed: u32,
+    pub last_client: Option<String>,
+}
+
+/// Serves a single firmware image at `/<name>`.
+pub struct FirmwareServer {
+    addr: SocketAddr,
+    image: FirmwareImage,
+    status: Arc<Mutex<DownloadStatus>>,
+    task: JoinHandle<()>,
+}
+
+impl FirmwareServer {
+    /// Listen on `addr`, e.g. `0.0.0.0:0` for any free port.
+    pub async fn start(addr: &str, image: FirmwareImage) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let status = Arc::new(Mutex::new(DownloadStatus::default()));
+
+        let path = format!("/{}", image.name);
+        let task = tokio::spawn(serve(listener, path, image.bytes.clone(), status.clone()));
+
+        log::info!("Serving firmware {} on {}", image.name, addr);
+        Ok(FirmwareServer {
+            addr,
+            image,
+            status,
+            task,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn image(&self) -> &FirmwareImage {
+        &self.image
+    }
+
+    /// URL the AP should download from, using `host` as seen from the AP.
+    pub fn url(&self, host: IpAddr) -> String {
+        format!(
+            "http://{}/{}",
+            SocketAddr::new(host, self.addr.port()),
+            self.image.name
+        )
+    }
+
+    pub fn status(&self) -> DownloadStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+impl Drop for FirmwareServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Accept downloads until the server is dropped. The image is written
+/// straight from the shared buffer rather than copied into each response.
+async fn serve(
+    listener: TcpListener,
+    path: String,
+    bytes: Arc<Vec<u8>>,
+    status: Arc<Mutex<DownloadStatus>>,
+) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("Firmware server accept failed: {}", e);
+                continue;
+            }
+        };
+        let path = path.clone();
+        let bytes = bytes.clone();
+        let status = status.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_image(stream, peer, &path, &bytes, &status).await {
+                log::warn!("Firmware download by {} failed: {}", peer.ip(), e);
+            }
+        });
+    }
+}
+
+/// Answer one request. A download only counts as complete once the client
+/// has read the whole image and closed the connection, so the AP is known
+/// to have it before flashing is reported.
+async fn serve_image(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    path: &str,
+    bytes: &[u8],
+    status: &Mutex<DownloadStatus>,
+) -> std::io::Result<()> {
+    let Some(request) = local_http::read_request(&mut stream, peer).await? else {
+        return Ok(());
+    };
+    if request.method != "GET" || request.path != path {
+        return HttpResponse::text(404, "Not Found")
+            .write_to(&mut stream)
+            .await;
+    }
+    {
+        let mut status = status.lock().unwrap();
+        status.requests += 1;
+        status.last_client = Some(peer.ip().to_string());
+    }
+    log::info!("{} is downloading {}", peer.ip(), path);
+
+    HttpResponse::new(200, "application/octet-stream", Vec::new())
+        .write_head(&mut stream, bytes.len())
+        .await?;
+    stream.write_all(bytes).await?;
+    stream.shutdown().await?;
+    // The client closes its end once it has read Content-Length bytes
+    let mut rest = [0u8; 512];
+    tokio::time::timeout(Duration::from_secs(CLOSE_TIMEOUT_SECS), async {
+        while stream.read(&mut rest).await? > 0 {}
+        Ok::<_, std::io::Error>(())
+    })
+    .await
+    .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+    status.lock().unwrap().completed += 1;
+    log::info!("{} finished downloading {}", peer.ip(), path);
+    Ok(())
+}
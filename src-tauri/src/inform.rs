@@ -0,0 +1,592 @@
+/// Local UniFi inform listener.
+///
+/// After `set-inform` the only proof that a device will reach its controller
+/// is an inform actually arriving. Pointing the device at this listener
+/// first shows that it informs at all, and what it reports, before it's
+/// re-pointed at the real controller.
+///
+/// An inform is an HTTP POST of a binary `TNBU` packet:
+///
+/// ```text
+/// offset  size  field
+///      0     4  magic "TNBU"
+///      4     4  packet version (u32 BE, 0)
+///      8     6  device MAC
+///     14     2  flags (u16 BE)
+///     16    16  AES IV (GCM: nonce)
+///     32     4  payload version (u32 BE, 1)
+///     36     4  payload length (u32 BE)
+///     40     …  payload
+/// ```
+///
+/// Unadopted devices encrypt with the factory-default authkey, so the
+/// payload can be read without any controller secrets. Adopted devices use
+/// a per-device key; their informs are still recorded, just not decoded.
+///
+/// In relay mode the listener also forwards each inform to the site's real
+/// inform URL and returns the controller's reply, so an AP that can reach
+/// the laptop but not the internet can still be adopted.
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use aes_gcm::aead::consts::U16;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::aes::Aes128;
+use aes_gcm::AesGcm;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::api::http_client_builder;
+use crate::local_http::{self, HttpRequest, HttpResponse};
+use crate::settings::NetworkSettings;
+
+/// MD5 of "ubnt", used by every device until a controller adopts it.
+pub const DEFAULT_AUTH_KEY: [u8; 16] = [
+    0xba, 0x86, 0xf2, 0xbb, 0xe1, 0x07, 0xc7, 0xc5, 0x7e, 0xb5, 0xf2, 0x69, 0x07, 0x75, 0xc7, 0x12,
+];
+
+/// Port UniFi controllers listen for informs on.
+pub const DEFAULT_PORT: u16 = 8080;
+
+const MAGIC: &[u8; 4] = b"TNBU";
+const HEADER_LEN: usize = 40;
+const PACKET_VERSION: u32 = 0;
+const PAYLOAD_VERSION: u32 = 1;
+/// Interval asked of the device in the noop reply
+const NOOP_INTERVAL_SECS: u64 = 10;
+/// Devices give up on an inform after about 30 seconds
+const RELAY_TIMEOUT_SECS: u64 = 20;
+
+pub const FLAG_ENCRYPTED: u16 = 0x01;
+pub const FLAG_ZLIB: u16 = 0x02;
+pub const FLAG_SNAPPY: u16 = 0x04;
+pub const FLAG_GCM: u16 = 0x08;
+
+type Aes128CbcDec = cbc::Decryptor<Aes128>;
+type Aes128CbcEnc = cbc::Encryptor<Aes128>;
+/// UniFi uses the whole 16-byte IV field as the GCM nonce
+type Aes128Gcm16 = AesGcm<Aes128, U16>;
+
+#[derive(Debug)]
+pub enum InformError {
+    Malformed(String),
+    Unsupported(String),
+    /// Wrong key, usually because the device is adopted elsewhere
+    Decrypt(String),
+}
+
+impl std::fmt::Display for InformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InformError::Malformed(msg) => write!(f, "Malformed inform packet: {}", msg),
+            InformError::Unsupported(msg) => write!(f, "Unsupported inform packet: {}", msg),
+            InformError::Decrypt(msg) => write!(f, "Couldn't decrypt inform: {}", msg),
+        }
+    }
+}
+
+/// A parsed `TNBU` packet. `payload` is still encrypted and compressed.
+#[derive(Debug, Clone)]
+pub struct InformPacket {
+    pub version: u32,
+    pub mac: [u8; 6],
+    pub flags: u16,
+    pub iv: [u8; 16],
+    pub payload_version: u32,
+    pub payload: Vec<u8>,
+}
+
+impl InformPacket {
+    pub fn parse(bytes: &[u8]) -> Result<Self, InformError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(InformError::Malformed(format!(
+                "{} bytes, header alone is {}",
+                bytes.len(),
+                HEADER_LEN
+            )));
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(InformError::Malformed("missing TNBU magic".to_string()));
+        }
+        let u32_at = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        let length = u32_at(36) as usize;
+        let payload = &bytes[HEADER_LEN..];
+        if payload.len() < length {
+            return Err(InformError::Malformed(format!(
+                "payload is {} bytes, header says {}",
+                payload.len(),
+                length
+            )));
+        }
+
+        Ok(InformPacket {
+            version: u32_at(4),
+            mac: bytes[8..14].try_into().unwrap(),
+            flags: u16::from_be_bytes([bytes[14], bytes[15]]),
+            iv: bytes[16..32].try_into().unwrap(),
+            payload_version: u32_at(32),
+            payload: payload[..length].to_vec(),
+        })
+    }
+
+    /// Build a packet carrying `plaintext`, encrypted and compressed as
+    /// `flags` say. Used for replies, and by tests to fake a device.
+    pub fn encode(
+        mac: [u8; 6],
+        flags: u16,
+        iv: [u8; 16],
+        plaintext: &[u8],
+        key: &[u8; 16],
+    ) -> Result<Vec<u8>, InformError> {
+        if flags & FLAG_SNAPPY != 0 {
+            return Err(InformError::Unsupported("snappy compression".to_string()));
+        }
+        let data = if flags & FLAG_ZLIB != 0 {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder
+                .write_all(plaintext)
+                .and_then(|_| encoder.finish())
+                .map_err(|e| InformError::Malformed(e.to_string()))?
+        } else {
+            plaintext.to_vec()
+        };
+
+        let mut packet = InformPacket {
+            version: PACKET_VERSION,
+            mac,
+            flags,
+            iv,
+            payload_version: PAYLOAD_VERSION,
+            payload: Vec::new(),
+        };
+        packet.payload = if flags & FLAG_ENCRYPTED == 0 {
+            data
+        } else if flags & FLAG_GCM != 0 {
+            // The header is authenticated too, so its length field must
+            // already cover the 16-byte tag
+            let aad = packet.header(data.len() + 16);
+            Aes128Gcm16::new(key.into())
+                .encrypt(
+                    &iv.into(),
+                    Payload {
+                        msg: &data,
+                        aad: &aad,
+                    },
+                )
+                .map_err(|_| InformError::Decrypt("GCM encryption failed".to_string()))?
+        } else {
+            Aes128CbcEnc::new(key.into(), &iv.into()).encrypt_padded_vec_mut::<Pkcs7>(&data)
+        };
+
+        let mut bytes = packet.header(packet.payload.len()).to_vec();
+        bytes.extend_from_slice(&packet.payload);
+        Ok(bytes)
+    }
+
+    fn header(&self, payload_len: usize) -> [u8; HEADER_LEN] {
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4..8].copy_from_slice(&self.version.to_be_bytes());
+        header[8..14].copy_from_slice(&self.mac);
+        header[14..16].copy_from_slice(&self.flags.to_be_bytes());
+        header[16..32].copy_from_slice(&self.iv);
+        header[32..36].copy_from_slice(&self.payload_version.to_be_bytes());
+        header[36..40].copy_from_slice(&(payload_len as u32).to_be_bytes());
+        header
+    }
+
+    /// MAC as `fc:ec:da:11:22:33`.
+    pub fn mac_string(&self) -> String {
+        self.mac
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    pub fn is_gcm(&self) -> bool {
+        self.flags & FLAG_GCM != 0
+    }
+
+    /// Decrypt and decompress the payload.
+    pub fn decrypt(&self, key: &[u8; 16]) -> Result<Vec<u8>, InformError> {
+        let data = if !self.is_encrypted() {
+            self.payload.clone()
+        } else if self.is_gcm() {
+            let aad = self.header(self.payload.len());
+            Aes128Gcm16::new(key.into())
+                .decrypt(
+                    &self.iv.into(),
+                    Payload {
+                        msg: &self.payload,
+                        aad: &aad,
+                    },
+                )
+                .map_err(|_| InformError::Decrypt("GCM tag mismatch".to_string()))?
+        } else {
+            Aes128CbcDec::new(key.into(), &self.iv.into())
+                .decrypt_padded_vec_mut::<Pkcs7>(&self.payload)
+                .map_err(|_| InformError::Decrypt("bad CBC padding".to_string()))?
+        };
+
+        if self.flags & FLAG_SNAPPY != 0 {
+            return Err(InformError::Unsupported("snappy compression".to_string()));
+        }
+        if self.flags & FLAG_ZLIB == 0 {
+            return Ok(data);
+        }
+        let mut decompressed = Vec::new();
+        flate2::read::ZlibDecoder::new(data.as_slice())
+            .read_to_end(&mut decompressed)
+            .map_err(|e| InformError::Decrypt(format!("payload didn't decompress ({})", e)))?;
+        Ok(decompressed)
+    }
+
+    /// Decrypt the payload and parse it as the device's JSON state.
+    pub fn decode_json(&self, key: &[u8; 16]) -> Result<serde_json::Value, InformError> {
+        let plaintext = self.decrypt(key)?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| InformError::Decrypt(format!("payload isn't JSON ({})", e)))
+    }
+}
+
+/// What the listener has heard from one device.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InformRecord {
+    pub mac: String,
+    /// Address the inform came from
+    pub source_ip: String,
+    pub model: Option<String>,
+    pub version: Option<String>,
+    pub hostname: Option<String>,
+    /// Inform URL the device is currently configured with
+    pub inform_url: Option<String>,
+    /// Device still on factory settings (`default: true`)
+    pub is_default: Option<bool>,
+    pub flags: u16,
+    pub inform_count: u32,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Why the payload couldn't be read, if it couldn't. Expected in relay
+    /// mode once the controller has given the device its own key.
+    pub error: Option<String>,
+    /// Forwarding results, in relay mode
+    pub relay: Option<RelayStatus>,
+}
+
+/// How forwarding a device's informs to the real controller is going.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayStatus {
+    pub forwarded: u32,
+    pub failed: u32,
+    /// HTTP status of the controller's last answer
+    pub last_status: Option<u16>,
+    pub last_error: Option<String>,
+}
+
+/// Listener state shared with the HTTP handler.
+#[derive(Default)]
+struct ListenerState {
+    records: HashMap<String, InformRecord>,
+}
+
+/// Forwards informs to the controller the device should really talk to.
+struct Relay {
+    http: reqwest::Client,
+    upstream: String,
+}
+
+pub struct InformListener {
+    addr: SocketAddr,
+    key: [u8; 16],
+    upstream: Option<String>,
+    state: Arc<Mutex<ListenerState>>,
+    task: JoinHandle<()>,
+}
+
+impl InformListener {
+    /// Listen on `addr` (e.g. `0.0.0.0:8080`), decoding with the factory key.
+    pub async fn start(addr: &str) -> std::io::Result<Self> {
+        Self::spawn(addr, DEFAULT_AUTH_KEY, None, None).await
+    }
+
+    pub async fn start_with_key(addr: &str, key: [u8; 16]) -> std::io::Result<Self> {
+        Self::spawn(addr, key, None, None).await
+    }
+
+    /// Listen on `addr` and forward every inform to `upstream`, the site's
+    /// real inform URL, passing the controller's replies back unchanged.
+    /// For APs that can reach this computer but not the controller yet.
+    /// The forwarding client uses the same proxy and CA settings as the API.
+    pub async fn start_relay(
+        addr: &str,
+        upstream: &str,
+        network: &NetworkSettings,
+    ) -> std::io::Result<Self> {
+        let http = http_client_builder(network)
+            .map_err(|e| std::io::Error::other(e.to_string()))?
+            .timeout(Duration::from_secs(RELAY_TIMEOUT_SECS))
+            .build()
+            .map_err(std::io::Error::other)?;
+        Self::spawn(
+            addr,
+            DEFAULT_AUTH_KEY,
+            Some(upstream.to_string()),
+            Some(http),
+        )
+        .await
+    }
+
+    async fn spawn(
+        addr: &str,
+        key: [u8; 16],
+        upstream: Option<String>,
+        http: Option<reqwest::Client>,
+    ) -> std::io::Result<Self> {
+        let relay = match (&upstream, http) {
+            (Some(upstream), Some(http)) => Some(Arc::new(Relay {
+                http,
+                upstream: upstream.clone(),
+            })),
+            _ => None,
+        };
+
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(ListenerState::default()));
+
+        let handler_state = state.clone();
+        let task = tokio::spawn(local_http::serve(listener, move |request: HttpRequest| {
+            let state = handler_state.clone();
+            let relay = relay.clone();
+            async move { handle_inform(&state, &key, relay.as_deref(), request).await }
+        }));
+
+        match &upstream {
+            Some(upstream) => log::info!("Inform relay on {} forwarding to {}", addr, upstream),
+            None => log::info!("Inform listener on {}", addr),
+        }
+        Ok(InformListener {
+            addr,
+            key,
+            upstream,
+            state,
+            task,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Inform URL devices should be given, using `host` as seen from them.
+    pub fn inform_url(&self, host: IpAddr) -> String {
+        format!("http://{}/inform", SocketAddr::new(host, self.addr.port()))
+    }
+
+    pub fn auth_key(&self) -> [u8; 16] {
+        self.key
+    }
+
+    /// The real inform URL, in relay mode.
+    pub fn upstream(&self) -> Option<&str> {
+        self.upstream.as_deref()
+    }
+
+    /// Every device heard from, most recent first.
+    pub fn records(&self) -> Vec<InformRecord> {
+        let mut records: Vec<InformRecord> = self
+            .state
+            .lock()
+            .unwrap()
+            .records
+            .values()
+            .cloned()
+            .collect();
+        records.sort_by_key(|r| std::cmp::Reverse(r.last_seen));
+        records
+    }
+
+    /// Stop listening and wait until the port is released.
+    pub async fn stop(mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+    }
+
+    pub fn record(&self, mac: &str) -> Option<InformRecord> {
+        self.state
+            .lock()
+            .unwrap()
+            .records
+            .get(&mac.to_lowercase())
+            .cloned()
+    }
+}
+
+impl Drop for InformListener {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle_inform(
+    state: &Mutex<ListenerState>,
+    key: &[u8; 16],
+    relay: Option<&Relay>,
+    request: HttpRequest,
+) -> HttpResponse {
+    if request.method != "POST" || request.path != "/inform" {
+        return HttpResponse::text(404, "Not Found");
+    }
+    let packet = match InformPacket::parse(&request.body) {
+        Ok(packet) => packet,
+        Err(e) => {
+            log::warn!("Inform from {}: {}", request.peer.ip(), e);
+            return HttpResponse::text(400, "Bad Request");
+        }
+    };
+
+    let mac = packet.mac_string();
+    let decoded = packet.decode_json(key);
+    let now = Utc::now();
+    {
+        let mut state = state.lock().unwrap();
+        let record = state
+            .records
+            .entry(mac.clone())
+            .or_insert_with(|| InformRecord {
+                mac: mac.clone(),
+                source_ip: request.peer.ip().to_string(),
+                model: None,
+                version: None,
+                hostname: None,
+                inform_url: None,
+                is_default: None,
+                flags: packet.flags,
+                inform_count: 0,
+                first_seen: now,
+                last_seen: now,
+                error: None,
+                relay: relay.map(|_| RelayStatus::default()),
+            });
+        record.source_ip = request.peer.ip().to_string();
+        record.flags = packet.flags;
+        record.inform_count += 1;
+        record.last_seen = now;
+        match &decoded {
+            Ok(body) => {
+                let text = |field: &str| body[field].as_str().map(str::to_string);
+                record.model = text("model_display").or_else(|| text("model"));
+                record.version = text("version");
+                record.hostname = text("hostname");
+                record.inform_url = text("inform_url");
+                record.is_default = body["default"].as_bool();
+                record.error = None;
+            }
+            Err(e) => record.error = Some(e.to_string()),
+        }
+    }
+
+    if let Some(relay) = relay {
+        let result = relay.forward(&request).await;
+        let mut state = state.lock().unwrap();
+        if let Some(status) = state.records.get_mut(&mac).and_then(|r| r.relay.as_mut()) {
+            match &result {
+                Ok(response) => {
+                    status.forwarded += 1;
+                    status.last_status = Some(response.status);
+                    status.last_error = None;
+                }
+                Err(e) => {
+                    status.failed += 1;
+                    status.last_error = Some(e.clone());
+                }
+            }
+        }
+        return result.unwrap_or_else(|e| {
+            log::warn!("Relaying inform from {} failed: {}", mac, e);
+            // The device retries on its own, as it would with an unreachable controller
+            HttpResponse::text(502, "Bad Gateway")
+        });
+    }
+
+    if let Err(e) = decoded {
+        log::info!("Inform from {} ({}): {}", mac, request.peer.ip(), e);
+        return HttpResponse::text(400, "Bad Request");
+    }
+    log::info!("Inform from {} ({})", mac, request.peer.ip());
+
+    // Keep the device informing at a steady pace without changing anything
+    let noop = json!({
+        "_type": "noop",
+        "interval": NOOP_INTERVAL_SECS,
+        "server_time_in_utc": now.timestamp_millis().to_string(),
+    });
+    match InformPacket::encode(
+        packet.mac,
+        packet.flags,
+        rand::random(),
+        noop.to_string().as_bytes(),
+        key,
+    ) {
+        Ok(reply) => HttpResponse::new(200, "application/x-binary", reply),
+        Err(e) => {
+            log::warn!("Failed to encode inform reply for {}: {}", mac, e);
+            HttpResponse::text(500, "Internal Server Error")
+        }
+    }
+}
+
+impl Relay {
+    /// POST the inform as-is and hand back the controller's answer.
+    async fn forward(&self, request: &HttpRequest) -> Result<HttpResponse, String> {
+        let mut forwarded = self
+            .http
+            .post(&self.upstream)
+            .header("Content-Type", "application/x-binary")
+            .body(request.body.clone());
+        if let Some(agent) = request.header("user-agent") {
+            forwarded = forwarded.header("User-Agent", agent);
+        }
+        let response = forwarded.send().await.map_err(|e| e.to_string())?;
+
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/x-binary")
+            .to_string();
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        Ok(HttpResponse::new(status, &content_type, body.to_vec()))
+    }
+}
+
+/// This machine's address on the route towards `device_ip`, i.e. the host
+/// to put in an inform URL pointing back at the listener. Connecting a UDP
+/// socket picks the outgoing interface without sending anything.
+pub fn local_ip_towards(device_ip: IpAddr) -> std::io::Result<IpAddr> {
+    let bind: SocketAddr = match device_ip {
+        IpAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+        IpAddr::V6(_) => "[::]:0".parse().unwrap(),
+    };
+    let socket = std::net::UdpSocket::bind(bind)?;
+    socket.connect(SocketAddr::new(device_ip, 10001))?;
+    Ok(socket.local_addr()?.ip())
+}
@@ -0,0 +1,96 @@
+/// Reconcile a site's expected device inventory with a discovery scan.
+///
+/// Expected devices are matched by MAC first. Entries provisioned by model
+/// only are then matched against the remaining scanned devices of that
+/// model. Whatever is left over is reported as missing (expected, not
+/// seen) or unexpected (seen, not planned), so installers know when a site
+/// is actually finished.
+use serde::Serialize;
+
+use crate::api::ExpectedDevice;
+use crate::discovery::{normalize_mac, DiscoveredDevice};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchedDevice {
+    pub expected: ExpectedDevice,
+    pub device: DiscoveredDevice,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reconciliation {
+    pub found: Vec<MatchedDevice>,
+    pub missing: Vec<ExpectedDevice>,
+    pub unexpected: Vec<DiscoveredDevice>,
+    /// True once every expected device has been seen
+    pub complete: bool,
+}
+
+pub fn reconcile(expected: &[ExpectedDevice], scanned: &[DiscoveredDevice]) -> Reconciliation {
+    let mut claimed = vec![false; scanned.len()];
+    let mut matches: Vec<Option<usize>> = vec![None; expected.len()];
+
+    // Pass 1: exact MAC matches
+    for (i, exp) in expected.iter().enumerate() {
+        let Some(mac) = exp.mac.as_deref().filter(|m| !m.is_empty()) else {
+            continue;
+        };
+        let mac = normalize_mac(mac);
+        if let Some(j) =
+            (0..scanned.len()).find(|&j| !claimed[j] && normalize_mac(&scanned[j].mac) == mac)
+        {
+            claimed[j] = true;
+            matches[i] = Some(j);
+        }
+    }
+
+    // Pass 2: model-only entries take the first unclaimed device of that model
+    for (i, exp) in expected.iter().enumerate() {
+        if exp.mac.as_deref().is_some_and(|m| !m.is_empty()) {
+            continue;
+        }
+        let Some(model) = exp.model.as_deref().filter(|m| !m.is_empty()) else {
+            continue;
+        };
+        if let Some(j) = (0..scanned.len())
+            .find(|&j| !claimed[j] && scanned[j].model.eq_ignore_ascii_case(model))
+        {
+            claimed[j] = true;
+            matches[i] = Some(j);
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for (exp, matched) in expected.iter().zip(&matches) {
+        match matched {
+            Some(j) => found.push(MatchedDevice {
+                expected: exp.clone(),
+                device: scanned[*j].clone(),
+            }),
+            None => missing.push(exp.clone()),
+        }
+    }
+
+    let unexpected: Vec<DiscoveredDevice> = scanned
+        .iter()
+        .zip(&claimed)
+        .filter(|(_, claimed)| !**claimed)
+        .map(|(d, _)| d.clone())
+        .collect();
+
+    log::info!(
+        "Inventory: {} found, {} missing, {} unexpected",
+        found.len(),
+        missing.len(),
+        unexpected.len()
+    );
+
+    Reconciliation {
+        complete: missing.is_empty(),
+        found,
+        missing,
+        unexpected,
+    }
+}
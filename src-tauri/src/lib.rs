@@ -1,13 +1,37 @@
-mod api;
-mod discovery;
+pub mod adoption;
+pub mod api;
+pub mod bundle;
+pub mod config_backup;
+pub mod controller;
+pub mod device_diagnostics;
+pub mod diagnostics;
+pub mod discovery;
+mod error;
+pub mod firmware;
+pub mod inform;
+pub mod inventory;
+pub mod local_http;
+pub mod locate;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock_api;
+pub mod preflight;
+pub mod recovery;
+pub mod reporting;
+pub mod reset;
+pub mod settings;
+pub mod setup_code;
+pub mod site_cache;
 mod ssh;
 mod ssh_process;
 
-use std::sync::Mutex;
 use serde::Serialize;
-use tauri::Manager;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
 use tauri_plugin_deep_link::DeepLinkExt;
 
+use error::AppError;
+
 // ============================================================
 // Tauri command return types
 // ============================================================
@@ -18,12 +42,84 @@ struct ValidateCodeResult {
     inform_url: String,
     site_id: String,
     site_name: String,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// True when served from the local cache rather than the API
+    cached: bool,
+}
+
+impl ValidateCodeResult {
+    fn new(site: api::SetupCodeResponse, cached: bool) -> Self {
+        ValidateCodeResult {
+            inform_url: site.inform_url,
+            site_id: site.site_id,
+            site_name: site.site_name,
+            expires_at: site.expires_at,
+            cached,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct ScanResult {
     devices: Vec<discovery::DiscoveredDevice>,
+    /// Present once the site's expected inventory has been loaded
+    inventory: Option<inventory::Reconciliation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ControllerSession {
+    kind: controller::ControllerKind,
+    site: String,
+    pending: Vec<controller::ControllerDevice>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InformListenerStatus {
+    running: bool,
+    port: Option<u16>,
+    /// URL to give a device so it informs this computer
+    inform_url: Option<String>,
+    /// Inform URL informs are forwarded to, in relay mode
+    relay_to: Option<String>,
+    devices: Vec<inform::InformRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeviceDiagnosticsResult {
+    /// Where the archive was written
+    path: String,
+    commands: Vec<device_diagnostics::CommandSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LocateResult {
+    enabled: bool,
+    method: locate::LocateMethod,
+    /// When the LED switches itself off again
+    off_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RestoreConfigResult {
+    restored: config_backup::BackupSummary,
+    /// Snapshot of the config that was replaced
+    previous: config_backup::BackupSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FactoryResetResult {
+    method: reset::ResetMethod,
+    /// The device as discovered after the reset; its IP may have changed
+    device: discovery::DiscoveredDevice,
+    /// From sending the reset until the device answered as default
+    elapsed_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -31,6 +127,87 @@ struct ScanResult {
 struct AdoptResult {
     success: bool,
     output: String,
+    /// Inform URL checks from the laptop and the AP; empty if skipped
+    preflight: Vec<preflight::PreflightReport>,
+    /// Each adoption step in order; also emitted as `adoption-phase` events
+    phases: Vec<adoption::AdoptionPhase>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FirmwareUpgradeResult {
+    previous_firmware: String,
+    /// The device as discovered after the upgrade
+    device: discovery::DiscoveredDevice,
+    elapsed_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FirmwareProgressEvent<'a> {
+    ip: &'a str,
+    stage: firmware::UpgradeStage,
+    detail: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SshOutputEvent<'a> {
+    job_id: &'a str,
+    ip: &'a str,
+    #[serde(flatten)]
+    chunk: ssh::OutputChunk,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TerminalOutputEvent<'a> {
+    session_id: &'a str,
+    /// Base64, since output can split multi-byte characters across chunks
+    data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TerminalClosedEvent<'a> {
+    session_id: &'a str,
+    exit_status: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryStatus {
+    ip: String,
+    /// A TFTP server answered at `ip`
+    in_recovery: bool,
+    /// This computer's address towards the device
+    local_ip: Option<String>,
+    /// Recovery mode has no gateway, so the laptop must be in its /24
+    same_subnet: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryResult {
+    transfer: recovery::TransferStats,
+    /// The device as discovered once it booted the new image
+    device: discovery::DiscoveredDevice,
+    elapsed_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryProgressEvent<'a> {
+    ip: &'a str,
+    sent_bytes: u64,
+    total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AdoptionPhaseEvent<'a> {
+    ip: &'a str,
+    phase: &'a adoption::AdoptionPhase,
 }
 
 // ============================================================
@@ -38,91 +215,1576 @@ struct AdoptResult {
 // ============================================================
 
 /// Validate a setup code against the VivaSpot API.
-/// Returns inform URL and site metadata.
+/// Returns inform URL and site metadata. If the API can't be reached, a
+/// code validated earlier is served from the site cache until it expires,
+/// so a restart or a dropped connection mid-job doesn't lose the site.
+/// Codes the API rejects are never served from the cache.
 #[tauri::command]
-async fn validate_code(code: String) -> Result<ValidateCodeResult, String> {
-    let result = api::validate_setup_code(&code)
-        .await
-        .map_err(|e| e.to_string())?;
+async fn validate_code(
+    code: String,
+    api_client: tauri::State<'_, ApiClientState>,
+    site_cache: tauri::State<'_, site_cache::SiteCache>,
+    site_inventory: tauri::State<'_, SiteInventoryState>,
+) -> Result<ValidateCodeResult, AppError> {
+    let client = api_client.0.lock().unwrap().clone();
+    let code = setup_code::normalize(&code)?;
+
+    let result = match client.validate_setup_code(&code).await {
+        Ok(result) => result,
+        Err(e @ (api::ApiError::InvalidCode(_) | api::ApiError::ExpiredCode(_))) => {
+            return Err(e.into())
+        }
+        Err(e) => {
+            let Some(entry) = site_cache.get(client.api_base(), &code) else {
+                return Err(e.into());
+            };
+            log::warn!(
+                "Couldn't validate {} ({}), using cached site {} (expires {})",
+                code,
+                e,
+                entry.site.site_name,
+                entry.expires_at
+            );
+            site_inventory.switch_site(&entry.site.site_id);
+            return Ok(ValidateCodeResult::new(entry.site, true));
+        }
+    };
+
+    site_inventory.switch_site(&result.site_id);
+    let entry = site_cache.insert(client.api_base(), &code, &result);
 
     Ok(ValidateCodeResult {
-        inform_url: result.inform_url,
-        site_id: result.site_id,
-        site_name: result.site_name,
+        expires_at: Some(entry.expires_at),
+        ..ValidateCodeResult::new(result, false)
     })
 }
 
+/// List setup codes whose site details are cached and still valid.
+#[tauri::command]
+fn list_cached_sites(
+    site_cache: tauri::State<'_, site_cache::SiteCache>,
+) -> Vec<site_cache::CachedSite> {
+    site_cache.list()
+}
+
+/// Forget one cached setup code, or all of them if `code` is omitted.
+/// Returns how many entries were removed.
+#[tauri::command]
+fn clear_cached_sites(
+    code: Option<String>,
+    site_cache: tauri::State<'_, site_cache::SiteCache>,
+) -> Result<usize, AppError> {
+    let code = code.as_deref().map(setup_code::normalize).transpose()?;
+    Ok(site_cache.clear(code.as_deref()))
+}
+
+/// Import an offline setup bundle from a `vivaspot://bundle` deep link, the
+/// JSON contents of a `.vivaspot` file (as read by the file picker) or a
+/// file path. Verified bundles yield the same result as `validate_code`.
+#[tauri::command]
+async fn import_setup_bundle(
+    source: String,
+    site_inventory: tauri::State<'_, SiteInventoryState>,
+) -> Result<ValidateCodeResult, AppError> {
+    let signed = if source.starts_with("vivaspot://") {
+        let url = tauri::Url::parse(&source)
+            .map_err(|e| AppError::InvalidBundle(format!("Invalid bundle link: {}", e)))?;
+        bundle::SignedBundle::from_deep_link(&url)?
+    } else if source.trim_start().starts_with('{') {
+        bundle::SignedBundle::from_json(&source)?
+    } else {
+        let text = tokio::fs::read_to_string(&source)
+            .await
+            .map_err(|e| AppError::InvalidInput(format!("Failed to read {}: {}", source, e)))?;
+        bundle::SignedBundle::from_json(&text)?
+    };
+
+    let result = signed.open()?;
+    site_inventory.switch_site(&result.site_id);
+
+    Ok(ValidateCodeResult::new(result, false))
+}
+
 /// Scan the local network for UniFi devices via UDP broadcast.
 #[tauri::command]
-async fn scan_devices() -> Result<ScanResult, String> {
+async fn scan_devices(
+    last_scan: tauri::State<'_, LastScan>,
+    site_inventory: tauri::State<'_, SiteInventoryState>,
+) -> Result<ScanResult, AppError> {
     // Run the blocking UDP scan on a separate thread
     let devices = tokio::task::spawn_blocking(discovery::scan_network)
         .await
-        .map_err(|e| format!("Scan task failed: {}", e))?
-        .map_err(|e| e)?;
+        .map_err(|e| AppError::Internal(format!("Scan task failed: {}", e)))?
+        .map_err(AppError::DiscoveryFailed)?;
 
-    Ok(ScanResult { devices })
+    // Remember the results so they can be included in a diagnostics export
+    *last_scan.0.lock().unwrap() = devices.clone();
+
+    let inventory = site_inventory
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|inv| inventory::reconcile(&inv.devices, &devices));
+
+    Ok(ScanResult { devices, inventory })
 }
 
-/// Execute set-inform on an AP via SSH.
-/// Uses the system ssh command (via ssh_process) for maximum compatibility
-/// with Dropbear SSH on UniFi APs. Falls back to russh library if that fails.
+/// Fetch the site's expected device inventory and reconcile it with the
+/// last scan. Subsequent scans include the reconciliation automatically.
 #[tauri::command]
+async fn load_site_inventory(
+    site_id: String,
+    api_client: tauri::State<'_, ApiClientState>,
+    last_scan: tauri::State<'_, LastScan>,
+    site_inventory: tauri::State<'_, SiteInventoryState>,
+) -> Result<inventory::Reconciliation, AppError> {
+    let client = api_client.0.lock().unwrap().clone();
+    let fetched = client.fetch_site_inventory(&site_id).await?;
+
+    let reconciliation = inventory::reconcile(&fetched.devices, &last_scan.0.lock().unwrap());
+    *site_inventory.0.lock().unwrap() = Some(fetched);
+
+    Ok(reconciliation)
+}
+
+/// Execute set-inform on an AP via SSH and report the outcome to VivaSpot.
+/// `context` carries the site and device details for the back-office report;
+/// older frontends that omit it simply skip reporting.
+///
+/// The inform URL is checked from this computer and from the AP itself
+/// first. Adoption stops if the AP can't reach the controller, unless
+/// `skip_preflight` is set; a failure from this computer alone is only a
+/// warning, since the laptop is often on a different network or proxy.
+///
+/// With `two_phase`, the AP's `info` status is watched after set-inform until
+/// the controller has accepted it, then set-inform is sent again — older
+/// firmware otherwise falls back to "Adopting" or "Disconnected".
+///
+/// The AP's config is backed up before set-inform. If that fails, adoption
+/// stops for a device that's managed or not on factory settings (as of the
+/// last scan), or when `require_backup` is set; a factory-default device has
+/// nothing worth keeping, so there it's only a warning.
+///
+/// Each step is emitted as an `adoption-phase` event as it completes; see
+/// [`adoption::AdoptionPhase`] for the names.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn adopt_device(
     ip: String,
     inform_url: String,
     custom_password: Option<String>,
-) -> Result<AdoptResult, String> {
-    let password_ref = custom_password.as_deref();
+    context: Option<reporting::AdoptionContext>,
+    skip_preflight: Option<bool>,
+    two_phase: Option<bool>,
+    require_backup: Option<bool>,
+    app: tauri::AppHandle,
+    api_client: tauri::State<'_, ApiClientState>,
+    last_scan: tauri::State<'_, LastScan>,
+) -> Result<AdoptResult, AppError> {
+    let password = custom_password.as_deref();
+    let mut preflight = Vec::new();
+    let mut phases = Vec::new();
+    let result = async {
+        if !skip_preflight.unwrap_or(false) {
+            let started = std::time::Instant::now();
+            let network = network_settings(&app);
+            let laptop = preflight::check_from_laptop(&inform_url, &network).await;
+            preflight.push(laptop.clone());
+            let device = check_from_device(&ip, &inform_url, password).await?;
+            preflight.push(device.clone());
+            if !device.passed {
+                return Err(AppError::PreflightFailed(device.summary()));
+            }
+            if !laptop.passed {
+                record_warning(
+                    &app,
+                    &ip,
+                    &mut phases,
+                    "preflight",
+                    laptop.summary(),
+                    started,
+                );
+            }
+        }
+
+        let started = std::time::Instant::now();
+        let result = backup_config(&app, &ip, password, "set-inform")
+            .await
+            .map(|backup| format!("Saved config backup {}", backup.id));
+        let factory_default = last_scan
+            .device_at(&ip)
+            .is_ok_and(|d| d.is_default && !d.is_managed);
+        match result {
+            Err(e) if factory_default && !require_backup.unwrap_or(false) => {
+                let detail = format!(
+                    "No config backup, the device is on factory settings: {}",
+                    e.message()
+                );
+                record_warning(&app, &ip, &mut phases, "backup-config", detail, started);
+            }
+            result => {
+                record_phase(&app, &ip, &mut phases, "backup-config", &result, started);
+                result?;
+            }
+        }
+
+        let started = std::time::Instant::now();
+        let result = set_inform_with_fallback(&ip, &inform_url, password).await;
+        record_phase(&app, &ip, &mut phases, "set-inform", &result, started);
+        let output = result?;
+        if !two_phase.unwrap_or(false) {
+            return Ok(output);
+        }
+
+        let started = std::time::Instant::now();
+        let result = wait_for_acceptance(&app, &ip, password)
+            .await
+            .map(|info| format!("Device is {}", info.status));
+        record_phase(
+            &app,
+            &ip,
+            &mut phases,
+            "wait-for-controller",
+            &result,
+            started,
+        );
+        result?;
+
+        let started = std::time::Instant::now();
+        let result = set_inform_with_fallback(&ip, &inform_url, password).await;
+        record_phase(&app, &ip, &mut phases, "set-inform-again", &result, started);
+        result.map(|second| format!("{}\n{}", output, second))
+    }
+    .await;
+
+    if let Some(context) = context {
+        let report = context.report(result.as_ref().err().map(AppError::code));
+        let client = api_client.0.lock().unwrap().clone();
+        // Report in the background so a slow or offline API never delays the UI
+        tauri::async_runtime::spawn(async move {
+            reporting::submit(&client, &app.state::<reporting::ReportQueue>(), report).await;
+        });
+    }
+
+    result.map(|output| AdoptResult {
+        success: true,
+        output,
+        preflight,
+        phases,
+    })
+}
+
+/// Record an adoption step and tell the UI about it straight away.
+fn record_phase(
+    app: &tauri::AppHandle,
+    ip: &str,
+    phases: &mut Vec<adoption::AdoptionPhase>,
+    name: &str,
+    result: &Result<String, AppError>,
+    started: std::time::Instant,
+) {
+    let phase = adoption::AdoptionPhase {
+        name: name.to_string(),
+        success: result.is_ok(),
+        warning: false,
+        detail: match result {
+            Ok(output) => output.clone(),
+            Err(e) => e.message().to_string(),
+        },
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    push_phase(app, ip, phases, phase);
+}
+
+/// Record a step that didn't go to plan but doesn't stop the adoption.
+fn record_warning(
+    app: &tauri::AppHandle,
+    ip: &str,
+    phases: &mut Vec<adoption::AdoptionPhase>,
+    name: &str,
+    detail: String,
+    started: std::time::Instant,
+) {
+    let phase = adoption::AdoptionPhase {
+        name: name.to_string(),
+        success: true,
+        warning: true,
+        detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    push_phase(app, ip, phases, phase);
+}
+
+fn push_phase(
+    app: &tauri::AppHandle,
+    ip: &str,
+    phases: &mut Vec<adoption::AdoptionPhase>,
+    phase: adoption::AdoptionPhase,
+) {
+    log::info!(
+        "Adoption phase {} on {}: {}",
+        phase.name,
+        ip,
+        match (phase.success, phase.warning) {
+            (true, false) => "ok",
+            (true, true) => "warning",
+            (false, _) => "failed",
+        }
+    );
+    if let Err(e) = app.emit("adoption-phase", AdoptionPhaseEvent { ip, phase: &phase }) {
+        log::warn!("Failed to emit adoption phase: {}", e);
+    }
+    phases.push(phase);
+}
+
+/// Poll the AP's `info` until the controller has accepted it. If a
+/// self-hosted controller is signed in, it's asked to adopt the device.
+async fn wait_for_acceptance(
+    app: &tauri::AppHandle,
+    ip: &str,
+    password: Option<&str>,
+) -> Result<adoption::DeviceInfo, AppError> {
+    let deadline =
+        std::time::Instant::now() + std::time::Duration::from_secs(TWO_PHASE_TIMEOUT_SECS);
+    let controller = app
+        .try_state::<ControllerState>()
+        .and_then(|state| state.0.lock().unwrap().clone());
+    let mut adopt_requested = false;
+    let mut last_status = String::from("unknown");
+
+    loop {
+        // The AP briefly drops SSH while it provisions, so errors just mean "try again"
+        match run_ssh_command(ip, adoption::INFO_COMMAND, password).await {
+            Ok(output) => match adoption::parse_info(&output) {
+                Some(info) if info.stage.is_accepted() => return Ok(info),
+                Some(info) => {
+                    if info.status != last_status {
+                        log::info!("{} status: {}", ip, info.status);
+                        last_status = info.status.clone();
+                    }
+                    if let (Some(client), Some(mac), false) =
+                        (&controller, &info.mac, adopt_requested)
+                    {
+                        adopt_requested = true;
+                        if let Err(e) = client.adopt(mac).await {
+                            log::warn!("Controller adopt of {} failed: {}", mac, e);
+                        }
+                    }
+                }
+                None => log::warn!("Unrecognised info output from {}: {}", ip, output),
+            },
+            Err(AppError::AuthFailed(msg)) => return Err(AppError::AuthFailed(msg)),
+            Err(e) => log::info!("Status check on {} failed, retrying: {}", ip, e),
+        }
+
+        if std::time::Instant::now() >= deadline {
+            return Err(AppError::AdoptionStalled(format!(
+                "The access point didn't reach \"Adopting\" within {} minutes (last status: {})",
+                TWO_PHASE_TIMEOUT_SECS / 60,
+                last_status
+            )));
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(TWO_PHASE_POLL_INTERVAL_SECS)).await;
+    }
+}
+
+/// Check the inform URL is reachable from this computer and, if `ip` is
+/// given, from the AP itself over SSH. Returns one report per vantage point.
+#[tauri::command]
+async fn preflight_inform(
+    app: tauri::AppHandle,
+    inform_url: String,
+    ip: Option<String>,
+    custom_password: Option<String>,
+) -> Result<Vec<preflight::PreflightReport>, AppError> {
+    preflight::inform_target(&inform_url).map_err(AppError::InvalidInput)?;
+
+    let network = network_settings(&app);
+    let mut reports = vec![preflight::check_from_laptop(&inform_url, &network).await];
+    if let Some(ip) = ip {
+        reports.push(check_from_device(&ip, &inform_url, custom_password.as_deref()).await?);
+    }
+    Ok(reports)
+}
+
+/// Turn the locate LED on `ip` on or off. Switching it on schedules an
+/// automatic switch-off after `duration_secs` (60 by default); any later
+/// call for the same AP replaces that schedule.
+#[tauri::command]
+async fn locate_device(
+    ip: String,
+    enabled: bool,
+    duration_secs: Option<u64>,
+    custom_password: Option<String>,
+    timers: tauri::State<'_, LocateTimers>,
+) -> Result<LocateResult, AppError> {
+    if let Some(timer) = timers.0.lock().unwrap().remove(&ip) {
+        timer.abort();
+    }
+
+    let output = run_ssh_command(
+        &ip,
+        &locate::locate_command(enabled),
+        custom_password.as_deref(),
+    )
+    .await?;
+    let method = locate::parse_output(&output).map_err(AppError::CommandFailed)?;
+    log::info!(
+        "Locate LED on {} turned {} via {:?}",
+        ip,
+        if enabled { "on" } else { "off" },
+        method
+    );
+
+    let mut off_at = None;
+    if enabled {
+        let secs = locate::duration_secs(duration_secs);
+        off_at = Some(chrono::Utc::now() + chrono::Duration::seconds(secs as i64));
+        let timer_ip = ip.clone();
+        let timer = tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+            let command = locate::locate_command(false);
+            match run_ssh_command(&timer_ip, &command, custom_password.as_deref()).await {
+                Ok(_) => log::info!("Locate LED on {} switched off after {}s", timer_ip, secs),
+                Err(e) => log::warn!("Failed to switch off locate LED on {}: {}", timer_ip, e),
+            }
+        });
+        timers.0.lock().unwrap().insert(ip, timer);
+    }
+
+    Ok(LocateResult {
+        enabled,
+        method,
+        off_at,
+    })
+}
+
+/// Factory reset the AP at `ip`, then wait until discovery finds it again
+/// with the default flag set — after it dropped out of a scan, if it was
+/// already on factory settings. `confirm_mac` must match the MAC discovery
+/// last saw at that IP, so a stale list or a typo can't wipe the wrong
+/// device.
+#[tauri::command]
+async fn factory_reset_device(
+    ip: String,
+    confirm_mac: String,
+    custom_password: Option<String>,
+    timeout_secs: Option<u64>,
+    app: tauri::AppHandle,
+    last_scan: tauri::State<'_, LastScan>,
+) -> Result<FactoryResetResult, AppError> {
+    let target = last_scan.device_at(&ip)?;
+    let mac = target.mac;
+    if !discovery::same_mac(&mac, &confirm_mac) {
+        return Err(AppError::InvalidInput(format!(
+            "Confirmation {} doesn't match {} ({})",
+            confirm_mac, ip, mac
+        )));
+    }
+
+    backup_config(&app, &ip, custom_password.as_deref(), "factory-reset").await?;
+    let output = run_ssh_command(&ip, &reset::reset_command(), custom_password.as_deref()).await?;
+    let method = reset::parse_output(&output).map_err(AppError::CommandFailed)?;
+    let started = std::time::Instant::now();
+    log::info!("Factory reset of {} ({}) started via {:?}", ip, mac, method);
+
+    let timeout =
+        std::time::Duration::from_secs(timeout_secs.unwrap_or(reset::DEFAULT_TIMEOUT_SECS));
+    // A device that was already on factory settings keeps answering as one
+    // until it goes down, so it has to drop out of a scan before it counts
+    let mut gone = !target.is_default;
+    let device = wait_for_device(
+        &last_scan,
+        |scan| match scan.iter().find(|d| discovery::same_mac(&d.mac, &mac)) {
+            None => {
+                gone = true;
+                None
+            }
+            Some(d) if gone && d.is_default => Some(d.clone()),
+            Some(_) => None,
+        },
+        started,
+        reset::POLL_INTERVAL_SECS,
+        timeout,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::ConnectionTimeout(format!(
+            "{} didn't come back on factory settings within {}s",
+            mac,
+            timeout.as_secs()
+        ))
+    })?;
+
+    let elapsed_secs = started.elapsed().as_secs();
+    log::info!(
+        "{} is back on factory settings at {} after {}s",
+        mac,
+        device.ip,
+        elapsed_secs
+    );
+    Ok(FactoryResetResult {
+        method,
+        device,
+        elapsed_secs,
+    })
+}
+
+/// Snapshot the AP's config before changing it. `reason` names the
+/// operation and ends up in the backup id.
+async fn backup_config(
+    app: &tauri::AppHandle,
+    ip: &str,
+    password: Option<&str>,
+    reason: &str,
+) -> Result<config_backup::BackupSummary, AppError> {
+    let output = run_secret_ssh_command(ip, &config_backup::backup_command(), password).await?;
+    let (reported_mac, files) =
+        config_backup::parse_backup(&output).map_err(AppError::CommandFailed)?;
+    // Discovery's MAC is what the UI shows, so file the snapshot under it
+    let mac = app
+        .state::<LastScan>()
+        .mac_at(ip)
+        .ok()
+        .or(reported_mac)
+        .ok_or_else(|| {
+            AppError::CommandFailed(format!("Couldn't tell which device is at {}", ip))
+        })?;
+
+    let backup = config_backup::ConfigBackup::new(&mac, ip, reason, files);
+    let path = app
+        .state::<config_backup::BackupStore>()
+        .save(&backup)
+        .map_err(AppError::Internal)?;
+    log::info!("Config of {} ({}) backed up to {}", ip, mac, path.display());
+    Ok(backup.summary())
+}
+
+/// Config snapshots stored for `mac`, newest first.
+#[tauri::command]
+fn list_config_backups(
+    mac: String,
+    store: tauri::State<'_, config_backup::BackupStore>,
+) -> Vec<config_backup::BackupSummary> {
+    store.list(&mac)
+}
+
+/// Push the stored snapshot `backup_id` back to the AP at `ip`, save it to
+/// flash and reboot. The current config is snapshotted first, and the
+/// snapshot must belong to the device discovery last saw at that IP.
+#[tauri::command]
+async fn restore_device_config(
+    ip: String,
+    backup_id: String,
+    custom_password: Option<String>,
+    app: tauri::AppHandle,
+    last_scan: tauri::State<'_, LastScan>,
+    store: tauri::State<'_, config_backup::BackupStore>,
+) -> Result<RestoreConfigResult, AppError> {
+    let mac = last_scan.mac_at(&ip)?;
+    let backup = store
+        .load(&mac, &backup_id)
+        .map_err(AppError::InvalidInput)?;
+    let command = config_backup::restore_command(&backup).map_err(AppError::InvalidInput)?;
+
+    let password = custom_password.as_deref();
+    let previous = backup_config(&app, &ip, password, "restore").await?;
+    let output = run_secret_ssh_command(&ip, &command, password).await?;
+    config_backup::parse_restore(&output).map_err(AppError::CommandFailed)?;
+    log::info!(
+        "Restored config {} to {} ({}), rebooting",
+        backup.id,
+        ip,
+        mac
+    );
+
+    Ok(RestoreConfigResult {
+        restored: backup.summary(),
+        previous,
+    })
+}
+
+/// Upgrade the AP at `ip` to the `.bin` image at `firmware_path`, served
+/// from this computer so no internet access is needed. Progress is emitted
+/// as `firmware-progress` events; the result is checked by waiting for the
+/// AP to reappear in discovery with the new firmware.
+#[tauri::command]
+async fn upgrade_firmware(
+    ip: String,
+    firmware_path: String,
+    custom_password: Option<String>,
+    timeout_secs: Option<u64>,
+    app: tauri::AppHandle,
+    last_scan: tauri::State<'_, LastScan>,
+) -> Result<FirmwareUpgradeResult, AppError> {
+    let password = custom_password.as_deref();
+    let discovery::DiscoveredDevice {
+        mac,
+        firmware: previous_firmware,
+        ..
+    } = last_scan.device_at(&ip)?;
+
+    let path = std::path::PathBuf::from(&firmware_path);
+    let image = tokio::task::spawn_blocking(move || firmware::FirmwareImage::load(&path))
+        .await
+        .map_err(|e| AppError::Internal(format!("Firmware read task failed: {}", e)))?
+        .map_err(AppError::InvalidInput)?;
+    if let Some(version) = &image.version {
+        if firmware::is_version(&previous_firmware, version) {
+            return Err(AppError::InvalidInput(format!(
+                "{} already runs {}",
+                ip, version
+            )));
+        }
+    }
+
+    let device_ip: std::net::IpAddr = ip
+        .parse()
+        .map_err(|_| AppError::InvalidInput(format!("\"{}\" isn't an IP address", ip)))?;
+    let host = inform::local_ip_towards(device_ip)
+        .map_err(|e| AppError::NetworkUnavailable(format!("No route to {}: {}", ip, e)))?;
+    let server = firmware::FirmwareServer::start("0.0.0.0:0", image)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to start firmware server: {}", e)))?;
+    let url = server.url(host);
+
+    let progress = |stage, detail: &str| {
+        let event = FirmwareProgressEvent {
+            ip: &ip,
+            stage,
+            detail,
+        };
+        if let Err(e) = app.emit("firmware-progress", event) {
+            log::warn!("Failed to emit firmware progress: {}", e);
+        }
+    };
+
+    backup_config(&app, &ip, password, "upgrade").await?;
+    let started = std::time::Instant::now();
+    let output = run_ssh_command(&ip, &firmware::upgrade_command(&url), password).await?;
+    if !output.contains("VS_UPGRADE=started") {
+        return Err(AppError::CommandFailed(format!(
+            "Upgrade didn't start: {}",
+            output.trim()
+        )));
+    }
+    log::info!("Upgrading {} ({}) from {}", ip, mac, url);
+    progress(firmware::UpgradeStage::Serving, &url);
+
+    let download_deadline =
+        started + std::time::Duration::from_secs(firmware::DOWNLOAD_TIMEOUT_SECS);
+    while server.status().completed == 0 {
+        if std::time::Instant::now() >= download_deadline {
+            let log = run_ssh_command(&ip, &format!("cat {}", firmware::LOG_PATH), password)
+                .await
+                .unwrap_or_default();
+            let what = if server.status().requests == 0 {
+                "never downloaded"
+            } else {
+                "didn't finish downloading"
+            };
+            return Err(AppError::CommandFailed(format!(
+                "{} {} the firmware from {}. {}",
+                ip,
+                what,
+                url,
+                log.trim()
+            )));
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    }
+    progress(
+        firmware::UpgradeStage::Flashing,
+        "Firmware downloaded, the access point is flashing and rebooting",
+    );
 
+    let timeout =
+        std::time::Duration::from_secs(timeout_secs.unwrap_or(firmware::DEFAULT_TIMEOUT_SECS));
+    let mut last_firmware = previous_firmware.clone();
+    let found = wait_for_device(
+        &last_scan,
+        |scan| {
+            let d = scan.iter().find(|d| discovery::same_mac(&d.mac, &mac))?;
+            last_firmware.clone_from(&d.firmware);
+            (d.firmware != previous_firmware).then(|| d.clone())
+        },
+        started,
+        firmware::POLL_INTERVAL_SECS,
+        timeout,
+    )
+    .await?;
+    let device = found.ok_or_else(|| {
+        AppError::ConnectionTimeout(format!(
+            "{} didn't come back with new firmware within {}s (still reports {})",
+            mac,
+            timeout.as_secs(),
+            last_firmware
+        ))
+    })?;
+
+    if let Some(version) = &server.image().version {
+        if !firmware::is_version(&device.firmware, version) {
+            return Err(AppError::CommandFailed(format!(
+                "{} came back with {}, expected {}",
+                mac, device.firmware, version
+            )));
+        }
+    }
+    progress(firmware::UpgradeStage::Upgraded, &device.firmware);
+    let elapsed_secs = started.elapsed().as_secs();
+    log::info!(
+        "{} upgraded from {} to {} in {}s",
+        mac,
+        previous_firmware,
+        device.firmware,
+        elapsed_secs
+    );
+    Ok(FirmwareUpgradeResult {
+        previous_firmware,
+        device,
+        elapsed_secs,
+    })
+}
+
+/// Check for an AP in TFTP recovery mode at `ip` (192.168.1.20 by default).
+#[tauri::command]
+async fn detect_recovery_device(ip: Option<String>) -> Result<RecoveryStatus, AppError> {
+    let device_ip = recovery_ip(ip)?;
+    let local_ip = inform::local_ip_towards(device_ip).ok();
+    let in_recovery = recovery::probe(
+        std::net::SocketAddr::new(device_ip, recovery::TFTP_PORT),
+        std::time::Duration::from_secs(2),
+    )
+    .await;
+    Ok(RecoveryStatus {
+        ip: device_ip.to_string(),
+        in_recovery,
+        local_ip: local_ip.map(|ip| ip.to_string()),
+        same_subnet: local_ip.is_some_and(|local| recovery::same_subnet(local, device_ip)),
+    })
+}
+
+/// Push the `.bin` image at `firmware_path` to an AP in recovery mode over
+/// TFTP, then wait for it to boot and show up in discovery. Transfer
+/// progress is emitted as `recovery-progress` events.
+#[tauri::command]
+async fn recovery_flash(
+    firmware_path: String,
+    ip: Option<String>,
+    timeout_secs: Option<u64>,
+    app: tauri::AppHandle,
+    last_scan: tauri::State<'_, LastScan>,
+) -> Result<RecoveryResult, AppError> {
+    let device_ip = recovery_ip(ip)?;
+    let ip = device_ip.to_string();
+    let path = std::path::PathBuf::from(&firmware_path);
+    let image = tokio::task::spawn_blocking(move || firmware::FirmwareImage::load(&path))
+        .await
+        .map_err(|e| AppError::Internal(format!("Firmware read task failed: {}", e)))?
+        .map_err(AppError::InvalidInput)?;
+
+    // Anything already on the network isn't the device coming back. The
+    // last scan may be stale or never have run, so look again first.
+    let before: Vec<String> = last_scan
+        .rescan()
+        .await?
+        .into_iter()
+        .map(|d| d.mac)
+        .collect();
+
+    let started = std::time::Instant::now();
+    let mut last_percent = None;
+    let transfer = recovery::put(
+        std::net::SocketAddr::new(device_ip, recovery::TFTP_PORT),
+        &image.name,
+        &image.bytes,
+        &recovery::TftpOptions::default(),
+        |sent_bytes, total_bytes| {
+            let percent = (sent_bytes * 100).checked_div(total_bytes);
+            if percent == last_percent {
+                return;
+            }
+            last_percent = percent;
+            let event = RecoveryProgressEvent {
+                ip: &ip,
+                sent_bytes,
+                total_bytes,
+            };
+            if let Err(e) = app.emit("recovery-progress", event) {
+                log::warn!("Failed to emit recovery progress: {}", e);
+            }
+        },
+    )
+    .await?;
+    log::info!(
+        "Sent {} to {} in {}ms ({} retransmits), waiting for it to boot",
+        image.name,
+        ip,
+        transfer.duration_ms,
+        transfer.retransmits
+    );
+
+    let timeout =
+        std::time::Duration::from_secs(timeout_secs.unwrap_or(recovery::DEFAULT_TIMEOUT_SECS));
+    let device = wait_for_device(
+        &last_scan,
+        |scan| {
+            scan.iter()
+                .find(|d| d.ip == ip || (d.is_default && !before.contains(&d.mac)))
+                .cloned()
+        },
+        started,
+        recovery::POLL_INTERVAL_SECS,
+        timeout,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::ConnectionTimeout(format!(
+            "The image was sent but no device came back within {}s",
+            timeout.as_secs()
+        ))
+    })?;
+
+    let elapsed_secs = started.elapsed().as_secs();
+    log::info!(
+        "Recovered {} at {} with {} after {}s",
+        device.mac,
+        device.ip,
+        device.firmware,
+        elapsed_secs
+    );
+    Ok(RecoveryResult {
+        transfer,
+        device,
+        elapsed_secs,
+    })
+}
+
+fn recovery_ip(ip: Option<String>) -> Result<std::net::IpAddr, AppError> {
+    match ip {
+        Some(ip) => ip
+            .parse()
+            .map_err(|_| AppError::InvalidInput(format!("\"{}\" isn't an IP address", ip))),
+        None => Ok(recovery::DEFAULT_RECOVERY_IP.into()),
+    }
+}
+
+/// Run the DNS, ping and HTTP checks on the AP over SSH.
+async fn check_from_device(
+    ip: &str,
+    inform_url: &str,
+    password: Option<&str>,
+) -> Result<preflight::PreflightReport, AppError> {
+    let command = preflight::device_command(inform_url).map_err(AppError::InvalidInput)?;
+    let output = run_ssh_command(ip, &command, password).await?;
+    let report = preflight::parse_device_output(inform_url, &output);
+    log::info!("Preflight from {}: {}", ip, report.summary());
+    Ok(report)
+}
+
+async fn set_inform_with_fallback(
+    ip: &str,
+    inform_url: &str,
+    password: Option<&str>,
+) -> Result<String, AppError> {
+    with_ssh_fallback(ssh_process::set_inform(ip, inform_url, password), || {
+        ssh::set_inform(ip, inform_url, password)
+    })
+    .await
+}
+
+/// Run a shell command on an AP and return its output.
+async fn run_ssh_command(
+    ip: &str,
+    command: &str,
+    password: Option<&str>,
+) -> Result<String, AppError> {
+    with_ssh_fallback(ssh_process::run_command(ip, command, password), || {
+        ssh::run_command(ip, command, password)
+    })
+    .await
+}
+
+/// [`run_ssh_command`] for commands that carry secrets, such as config
+/// backups and restores; their output is never logged.
+async fn run_secret_ssh_command(
+    ip: &str,
+    command: &str,
+    password: Option<&str>,
+) -> Result<String, AppError> {
+    with_ssh_fallback(
+        ssh_process::run_command_quiet(ip, command, password),
+        || ssh::run_command(ip, command, password),
+    )
+    .await
+}
+
+/// [`run_ssh_command`], emitting the output as `ssh-output` events tagged
+/// with `job_id` while the command runs. The last event is always an exit
+/// status or a failure.
+async fn run_ssh_command_streaming(
+    app: &tauri::AppHandle,
+    job_id: &str,
+    ip: &str,
+    command: &str,
+    password: Option<&str>,
+) -> Result<String, AppError> {
+    let emit = |chunk| {
+        if let Err(e) = app.emit("ssh-output", SshOutputEvent { job_id, ip, chunk }) {
+            log::warn!("Failed to emit SSH output: {}", e);
+        }
+    };
+    let result = with_ssh_fallback(
+        ssh_process::run_command_streaming(ip, command, password, true, emit),
+        || ssh::run_command_streaming(ip, command, password, emit),
+    )
+    .await;
+    // Neither backend reports a chunk for the errors it returns
+    if let Err(e) = &result {
+        emit(ssh::OutputChunk::Failed(e.message().to_string()));
+    }
+    result
+}
+
+/// Uses the system ssh command (via ssh_process) for maximum compatibility
+/// with Dropbear SSH on UniFi APs. Falls back to the russh library only if
+/// system SSH never got as far as logging in (expect missing, connection
+/// refused, no common algorithms), so a command is never run twice.
+async fn with_ssh_fallback<T, S, R>(system: S, russh: impl FnOnce() -> R) -> Result<T, AppError>
+where
+    S: std::future::Future<Output = Result<T, ssh_process::SshError>>,
+    R: std::future::Future<Output = Result<T, ssh::SshError>>,
+{
     // Try system SSH first (uses macOS OpenSSH via expect, proven compatible with Dropbear)
     log::info!("Attempting SSH via system expect command...");
-    match ssh_process::set_inform(&ip, &inform_url, password_ref).await {
+    match system.await {
         Ok(output) => {
             log::info!("System SSH succeeded");
-            return Ok(AdoptResult {
-                success: true,
-                output,
-            });
+            Ok(output)
         }
         Err(e) => {
-            let err_str = e.to_string();
-            log::warn!("System SSH failed: {}", err_str);
+            log::warn!("System SSH failed: {}", e);
 
-            // If it's an auth failure, don't bother with russh — report it directly
-            if matches!(e, ssh_process::SshError::AuthFailed(_)) {
-                return Err(err_str);
+            // Anything past the connection stage may have run the command
+            // already, or would fail the same way with russh
+            if !matches!(
+                e,
+                ssh_process::SshError::ConnectionRefused(_) | ssh_process::SshError::Other(_)
+            ) {
+                return Err(e.into());
             }
 
             // For other failures (e.g. expect not found), try russh as fallback
             log::info!("Falling back to russh library...");
-            match ssh::set_inform(&ip, &inform_url, password_ref).await {
-                Ok(output) => {
-                    return Ok(AdoptResult {
-                        success: true,
-                        output,
-                    });
-                }
+            match russh().await {
+                Ok(output) => Ok(output),
                 Err(russh_err) => {
-                    // Return whichever error is more informative
-                    let russh_str = russh_err.to_string();
-                    log::warn!("russh also failed: {}", russh_str);
-                    return Err(format!(
-                        "SSH error: Failed to connect to {}: {}",
-                        ip, err_str
-                    ));
+                    log::warn!("russh also failed: {}", russh_err);
+                    // Return whichever error is more informative — a generic
+                    // system SSH failure (e.g. expect missing) says less than
+                    // whatever russh ran into.
+                    if matches!(e, ssh_process::SshError::Other(_)) {
+                        Err(russh_err.into())
+                    } else {
+                        Err(e.into())
+                    }
                 }
             }
         }
     }
 }
 
+/// Open an interactive shell on the AP at `ip` with a `cols` x `rows`
+/// terminal. Output arrives as `terminal-output` events and the end of the
+/// session as `terminal-closed`, both tagged with `session_id`. The caller
+/// picks the id so it can be listening before the first output arrives.
+#[tauri::command]
+async fn open_terminal(
+    session_id: String,
+    ip: String,
+    custom_password: Option<String>,
+    cols: u32,
+    rows: u32,
+    app: tauri::AppHandle,
+    sessions: tauri::State<'_, TerminalSessions>,
+) -> Result<(), AppError> {
+    use base64::Engine;
+
+    {
+        let mut sessions = sessions.0.lock().unwrap();
+        // Shells that ended on their own (`exit`, reboot) are dropped here
+        sessions.retain(|_, session| !session.as_ref().is_some_and(ssh::ShellSession::is_closed));
+        if session_id.is_empty() || sessions.contains_key(&session_id) {
+            return Err(AppError::InvalidInput(format!(
+                "\"{}\" can't be used as a terminal session id",
+                session_id
+            )));
+        }
+        // Reserve the id while the shell opens so a second open can't take it
+        sessions.insert(session_id.clone(), None);
+    }
+    let id = session_id.clone();
+    let opened = ssh::open_shell(&ip, custom_password.as_deref(), cols, rows, move |event| {
+        let result = match event {
+            ssh::ShellEvent::Output(data) => app.emit(
+                "terminal-output",
+                TerminalOutputEvent {
+                    session_id: &id,
+                    data: base64::engine::general_purpose::STANDARD.encode(data),
+                },
+            ),
+            ssh::ShellEvent::Closed { exit_status } => app.emit(
+                "terminal-closed",
+                TerminalClosedEvent {
+                    session_id: &id,
+                    exit_status,
+                },
+            ),
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to emit terminal event: {}", e);
+        }
+    })
+    .await;
+
+    let unwanted = {
+        let mut sessions = sessions.0.lock().unwrap();
+        match (opened, sessions.get_mut(&session_id)) {
+            (Ok(session), Some(slot)) => {
+                *slot = Some(session);
+                None
+            }
+            // The view was closed while the shell was opening
+            (Ok(session), None) => Some(session),
+            (Err(e), _) => {
+                sessions.remove(&session_id);
+                return Err(e.into());
+            }
+        }
+    };
+    if let Some(session) = unwanted {
+        session.close().await;
+    }
+    Ok(())
+}
+
+/// Send keystrokes to an open terminal.
+#[tauri::command]
+fn terminal_input(
+    session_id: String,
+    data: String,
+    sessions: tauri::State<'_, TerminalSessions>,
+) -> Result<(), AppError> {
+    terminal_session(&sessions, &session_id, |session| {
+        session.write(data.into_bytes())
+    })
+}
+
+/// Tell the AP the terminal view changed size.
+#[tauri::command]
+fn terminal_resize(
+    session_id: String,
+    cols: u32,
+    rows: u32,
+    sessions: tauri::State<'_, TerminalSessions>,
+) -> Result<(), AppError> {
+    terminal_session(&sessions, &session_id, |session| session.resize(cols, rows))
+}
+
+fn terminal_session(
+    sessions: &TerminalSessions,
+    session_id: &str,
+    action: impl FnOnce(&ssh::ShellSession) -> Result<(), ssh::SshError>,
+) -> Result<(), AppError> {
+    let sessions = sessions.0.lock().unwrap();
+    let session = sessions
+        .get(session_id)
+        .and_then(Option::as_ref)
+        .ok_or_else(|| AppError::InvalidInput("The terminal session has ended".to_string()))?;
+    action(session).map_err(AppError::from)
+}
+
+/// Close a terminal when its view goes away. Unknown or already closed
+/// sessions are ignored; one that's still opening is closed once it's up.
+#[tauri::command]
+async fn close_terminal(
+    session_id: String,
+    sessions: tauri::State<'_, TerminalSessions>,
+) -> Result<(), AppError> {
+    let session = sessions.0.lock().unwrap().remove(&session_id);
+    if let Some(Some(session)) = session {
+        session.close().await;
+    }
+    Ok(())
+}
+
+/// Sign in to a self-hosted UniFi Network controller with a local account
+/// and list the devices waiting for adoption on `site` (default "default").
+/// The certificate is verified unless `accept_invalid_certs` is set.
+#[tauri::command]
+async fn controller_login(
+    url: String,
+    username: String,
+    password: String,
+    site: Option<String>,
+    accept_invalid_certs: Option<bool>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ControllerState>,
+) -> Result<ControllerSession, AppError> {
+    if url::Url::parse(&url).is_err() {
+        return Err(AppError::InvalidInput(format!(
+            "\"{}\" isn't a valid controller URL, e.g. https://192.168.1.2:8443",
+            url
+        )));
+    }
+    let site = site
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| "default".to_string());
+    let client = controller::ControllerClient::login(
+        &url,
+        &username,
+        &password,
+        &site,
+        &network_settings(&app),
+        accept_invalid_certs.unwrap_or(false),
+    )
+    .await?;
+    let pending = client.pending_devices().await?;
+
+    let session = ControllerSession {
+        kind: client.kind(),
+        site,
+        pending,
+    };
+    *state.0.lock().unwrap() = Some(Arc::new(client));
+    Ok(session)
+}
+
+/// Devices that have informed the signed-in controller but aren't adopted.
+#[tauri::command]
+async fn controller_pending_devices(
+    state: tauri::State<'_, ControllerState>,
+) -> Result<Vec<controller::ControllerDevice>, AppError> {
+    Ok(state.client()?.pending_devices().await?)
+}
+
+/// Adopt `mac` on the signed-in controller and wait until it's connected.
+#[tauri::command]
+async fn controller_adopt_device(
+    mac: String,
+    timeout_secs: Option<u64>,
+    state: tauri::State<'_, ControllerState>,
+) -> Result<controller::ControllerDevice, AppError> {
+    let client = state.client()?;
+    client.adopt(&mac).await?;
+    Ok(client
+        .wait_until_connected(
+            &mac,
+            std::time::Duration::from_secs(timeout_secs.unwrap_or(CONTROLLER_ADOPT_TIMEOUT_SECS)),
+            std::time::Duration::from_secs(CONTROLLER_POLL_INTERVAL_SECS),
+        )
+        .await?)
+}
+
+#[tauri::command]
+fn controller_logout(state: tauri::State<'_, ControllerState>) {
+    state.0.lock().unwrap().take();
+}
+
+/// Start listening for informs on this computer (port 8080 by default).
+/// With `device_ip`, the returned inform URL uses this computer's address
+/// on the route to that device. With `relay_to` (the site's inform URL),
+/// informs are forwarded to the controller so APs that can't reach it yet
+/// can still be adopted. Restarting replaces any running listener.
+#[tauri::command]
+async fn start_inform_listener(
+    port: Option<u16>,
+    device_ip: Option<String>,
+    relay_to: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, InformListenerState>,
+) -> Result<InformListenerStatus, AppError> {
+    let port = port.unwrap_or(inform::DEFAULT_PORT);
+    let host =
+        match device_ip {
+            Some(ip) => {
+                let ip: std::net::IpAddr = ip.parse().map_err(|_| {
+                    AppError::InvalidInput(format!("\"{}\" isn't an IP address", ip))
+                })?;
+                Some(inform::local_ip_towards(ip).map_err(|e| {
+                    AppError::NetworkUnavailable(format!("No route to {}: {}", ip, e))
+                })?)
+            }
+            None => None,
+        };
+
+    if let Some(upstream) = &relay_to {
+        preflight::inform_target(upstream).map_err(AppError::InvalidInput)?;
+    }
+
+    // Free the port before binding it again
+    let previous = state.0.lock().unwrap().take();
+    if let Some(previous) = previous {
+        previous.stop().await;
+    }
+    let addr = format!("0.0.0.0:{}", port);
+    let listener = match &relay_to {
+        Some(upstream) => {
+            inform::InformListener::start_relay(&addr, upstream, &network_settings(&app)).await
+        }
+        None => inform::InformListener::start(&addr).await,
+    }
+    .map_err(|e| AppError::InvalidInput(format!("Can't listen on port {}: {}", port, e)))?;
+
+    let mut current = state.0.lock().unwrap();
+    current.host = host;
+    current.listener = Some(listener);
+    Ok(current.status())
+}
+
+#[tauri::command]
+async fn stop_inform_listener(
+    state: tauri::State<'_, InformListenerState>,
+) -> Result<(), AppError> {
+    let listener = state.0.lock().unwrap().take();
+    if let Some(listener) = listener {
+        listener.stop().await;
+        log::info!("Inform listener stopped");
+    }
+    Ok(())
+}
+
+/// Devices heard from so far, most recent first.
+#[tauri::command]
+fn get_inform_status(state: tauri::State<'_, InformListenerState>) -> InformListenerStatus {
+    state.0.lock().unwrap().status()
+}
+
 /// Return the app version for display in the UI.
 #[tauri::command]
 fn get_app_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
+/// Return the most recent log lines (already redacted) for an in-app log viewer.
+#[tauri::command]
+async fn get_recent_logs(
+    app: tauri::AppHandle,
+    max_lines: Option<usize>,
+) -> Result<Vec<String>, AppError> {
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| AppError::Internal(format!("Failed to locate log directory: {}", e)))?;
+
+    tokio::task::spawn_blocking(move || diagnostics::recent_log_lines(&log_dir, max_lines))
+        .await
+        .map_err(|e| AppError::Internal(format!("Log read task failed: {}", e)))?
+        .map_err(AppError::Internal)
+}
+
+/// Bundle logs, app version and the last scan results into a zip file.
+/// Returns the path of the written bundle so the UI can reveal it.
+#[tauri::command]
+async fn export_diagnostics(
+    app: tauri::AppHandle,
+    last_scan: tauri::State<'_, LastScan>,
+) -> Result<String, AppError> {
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| AppError::Internal(format!("Failed to locate log directory: {}", e)))?;
+    // Prefer Downloads so the technician can find and attach the file easily
+    let out_dir = app
+        .path()
+        .download_dir()
+        .unwrap_or_else(|_| log_dir.clone());
+
+    let now = chrono::Local::now();
+    let manifest = diagnostics::DiagnosticsManifest {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        created_at: now.to_rfc3339(),
+        last_scan: last_scan.0.lock().unwrap().clone(),
+    };
+    let out_path = out_dir.join(format!(
+        "vivaspot-diagnostics-{}.zip",
+        now.format("%Y%m%d-%H%M%S")
+    ));
+
+    let result_path = out_path.clone();
+    tokio::task::spawn_blocking(move || diagnostics::export_bundle(&out_path, &log_dir, &manifest))
+        .await
+        .map_err(|e| AppError::Internal(format!("Diagnostics export task failed: {}", e)))?
+        .map_err(AppError::Internal)?;
+
+    Ok(result_path.to_string_lossy().to_string())
+}
+
+/// Run the read-only diagnostic commands on the AP at `ip` and save their
+/// output, with the discovery record, as a timestamped zip in the app data
+/// directory. With a `job_id`, output is streamed as `ssh-output` events
+/// while the commands run.
+#[tauri::command]
+async fn collect_device_diagnostics(
+    app: tauri::AppHandle,
+    ip: String,
+    custom_password: Option<String>,
+    job_id: Option<String>,
+    last_scan: tauri::State<'_, LastScan>,
+) -> Result<DeviceDiagnosticsResult, AppError> {
+    let device = last_scan.device_at(&ip).ok();
+    let out_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Internal(format!("Failed to locate app data directory: {}", e)))?
+        .join(device_diagnostics::ARCHIVE_DIR_NAME);
+
+    let command = device_diagnostics::collect_command();
+    let password = custom_password.as_deref();
+    let output = match &job_id {
+        Some(job_id) => run_ssh_command_streaming(&app, job_id, &ip, &command, password).await?,
+        None => run_ssh_command(&ip, &command, password).await?,
+    };
+    let mut sections = device_diagnostics::parse_output(&output);
+    for section in &mut sections {
+        section.output = diagnostics::redact(&section.output);
+    }
+
+    let now = chrono::Local::now();
+    let manifest = device_diagnostics::ArchiveManifest::new(
+        &ip,
+        device.as_ref(),
+        &sections,
+        &now.to_rfc3339(),
+    );
+    let out_path = out_dir.join(device_diagnostics::archive_name(
+        &ip,
+        device.as_ref(),
+        &now.format("%Y%m%d-%H%M%S").to_string(),
+    ));
+    let commands = manifest.commands.clone();
+
+    let result_path = out_path.clone();
+    tokio::task::spawn_blocking(move || {
+        device_diagnostics::write_archive(&out_path, &manifest, device.as_ref(), &sections)
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Device diagnostics task failed: {}", e)))?
+    .map_err(AppError::Internal)?;
+
+    Ok(DeviceDiagnosticsResult {
+        path: result_path.to_string_lossy().to_string(),
+        commands,
+    })
+}
+
+/// Return the persisted settings, including all API profiles.
+#[tauri::command]
+fn get_settings(settings: tauri::State<'_, AppSettings>) -> settings::Settings {
+    settings.0.lock().unwrap().clone()
+}
+
+/// Switch the active API profile (production, staging, local) and persist it.
+#[tauri::command]
+fn set_active_profile(
+    app: tauri::AppHandle,
+    name: String,
+    settings: tauri::State<'_, AppSettings>,
+) -> Result<settings::Settings, AppError> {
+    let mut current = settings.0.lock().unwrap();
+    current.set_active(&name).map_err(AppError::InvalidInput)?;
+    rebuild_api_client(&app, &current)?;
+    save_settings(&app, &current)?;
+    Ok(current.clone())
+}
+
+/// Update proxy, CA bundle and timeout settings for the VivaSpot API client.
+/// The new client is built before anything is saved, so a bad proxy URL or
+/// CA file is reported without replacing a working configuration.
+#[tauri::command]
+fn set_network_settings(
+    app: tauri::AppHandle,
+    network: settings::NetworkSettings,
+    settings: tauri::State<'_, AppSettings>,
+) -> Result<settings::Settings, AppError> {
+    let mut current = settings.0.lock().unwrap();
+    let mut updated = current.clone();
+    updated.network = network;
+    rebuild_api_client(&app, &updated)?;
+    save_settings(&app, &updated)?;
+    *current = updated;
+    Ok(current.clone())
+}
+
+/// Proxy, CA and timeout settings for requests from this computer.
+fn network_settings(app: &tauri::AppHandle) -> settings::NetworkSettings {
+    app.try_state::<AppSettings>()
+        .map(|state| state.0.lock().unwrap().network.clone())
+        .unwrap_or_default()
+}
+
+/// Build a new API client from `settings` and swap it into managed state.
+fn rebuild_api_client(
+    app: &tauri::AppHandle,
+    settings: &settings::Settings,
+) -> Result<(), AppError> {
+    let client = api::ApiClient::new(&settings.api_base(), &settings.network)?;
+    log::info!("API client using {}", client.api_base());
+    if let Some(state) = app.try_state::<ApiClientState>() {
+        *state.0.lock().unwrap() = Arc::new(client);
+    }
+    Ok(())
+}
+
+fn save_settings(app: &tauri::AppHandle, settings: &settings::Settings) -> Result<(), AppError> {
+    let path = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| AppError::Internal(format!("Failed to locate config directory: {}", e)))?
+        .join(settings::SETTINGS_FILE_NAME);
+    settings::save(&path, settings).map_err(AppError::Internal)
+}
+
+/// Persisted settings, loaded from the app config directory at startup.
+struct AppSettings(Mutex<settings::Settings>);
+
+/// Shared VivaSpot API client. Commands clone the `Arc` out of the lock
+/// before awaiting so a settings change never blocks on an in-flight request.
+struct ApiClientState(Mutex<Arc<api::ApiClient>>);
+
+/// Signed-in self-hosted controller, if any.
+struct ControllerState(Mutex<Option<Arc<controller::ControllerClient>>>);
+
+/// Pending automatic locate switch-offs, by AP IP.
+struct LocateTimers(Mutex<HashMap<String, tauri::async_runtime::JoinHandle<()>>>);
+
+/// Open interactive shells, by session id; `None` while one is opening.
+struct TerminalSessions(Mutex<HashMap<String, Option<ssh::ShellSession>>>);
+
+/// Local inform listener, while running, and the address devices reach it on.
+struct InformListenerState(Mutex<InformListenerSlot>);
+
+#[derive(Default)]
+struct InformListenerSlot {
+    listener: Option<inform::InformListener>,
+    host: Option<std::net::IpAddr>,
+}
+
+impl InformListenerSlot {
+    /// Stop the listener, returning it if one was running.
+    fn take(&mut self) -> Option<inform::InformListener> {
+        self.host = None;
+        self.listener.take()
+    }
+
+    fn status(&self) -> InformListenerStatus {
+        match &self.listener {
+            Some(listener) => InformListenerStatus {
+                running: true,
+                port: Some(listener.local_addr().port()),
+                inform_url: self.host.map(|host| listener.inform_url(host)),
+                relay_to: listener.upstream().map(str::to_string),
+                devices: listener.records(),
+            },
+            None => InformListenerStatus {
+                running: false,
+                port: None,
+                inform_url: None,
+                relay_to: None,
+                devices: Vec::new(),
+            },
+        }
+    }
+}
+
+/// How long two-phase adoption waits for the controller to accept the AP.
+const TWO_PHASE_TIMEOUT_SECS: u64 = 180;
+const TWO_PHASE_POLL_INTERVAL_SECS: u64 = 5;
+
+/// Adopting and provisioning usually takes a minute or two.
+const CONTROLLER_ADOPT_TIMEOUT_SECS: u64 = 300;
+const CONTROLLER_POLL_INTERVAL_SECS: u64 = 3;
+
+impl ControllerState {
+    fn client(&self) -> Result<Arc<controller::ControllerClient>, AppError> {
+        self.0.lock().unwrap().clone().ok_or_else(|| {
+            AppError::InvalidInput("Sign in to the UniFi controller first".to_string())
+        })
+    }
+}
+
+/// Expected devices for the current site, once fetched.
+struct SiteInventoryState(Mutex<Option<api::SiteInventory>>);
+
+impl SiteInventoryState {
+    /// Forget an inventory loaded for a different site, so scans after a
+    /// site switch aren't reconciled against the previous site's devices.
+    fn switch_site(&self, site_id: &str) {
+        let mut current = self.0.lock().unwrap();
+        if current.as_ref().is_some_and(|inv| inv.site_id != site_id) {
+            *current = None;
+        }
+    }
+}
+
+/// Devices found by the most recent scan, kept for diagnostics exports.
+struct LastScan(Mutex<Vec<discovery::DiscoveredDevice>>);
+
+impl LastScan {
+    /// The device the last scan found at `ip`.
+    fn device_at(&self, ip: &str) -> Result<discovery::DiscoveredDevice, AppError> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|d| d.ip == ip)
+            .cloned()
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!("{} wasn't found by the last scan, scan again", ip))
+            })
+    }
+
+    fn mac_at(&self, ip: &str) -> Result<String, AppError> {
+        self.device_at(ip).map(|d| d.mac)
+    }
+
+    /// Scan again and remember the results.
+    async fn rescan(&self) -> Result<Vec<discovery::DiscoveredDevice>, AppError> {
+        let devices = tokio::task::spawn_blocking(discovery::scan_network)
+            .await
+            .map_err(|e| AppError::Internal(format!("Scan task failed: {}", e)))?
+            .map_err(AppError::DiscoveryFailed)?;
+        *self.0.lock().unwrap() = devices.clone();
+        Ok(devices)
+    }
+}
+
+/// Rescan every `interval_secs` until `pick` chooses a device from the
+/// scan, keeping [`LastScan`] current meanwhile. `pick` sees every scan in
+/// turn, so it can track devices going away. `None` once `timeout` has
+/// passed since `started`.
+async fn wait_for_device(
+    last_scan: &LastScan,
+    mut pick: impl FnMut(&[discovery::DiscoveredDevice]) -> Option<discovery::DiscoveredDevice>,
+    started: std::time::Instant,
+    interval_secs: u64,
+    timeout: std::time::Duration,
+) -> Result<Option<discovery::DiscoveredDevice>, AppError> {
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(interval_secs)).await;
+        let found = pick(&last_scan.rescan().await?);
+        if found.is_some() {
+            return Ok(found);
+        }
+        if started.elapsed() >= timeout {
+            return Ok(None);
+        }
+    }
+}
+
 /// State to hold the initial deep link URL that launched the app.
 /// Consumed once by the frontend on mount.
 struct InitialDeepLink(Mutex<Option<String>>);
@@ -143,14 +1805,61 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_deep_link::init())
         .manage(InitialDeepLink(Mutex::new(None)))
+        .manage(LastScan(Mutex::new(Vec::new())))
+        .manage(SiteInventoryState(Mutex::new(None)))
+        .manage(ControllerState(Mutex::new(None)))
+        .manage(LocateTimers(Mutex::new(HashMap::new())))
+        .manage(TerminalSessions(Mutex::new(HashMap::new())))
+        .manage(InformListenerState(Mutex::new(
+            InformListenerSlot::default(),
+        )))
         .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
-            }
+            // File logging is enabled in release builds too so field reports
+            // can be diagnosed from an exported bundle.
+            app.handle().plugin(diagnostics::log_plugin())?;
+
+            let settings_path = app
+                .path()
+                .app_config_dir()?
+                .join(settings::SETTINGS_FILE_NAME);
+            let settings = settings::load(&settings_path);
+            let api_client = api::ApiClient::new(&settings.api_base(), &settings.network)
+                .or_else(|e| {
+                    // A missing CA file shouldn't stop the app from launching
+                    log::warn!("Invalid network settings, using defaults: {}", e);
+                    api::ApiClient::new(&settings.api_base(), &settings::NetworkSettings::default())
+                })
+                .map_err(|e| e.to_string())?;
+            app.manage(ApiClientState(Mutex::new(Arc::new(api_client))));
+            app.manage(AppSettings(Mutex::new(settings)));
+
+            let queue_path = app.path().app_data_dir()?.join(reporting::QUEUE_FILE_NAME);
+            app.manage(reporting::ReportQueue::load(queue_path));
+
+            let cache_path = app.path().app_data_dir()?.join(site_cache::CACHE_FILE_NAME);
+            app.manage(site_cache::SiteCache::load(cache_path));
+
+            let backup_dir = app
+                .path()
+                .app_data_dir()?
+                .join(config_backup::BACKUP_DIR_NAME);
+            app.manage(config_backup::BackupStore::new(backup_dir));
+
+            // Retry undelivered adoption reports whenever connectivity returns
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+                    reporting::RETRY_INTERVAL_SECS,
+                ));
+                loop {
+                    interval.tick().await;
+                    let client = handle.state::<ApiClientState>().0.lock().unwrap().clone();
+                    handle
+                        .state::<reporting::ReportQueue>()
+                        .flush(&client)
+                        .await;
+                }
+            });
 
             // Capture the deep link URL that launched the app (if any).
             // This must happen in setup() because the JS onOpenUrl listener
@@ -169,10 +1878,39 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             validate_code,
+            list_cached_sites,
+            clear_cached_sites,
+            import_setup_bundle,
             scan_devices,
+            load_site_inventory,
             adopt_device,
+            preflight_inform,
+            locate_device,
+            factory_reset_device,
+            list_config_backups,
+            restore_device_config,
+            open_terminal,
+            terminal_input,
+            terminal_resize,
+            close_terminal,
+            upgrade_firmware,
+            detect_recovery_device,
+            recovery_flash,
+            controller_login,
+            controller_pending_devices,
+            controller_adopt_device,
+            controller_logout,
+            start_inform_listener,
+            stop_inform_listener,
+            get_inform_status,
             get_app_version,
             get_initial_deep_link,
+            get_recent_logs,
+            export_diagnostics,
+            collect_device_diagnostics,
+            get_settings,
+            set_active_profile,
+            set_network_settings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,67 @@
+/// Locate LED control over SSH.
+///
+/// Which tool blinks the LED depends on the firmware: most UniFi APs have
+/// `syswrapper.sh set-locate` / `unset-locate`, some older or stripped-down
+/// builds only have `mca-ctrl`. The generated command tries each in turn
+/// and prints a marker saying which one worked, so the caller can tell
+/// "LED is blinking" from "this firmware has no way to do it".
+use serde::Serialize;
+
+/// Default time before the LED is switched off again.
+pub const DEFAULT_DURATION_SECS: u64 = 60;
+/// Longest a locate may run before it's switched off regardless.
+pub const MAX_DURATION_SECS: u64 = 30 * 60;
+
+const MARKER: &str = "VS_LOCATE=";
+
+/// Tool that toggled the LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LocateMethod {
+    Syswrapper,
+    McaCtrl,
+}
+
+impl LocateMethod {
+    fn marker(&self) -> &'static str {
+        match self {
+            LocateMethod::Syswrapper => "syswrapper",
+            LocateMethod::McaCtrl => "mca-ctrl",
+        }
+    }
+}
+
+/// Shell command that turns the locate LED on or off.
+pub fn locate_command(enabled: bool) -> String {
+    let (syswrapper, mca) = if enabled {
+        ("set-locate", "locate-on")
+    } else {
+        ("unset-locate", "locate-off")
+    };
+    format!(
+        "if syswrapper.sh {syswrapper} >/dev/null 2>&1; then echo {MARKER}{}; \
+         elif mca-ctrl -t {mca} >/dev/null 2>&1; then echo {MARKER}{}; \
+         else echo {MARKER}none; fi",
+        LocateMethod::Syswrapper.marker(),
+        LocateMethod::McaCtrl.marker(),
+    )
+}
+
+/// Which tool worked, from the output of [`locate_command`].
+pub fn parse_output(output: &str) -> Result<LocateMethod, String> {
+    let marker = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(MARKER))
+        .ok_or_else(|| format!("Unexpected output: {}", output.trim()))?;
+    [LocateMethod::Syswrapper, LocateMethod::McaCtrl]
+        .into_iter()
+        .find(|method| method.marker() == marker)
+        .ok_or_else(|| "This firmware has no locate command".to_string())
+}
+
+/// Clamp a requested duration to something sensible.
+pub fn duration_secs(requested: Option<u64>) -> u64 {
+    requested
+        .unwrap_or(DEFAULT_DURATION_SECS)
+        .clamp(1, MAX_DURATION_SECS)
+}
@@ -0,0 +1,234 @@
+/// In-process stand-in for the VivaSpot API.
+///
+/// Serves scripted responses on a local port so `ApiClient` can be tested
+/// against the 200, 404-invalid, 404-expired and unexpected-status branches
+/// without touching ubiquitywizard.onrender.com. Responses can be delayed
+/// or deliberately malformed, and every request is recorded for assertions.
+///
+/// `with_default_routes` scripts a small fake site for offline runs of the
+/// full flow; see `examples/mock_api.rs`.
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::local_http::{self, HttpRequest, HttpResponse};
+
+/// Setup code the default routes accept.
+pub const VALID_CODE: &str = "VS-TEST";
+/// Setup code the default routes report as expired.
+pub const EXPIRED_CODE: &str = "VS-EXPD";
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub response: HttpResponse,
+    pub delay: Option<Duration>,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, value: serde_json::Value) -> Self {
+        MockResponse {
+            response: HttpResponse::json(status, &value),
+            delay: None,
+        }
+    }
+
+    /// A body that is not valid JSON, for exercising parse failures.
+    pub fn malformed(status: u16) -> Self {
+        MockResponse {
+            response: HttpResponse::new(status, "application/json", "{\"informUrl\": "),
+            delay: None,
+        }
+    }
+
+    pub fn text(status: u16, body: &str) -> Self {
+        MockResponse {
+            response: HttpResponse::text(status, body),
+            delay: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.response = self.response.with_header(name, value);
+        self
+    }
+
+    /// Wait before answering, e.g. to trigger client timeouts.
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+type Handler = Arc<dyn Fn(&HttpRequest) -> MockResponse + Send + Sync>;
+
+enum Route {
+    /// Answered in order; the last response repeats once the queue is down to one.
+    Queue(VecDeque<MockResponse>),
+    Handler(Handler),
+}
+
+#[derive(Default)]
+struct MockState {
+    routes: HashMap<String, Route>,
+    requests: Vec<HttpRequest>,
+}
+
+impl MockState {
+    /// Exact path match first, then the longest `prefix*` route.
+    fn next_response(&mut self, request: &HttpRequest) -> MockResponse {
+        let key = if self.routes.contains_key(&request.path) {
+            Some(request.path.clone())
+        } else {
+            self.routes
+                .keys()
+                .filter(|k| {
+                    k.strip_suffix('*')
+                        .is_some_and(|prefix| request.path.starts_with(prefix))
+                })
+                .max_by_key(|k| k.len())
+                .cloned()
+        };
+
+        match key.and_then(|k| self.routes.get_mut(&k)) {
+            Some(Route::Queue(queue)) if queue.len() > 1 => queue.pop_front().unwrap(),
+            Some(Route::Queue(queue)) => queue
+                .front()
+                .cloned()
+                .unwrap_or_else(|| MockResponse::json(404, json!({ "error": "Not found" }))),
+            Some(Route::Handler(handler)) => handler(request),
+            None => MockResponse::json(404, json!({ "error": "Not found" })),
+        }
+    }
+}
+
+pub struct MockApi {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    task: JoinHandle<()>,
+}
+
+impl MockApi {
+    /// Start on a random localhost port.
+    pub async fn start() -> std::io::Result<Self> {
+        Self::start_on("127.0.0.1:0").await
+    }
+
+    pub async fn start_on(addr: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let handler_state = state.clone();
+        let task = tokio::spawn(local_http::serve(listener, move |request: HttpRequest| {
+            let scripted = {
+                let mut state = handler_state.lock().unwrap();
+                state.requests.push(request.clone());
+                state.next_response(&request)
+            };
+            async move {
+                if let Some(delay) = scripted.delay {
+                    tokio::time::sleep(delay).await;
+                }
+                scripted.response
+            }
+        }));
+
+        log::info!("Mock VivaSpot API listening on http://{}", addr);
+        Ok(MockApi { addr, state, task })
+    }
+
+    /// Base URL to hand to `ApiClient::new` or `VIVASPOT_API_URL`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Always answer `path` with `response`. A trailing `*` matches a prefix.
+    pub fn respond(&self, path: &str, response: MockResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .insert(path.to_string(), Route::Queue(VecDeque::from([response])));
+    }
+
+    /// Queue `response` for `path` after any already queued.
+    pub fn enqueue(&self, path: &str, response: MockResponse) {
+        let mut state = self.state.lock().unwrap();
+        match state.routes.get_mut(path) {
+            Some(Route::Queue(queue)) => queue.push_back(response),
+            _ => {
+                state
+                    .routes
+                    .insert(path.to_string(), Route::Queue(VecDeque::from([response])));
+            }
+        }
+    }
+
+    /// Compute the response for `path` from the request itself.
+    pub fn handle<F>(&self, path: &str, handler: F)
+    where
+        F: Fn(&HttpRequest) -> MockResponse + Send + Sync + 'static,
+    {
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .insert(path.to_string(), Route::Handler(Arc::new(handler)));
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Script a small fake site: `VS-TEST` is valid, `VS-EXPD` is expired,
+    /// anything else is invalid. Reports are accepted and the inventory
+    /// lists two APs.
+    pub fn with_default_routes(self) -> Self {
+        let inform_url = format!("http://{}:8080/inform", self.addr.ip());
+        self.handle("/api/setup-code", move |request| {
+            match request.query_param("code").as_deref() {
+                Some(VALID_CODE) => MockResponse::json(
+                    200,
+                    json!({
+                        "informUrl": inform_url,
+                        "siteId": "mock-site",
+                        "siteName": "Mock Site",
+                        "expiresAt": (chrono::Utc::now() + chrono::Duration::days(1)).to_rfc3339(),
+                    }),
+                ),
+                Some(EXPIRED_CODE) => MockResponse::json(
+                    404,
+                    json!({ "error": "This setup code has expired", "expired": true }),
+                ),
+                _ => MockResponse::json(404, json!({ "error": "Invalid setup code" })),
+            }
+        });
+        self.respond("/api/adoption-report", MockResponse::json(200, json!({})));
+        self.respond(
+            "/api/sites/*",
+            MockResponse::json(
+                200,
+                json!({
+                    "siteId": "mock-site",
+                    "devices": [
+                        { "model": "U7LT", "name": "Lobby AP", "location": "Ground floor" },
+                        { "model": "U7LT", "name": "Bar AP", "location": "Ground floor" },
+                    ],
+                }),
+            ),
+        );
+        self
+    }
+}
+
+impl Drop for MockApi {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
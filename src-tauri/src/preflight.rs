@@ -0,0 +1,548 @@
+/// Reachability checks for the inform URL, run before `set-inform`.
+///
+/// An AP pointed at a controller it can't reach just sits in "Adopting",
+/// and nobody notices until much later. These checks resolve the inform
+/// host, open a TCP connection to it and send an HTTP request to confirm
+/// a UniFi inform endpoint answers — from the laptop, and optionally from
+/// the AP itself over SSH (the command and output parsing live here; the
+/// SSH session is the caller's).
+///
+/// The laptop reaching the controller doesn't prove the AP can — APs often
+/// sit on an isolated management VLAN — so the device checks repeat DNS,
+/// add a ping, and POST to the inform URL with the AP's own BusyBox tools.
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::net::TcpStream;
+
+use crate::api;
+use crate::settings::NetworkSettings;
+
+const STEP_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Pass,
+    /// Worth knowing, but doesn't block adoption (e.g. ICMP filtered)
+    Warn,
+    Fail,
+    /// Not run because an earlier check failed
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightCheck {
+    /// `dns`, `ping`, `tcp` or `http`
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightReport {
+    pub inform_url: String,
+    /// `laptop` or `device`
+    pub source: String,
+    pub checks: Vec<PreflightCheck>,
+    pub passed: bool,
+}
+
+impl PreflightReport {
+    fn new(inform_url: &str, source: &str, checks: Vec<PreflightCheck>) -> Self {
+        PreflightReport {
+            inform_url: inform_url.to_string(),
+            source: source.to_string(),
+            passed: !checks.iter().any(|c| c.status == CheckStatus::Fail),
+            checks,
+        }
+    }
+
+    /// One line naming the first failed check, for error messages.
+    pub fn summary(&self) -> String {
+        match self.checks.iter().find(|c| c.status == CheckStatus::Fail) {
+            Some(check) => format!(
+                "Inform URL check failed from the {} ({}): {}",
+                self.source, check.name, check.detail
+            ),
+            None => format!("Inform URL reachable from the {}", self.source),
+        }
+    }
+}
+
+fn check(
+    name: &str,
+    status: CheckStatus,
+    detail: impl Into<String>,
+    started: Instant,
+) -> PreflightCheck {
+    PreflightCheck {
+        name: name.to_string(),
+        status,
+        detail: detail.into(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn skipped(name: &str) -> PreflightCheck {
+    PreflightCheck {
+        name: name.to_string(),
+        status: CheckStatus::Skipped,
+        detail: "Skipped after an earlier failure".to_string(),
+        duration_ms: 0,
+    }
+}
+
+/// Host and port of an inform URL, e.g. `("unifi.example.com", 8080)`.
+pub fn inform_target(inform_url: &str) -> Result<(String, u16), String> {
+    let url = url::Url::parse(inform_url).map_err(|e| format!("Invalid inform URL: {}", e))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| "Inform URL has no host".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| "Inform URL has no port".to_string())?;
+    Ok((host, port))
+}
+
+/// Resolve, connect to and query the inform endpoint from this computer,
+/// through the proxy and CAs in `network`.
+pub async fn check_from_laptop(inform_url: &str, network: &NetworkSettings) -> PreflightReport {
+    let step_timeout = Duration::from_secs(STEP_TIMEOUT_SECS);
+    let mut checks = Vec::new();
+
+    let (host, port) = match inform_target(inform_url) {
+        Ok(target) => target,
+        Err(msg) => {
+            checks.push(check("dns", CheckStatus::Fail, msg, Instant::now()));
+            checks.extend(["tcp", "http"].map(skipped));
+            return PreflightReport::new(inform_url, "laptop", checks);
+        }
+    };
+
+    // DNS
+    let started = Instant::now();
+    let lookup =
+        tokio::time::timeout(step_timeout, tokio::net::lookup_host((host.as_str(), port))).await;
+    let addrs: Vec<SocketAddr> = match lookup {
+        Ok(Ok(addrs)) => addrs.collect(),
+        Ok(Err(e)) => {
+            checks.push(check(
+                "dns",
+                CheckStatus::Fail,
+                format!("Can't resolve {}: {}", host, e),
+                started,
+            ));
+            checks.extend(["tcp", "http"].map(skipped));
+            return PreflightReport::new(inform_url, "laptop", checks);
+        }
+        Err(_) => {
+            checks.push(check(
+                "dns",
+                CheckStatus::Fail,
+                format!("Timed out resolving {}", host),
+                started,
+            ));
+            checks.extend(["tcp", "http"].map(skipped));
+            return PreflightReport::new(inform_url, "laptop", checks);
+        }
+    };
+    let listed: Vec<String> = addrs.iter().map(|a| a.ip().to_string()).collect();
+    checks.push(check(
+        "dns",
+        CheckStatus::Pass,
+        format!("{} → {}", host, listed.join(", ")),
+        started,
+    ));
+
+    // TCP: the first address that accepts wins
+    let started = Instant::now();
+    let mut last_error = String::new();
+    let mut connected = None;
+    for addr in &addrs {
+        match tokio::time::timeout(step_timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => {
+                connected = Some(*addr);
+                break;
+            }
+            Ok(Err(e)) => last_error = format!("{}: {}", addr, e),
+            Err(_) => last_error = format!("{}: timed out", addr),
+        }
+    }
+    match connected {
+        Some(addr) => checks.push(check(
+            "tcp",
+            CheckStatus::Pass,
+            format!("Connected to {}", addr),
+            started,
+        )),
+        None => {
+            checks.push(check(
+                "tcp",
+                CheckStatus::Fail,
+                format!(
+                    "Can't connect to port {} ({}). A firewall may be blocking it.",
+                    port, last_error
+                ),
+                started,
+            ));
+            checks.push(skipped("http"));
+            return PreflightReport::new(inform_url, "laptop", checks);
+        }
+    }
+
+    // HTTP: with the same proxy and CAs as every other request from here.
+    // The AP's own path is what the device checks are for.
+    let started = Instant::now();
+    let http = api::http_client_builder(network)
+        .map_err(|e| e.to_string())
+        .and_then(|builder| {
+            // A redirect is itself the answer worth classifying
+            builder
+                .timeout(step_timeout)
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .map_err(|e| e.to_string())
+        });
+    let response = match http {
+        Ok(http) => http
+            .post(inform_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-binary")
+            .send()
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    checks.push(match response {
+        Ok(response) => {
+            let (status, detail) = classify_http_status(response.status().as_u16());
+            check("http", status, detail, started)
+        }
+        Err(e) => check(
+            "http",
+            CheckStatus::Fail,
+            format!("No HTTP response: {}", e),
+            started,
+        ),
+    });
+
+    let report = PreflightReport::new(inform_url, "laptop", checks);
+    log::info!("Preflight for {}: {}", inform_url, report.summary());
+    report
+}
+
+/// A UniFi controller answers an inform request without a valid packet
+/// with 400, or 405 for the wrong method. Anything else came from something
+/// else: redirects and 407s from captive portals and proxies, 5xx from a
+/// proxy in front of a controller that's down.
+pub fn classify_http_status(status: u16) -> (CheckStatus, String) {
+    let (check_status, detail) = match status {
+        400 | 405 => (CheckStatus::Pass, "Inform endpoint answered"),
+        404 => (
+            CheckStatus::Fail,
+            "Something answered, but it has no inform endpoint. Check the inform URL path.",
+        ),
+        300..=399 => (
+            CheckStatus::Warn,
+            "Redirected. A captive portal or proxy may be intercepting the connection.",
+        ),
+        407 => (
+            CheckStatus::Warn,
+            "A proxy wants a login before it lets the request through.",
+        ),
+        500..=599 => (
+            CheckStatus::Warn,
+            "Server error, probably from a proxy in front of the controller. The controller may be down.",
+        ),
+        _ => (
+            CheckStatus::Warn,
+            "Something answered, but not the way an inform endpoint does.",
+        ),
+    };
+    (check_status, format!("HTTP {}: {}", status, detail))
+}
+
+/// Markers the device command prints around each step's output.
+const SECTION_MARKER: &str = "VS_CHECK=";
+const EXIT_MARKER: &str = "VS_EXIT=";
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Shell command that runs the DNS, ping and HTTP checks on the AP with its
+/// BusyBox tools, tagging each step's output and exit status.
+pub fn device_command(inform_url: &str) -> Result<String, String> {
+    let (host, _) = inform_target(inform_url)?;
+    let mut steps = Vec::new();
+    // An IP literal needs no lookup
+    if host.parse::<IpAddr>().is_err() {
+        steps.push(("dns", format!("nslookup {}", shell_quote(&host))));
+    }
+    steps.push(("ping", format!("ping -c 3 -W 2 {}", shell_quote(&host))));
+    steps.push((
+        "http",
+        format!(
+            "wget -q -O /dev/null -T {} --post-data '' {}",
+            STEP_TIMEOUT_SECS,
+            shell_quote(inform_url)
+        ),
+    ));
+
+    Ok(steps
+        .into_iter()
+        .map(|(name, cmd)| {
+            format!(
+                "echo {section}{name}; {cmd} 2>&1; echo \"{exit}$?\"",
+                section = SECTION_MARKER,
+                exit = EXIT_MARKER,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+/// Output and exit status of one step of `device_command`.
+#[derive(Debug, Default)]
+struct Section {
+    output: String,
+    exit: Option<i32>,
+}
+
+fn split_sections(output: &str) -> HashMap<String, Section> {
+    let mut sections: HashMap<String, Section> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix(SECTION_MARKER) {
+            current = Some(name.to_string());
+            sections.entry(name.to_string()).or_default();
+        } else if let Some(code) = line.strip_prefix(EXIT_MARKER) {
+            if let Some(section) = current.take().and_then(|name| sections.get_mut(&name)) {
+                section.exit = code.parse().ok();
+            }
+        } else if let Some(section) = current.as_ref().and_then(|name| sections.get_mut(name)) {
+            if !line.is_empty() {
+                if !section.output.is_empty() {
+                    section.output.push(' ');
+                }
+                section.output.push_str(line);
+            }
+        }
+    }
+    sections
+}
+
+/// Interpret the output of `device_command` as one check per step.
+pub fn parse_device_output(inform_url: &str, output: &str) -> PreflightReport {
+    let started = Instant::now();
+    let host = inform_target(inform_url)
+        .map(|(host, _)| host)
+        .unwrap_or_default();
+    let sections = split_sections(output);
+    let mut checks = Vec::new();
+
+    // DNS
+    let dns_ok = match sections.get("dns") {
+        None if host.parse::<IpAddr>().is_ok() => {
+            checks.push(check(
+                "dns",
+                CheckStatus::Pass,
+                "Inform URL uses an IP address",
+                started,
+            ));
+            true
+        }
+        Some(Section {
+            output,
+            exit: Some(0),
+        }) => {
+            let addresses = nslookup_addresses(output);
+            let detail = if addresses.is_empty() {
+                format!("{} resolves", host)
+            } else {
+                format!("{} → {}", host, addresses.join(", "))
+            };
+            checks.push(check("dns", CheckStatus::Pass, detail, started));
+            true
+        }
+        Some(Section {
+            output,
+            exit: Some(_),
+        }) => {
+            checks.push(check(
+                "dns",
+                CheckStatus::Fail,
+                format!(
+                    "The AP can't resolve {}. Check the DNS server it gets from DHCP. ({})",
+                    host, output
+                ),
+                started,
+            ));
+            false
+        }
+        _ => {
+            checks.push(check(
+                "dns",
+                CheckStatus::Fail,
+                format!("Couldn't run nslookup on the AP: {}", output.trim()),
+                started,
+            ));
+            false
+        }
+    };
+    if !dns_ok {
+        checks.extend(["ping", "tcp", "http"].map(skipped));
+        return PreflightReport::new(inform_url, "device", checks);
+    }
+
+    // Ping is informational: plenty of networks filter ICMP
+    checks.push(match sections.get("ping") {
+        Some(Section {
+            output,
+            exit: Some(0),
+        }) => check("ping", CheckStatus::Pass, ping_summary(output), started),
+        Some(Section { output, .. }) if !output.is_empty() => check(
+            "ping",
+            CheckStatus::Warn,
+            format!(
+                "No reply to ping ({}). ICMP may be filtered — fine if the HTTP check passes.",
+                ping_summary(output)
+            ),
+            started,
+        ),
+        _ => check(
+            "ping",
+            CheckStatus::Warn,
+            "No reply to ping. ICMP may be filtered — fine if the HTTP check passes.",
+            started,
+        ),
+    });
+
+    checks.extend(classify_wget(sections.get("http"), started));
+    PreflightReport::new(inform_url, "device", checks)
+}
+
+/// TCP and HTTP checks from BusyBox wget's output and exit status.
+fn classify_wget(section: Option<&Section>, started: Instant) -> [PreflightCheck; 2] {
+    let connected = || {
+        check(
+            "tcp",
+            CheckStatus::Pass,
+            "Connected to the inform port",
+            started,
+        )
+    };
+    let Some(Section {
+        output,
+        exit: Some(exit),
+    }) = section
+    else {
+        return [
+            skipped("tcp"),
+            check(
+                "http",
+                CheckStatus::Fail,
+                "Couldn't run wget on the AP",
+                started,
+            ),
+        ];
+    };
+    let lower = output.to_lowercase();
+
+    if *exit == 0 {
+        return [
+            connected(),
+            check(
+                "http",
+                CheckStatus::Pass,
+                "Inform endpoint answered",
+                started,
+            ),
+        ];
+    }
+    if lower.contains("server returned error") {
+        let status = output
+            .split_whitespace()
+            .find_map(|word| word.parse::<u16>().ok())
+            .unwrap_or(400);
+        let (status, detail) = classify_http_status(status);
+        return [connected(), check("http", status, detail, started)];
+    }
+    let tcp_failure = if lower.contains("bad address") || lower.contains("unknown host") {
+        Some("wget on the AP couldn't resolve the inform host".to_string())
+    } else if lower.contains("refused") {
+        Some("Connection refused from the AP. Nothing is listening on the inform port.".to_string())
+    } else if lower.contains("timed out")
+        || lower.contains("unreachable")
+        || lower.contains("no route")
+    {
+        Some(format!(
+            "The AP can't reach the inform port ({}). Its VLAN or firewall may be blocking it.",
+            output
+        ))
+    } else {
+        None
+    };
+    match tcp_failure {
+        Some(detail) => [
+            check("tcp", CheckStatus::Fail, detail, started),
+            skipped("http"),
+        ],
+        None => [
+            skipped("tcp"),
+            check(
+                "http",
+                CheckStatus::Fail,
+                format!("wget exited with {}: {}", exit, output),
+                started,
+            ),
+        ],
+    }
+}
+
+/// Addresses from BusyBox nslookup output, skipping the DNS server's own
+/// (which comes before the `Name:` line).
+fn nslookup_addresses(output: &str) -> Vec<String> {
+    let Some(answer) = output.find("Name:").map(|idx| &output[idx..]) else {
+        return Vec::new();
+    };
+    let mut addresses: Vec<String> = answer
+        .split_whitespace()
+        .filter_map(|word| word.trim_end_matches(',').parse::<IpAddr>().ok())
+        .map(|ip| ip.to_string())
+        .collect();
+    addresses.dedup();
+    addresses
+}
+
+/// "3/3 replies, avg 1.9 ms" from BusyBox ping statistics.
+fn ping_summary(output: &str) -> String {
+    // "3 packets transmitted, 3 packets received": the number two words before
+    let words: Vec<&str> = output.split_whitespace().collect();
+    let count = |label: &str| {
+        let idx = words
+            .iter()
+            .position(|w| w.trim_end_matches(',') == label)?;
+        words.get(idx.checked_sub(2)?)?.parse::<u32>().ok()
+    };
+    let replies = match (count("received"), count("transmitted")) {
+        (Some(received), Some(sent)) => format!("{}/{} replies", received, sent),
+        _ => "no statistics".to_string(),
+    };
+    // round-trip min/avg/max = 1.2/1.9/2.6 ms
+    let avg = output
+        .split("= ")
+        .nth(1)
+        .and_then(|rest| rest.split('/').nth(1))
+        .map(|avg| format!(", avg {} ms", avg));
+    format!("{}{}", replies, avg.unwrap_or_default())
+}
@@ -0,0 +1,391 @@
+/// TFTP recovery flashing for soft-bricked APs.
+///
+/// A UniFi AP held in recovery mode (reset button held through power-up)
+/// comes up on a fixed address with a TFTP server that accepts a firmware
+/// image and flashes it. The laptop needs an address in the same subnet,
+/// e.g. 192.168.1.25/24, and then writes the image with a plain TFTP
+/// `put` in binary mode.
+///
+/// The client speaks RFC 1350 with the RFC 2348 `blksize` option: larger
+/// blocks make a 30 MB image much quicker, but recovery bootloaders that
+/// don't understand options are handled by falling back to 512-byte
+/// blocks.
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::net::UdpSocket;
+
+/// Where UniFi APs listen in recovery mode.
+pub const DEFAULT_RECOVERY_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 20);
+pub const TFTP_PORT: u16 = 69;
+/// Flashing and the first boot take a few minutes.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 600;
+pub const POLL_INTERVAL_SECS: u64 = 5;
+
+const OP_RRQ: u16 = 1;
+const OP_WRQ: u16 = 2;
+const OP_DATA: u16 = 3;
+const OP_ACK: u16 = 4;
+const OP_ERROR: u16 = 5;
+const OP_OACK: u16 = 6;
+/// RFC 2347: the server refused the requested options
+const ERROR_OPTION_REFUSED: u16 = 8;
+/// RFC 1350's block size, used when options aren't understood
+pub const DEFAULT_BLOCK_SIZE: u16 = 512;
+/// Block numbers are 16 bits and start at 1; recovery bootloaders don't
+/// agree on what comes after 65535, so bigger transfers aren't attempted.
+pub const MAX_BLOCKS: u64 = u16::MAX as u64;
+/// Largest block that fits an Ethernet frame without fragmenting
+pub const MAX_ETHERNET_BLOCK_SIZE: u16 = 1468;
+
+#[derive(Debug)]
+pub enum TftpError {
+    Io(String),
+    /// No answer after every retry
+    Timeout(String),
+    /// The server sent an ERROR packet
+    Remote {
+        code: u16,
+        message: String,
+    },
+    Protocol(String),
+    /// The image needs more blocks than TFTP can number
+    TooLarge(String),
+}
+
+impl std::fmt::Display for TftpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TftpError::Io(msg) => write!(f, "TFTP socket error: {}", msg),
+            TftpError::Timeout(msg) => write!(f, "TFTP timed out: {}", msg),
+            TftpError::Remote { code, message } => {
+                write!(
+                    f,
+                    "Device refused the transfer (error {}): {}",
+                    code, message
+                )
+            }
+            TftpError::Protocol(msg) => write!(f, "Unexpected TFTP reply: {}", msg),
+            TftpError::TooLarge(msg) => write!(f, "Image too large for TFTP: {}", msg),
+        }
+    }
+}
+
+impl From<std::io::Error> for TftpError {
+    fn from(e: std::io::Error) -> Self {
+        TftpError::Io(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TftpOptions {
+    /// Block size to ask for; the server may negotiate it down
+    pub block_size: u16,
+    /// Wait for each ACK before resending
+    pub timeout: Duration,
+    /// Resends of a single packet before giving up
+    pub retries: u32,
+}
+
+impl Default for TftpOptions {
+    fn default() -> Self {
+        TftpOptions {
+            block_size: MAX_ETHERNET_BLOCK_SIZE,
+            timeout: Duration::from_secs(2),
+            retries: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferStats {
+    pub bytes: u64,
+    pub blocks: u64,
+    /// Block size actually used
+    pub block_size: u16,
+    /// Packets sent again after a timeout
+    pub retransmits: u32,
+    pub duration_ms: u64,
+}
+
+/// A received TFTP packet.
+#[derive(Debug)]
+enum Packet {
+    Ack(u16),
+    Oack(Vec<(String, String)>),
+    Error(u16, String),
+    Data,
+    Other,
+}
+
+fn parse(bytes: &[u8]) -> Result<Packet, TftpError> {
+    if bytes.len() < 2 {
+        return Err(TftpError::Protocol(format!("{}-byte packet", bytes.len())));
+    }
+    let opcode = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let number = || {
+        bytes
+            .get(2..4)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| TftpError::Protocol("truncated packet".to_string()))
+    };
+    Ok(match opcode {
+        OP_ACK => Packet::Ack(number()?),
+        OP_DATA => Packet::Data,
+        OP_ERROR => {
+            let message = bytes.get(4..).unwrap_or_default();
+            let message = message.split(|&b| b == 0).next().unwrap_or_default();
+            Packet::Error(number()?, String::from_utf8_lossy(message).to_string())
+        }
+        OP_OACK => {
+            let fields: Vec<String> = bytes[2..]
+                .split(|&b| b == 0)
+                .map(|f| String::from_utf8_lossy(f).to_lowercase())
+                .collect();
+            Packet::Oack(
+                fields
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0].clone(), pair[1].clone()))
+                    .collect(),
+            )
+        }
+        _ => Packet::Other,
+    })
+}
+
+fn request(opcode: u16, filename: &str, options: &[(&str, String)]) -> Vec<u8> {
+    let mut packet = opcode.to_be_bytes().to_vec();
+    for field in [filename, "octet"] {
+        packet.extend_from_slice(field.as_bytes());
+        packet.push(0);
+    }
+    for (name, value) in options {
+        packet.extend_from_slice(name.as_bytes());
+        packet.push(0);
+        packet.extend_from_slice(value.as_bytes());
+        packet.push(0);
+    }
+    packet
+}
+
+/// Send `packet` to `to` and wait for a reply, resending on timeout.
+/// Returns the reply and the address it came from.
+async fn exchange(
+    socket: &UdpSocket,
+    packet: &[u8],
+    to: SocketAddr,
+    from: Option<SocketAddr>,
+    options: &TftpOptions,
+    retransmits: &mut u32,
+    accept: impl Fn(&Packet) -> bool,
+) -> Result<(Packet, SocketAddr), TftpError> {
+    let mut buf = vec![0u8; 4 + MAX_ETHERNET_BLOCK_SIZE as usize + 512];
+    for attempt in 0..=options.retries {
+        if attempt > 0 {
+            *retransmits += 1;
+        }
+        socket.send_to(packet, to).await?;
+        let deadline = tokio::time::Instant::now() + options.timeout;
+        loop {
+            let received = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await;
+            let Ok(received) = received else { break };
+            let (len, peer) = received?;
+            // Stray packets from anything but the transfer's peer are ignored
+            if from.is_some_and(|from| from != peer) || peer.ip() != to.ip() {
+                continue;
+            }
+            let reply = parse(&buf[..len])?;
+            if let Packet::Error(code, message) = reply {
+                return Err(TftpError::Remote { code, message });
+            }
+            if accept(&reply) {
+                return Ok((reply, peer));
+            }
+            // Duplicate ACKs for earlier blocks: keep waiting for ours
+        }
+    }
+    Err(TftpError::Timeout(format!(
+        "no answer from {} after {} attempts",
+        to.ip(),
+        options.retries + 1
+    )))
+}
+
+/// Blocks needed for `len` bytes, counting the short or empty last one.
+fn blocks_needed(len: usize, block_size: u16) -> u64 {
+    (len / block_size as usize) as u64 + 1
+}
+
+fn check_size(len: usize, block_size: u16) -> Result<(), TftpError> {
+    let blocks = blocks_needed(len, block_size);
+    if blocks > MAX_BLOCKS {
+        return Err(TftpError::TooLarge(format!(
+            "{} bytes need {} blocks of {} bytes, at most {} are possible",
+            len, blocks, block_size, MAX_BLOCKS
+        )));
+    }
+    Ok(())
+}
+
+/// Write `data` to `server` as `filename` (binary mode). `progress` is
+/// called with (bytes acknowledged, total) after every block. Fails with
+/// [`TftpError::TooLarge`] if the image needs more than [`MAX_BLOCKS`]
+/// blocks at the negotiated size.
+pub async fn put(
+    server: SocketAddr,
+    filename: &str,
+    data: &[u8],
+    options: &TftpOptions,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<TransferStats, TftpError> {
+    let bind: SocketAddr = match server.ip() {
+        IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        IpAddr::V6(_) => "[::]:0".parse().unwrap(),
+    };
+    // Too big even at the size we'll ask for: don't start at all
+    check_size(data.len(), options.block_size.max(DEFAULT_BLOCK_SIZE))?;
+    let socket = UdpSocket::bind(bind).await?;
+    let started = Instant::now();
+    let mut retransmits = 0;
+
+    let with_option = options.block_size != DEFAULT_BLOCK_SIZE;
+    let negotiate = [("blksize", options.block_size.to_string())];
+    let first = exchange(
+        &socket,
+        &request(OP_WRQ, filename, if with_option { &negotiate } else { &[] }),
+        server,
+        None,
+        options,
+        &mut retransmits,
+        |reply| matches!(reply, Packet::Ack(0) | Packet::Oack(_)),
+    )
+    .await;
+    let (reply, peer) = match first {
+        // Bootloaders that don't do options may refuse them outright
+        Err(TftpError::Remote { code, .. }) if with_option && code == ERROR_OPTION_REFUSED => {
+            log::info!(
+                "{} refused blksize, using {}",
+                server.ip(),
+                DEFAULT_BLOCK_SIZE
+            );
+            exchange(
+                &socket,
+                &request(OP_WRQ, filename, &[]),
+                server,
+                None,
+                options,
+                &mut retransmits,
+                |reply| matches!(reply, Packet::Ack(0)),
+            )
+            .await?
+        }
+        other => other?,
+    };
+
+    let block_size = match reply {
+        Packet::Oack(fields) => fields
+            .iter()
+            .find(|(name, _)| name == "blksize")
+            .and_then(|(_, value)| value.parse::<u16>().ok())
+            .filter(|&size| size >= 8 && size <= options.block_size)
+            .unwrap_or(DEFAULT_BLOCK_SIZE),
+        _ => DEFAULT_BLOCK_SIZE,
+    };
+    if let Err(e) = check_size(data.len(), block_size) {
+        // Tell the device so it stops waiting for data
+        let mut abort = OP_ERROR.to_be_bytes().to_vec();
+        abort.extend_from_slice(&0u16.to_be_bytes());
+        abort.extend_from_slice(b"File too large\0");
+        let _ = socket.send_to(&abort, peer).await;
+        return Err(e);
+    }
+    log::info!(
+        "TFTP transfer of {} ({} bytes) to {} with {}-byte blocks",
+        filename,
+        data.len(),
+        peer,
+        block_size
+    );
+
+    let total = data.len() as u64;
+    let mut chunks = data.chunks(block_size as usize);
+    let mut block: u16 = 0;
+    let mut blocks: u64 = 0;
+    let mut sent: u64 = 0;
+    // A final short (possibly empty) block marks the end of the file
+    let needs_empty_last = data.len() % block_size as usize == 0;
+    loop {
+        let chunk = match chunks.next() {
+            Some(chunk) => chunk,
+            None if needs_empty_last => &[][..],
+            None => break,
+        };
+        block += 1;
+        let mut packet = OP_DATA.to_be_bytes().to_vec();
+        packet.extend_from_slice(&block.to_be_bytes());
+        packet.extend_from_slice(chunk);
+
+        let expected = block;
+        exchange(
+            &socket,
+            &packet,
+            peer,
+            Some(peer),
+            options,
+            &mut retransmits,
+            |reply| matches!(reply, Packet::Ack(n) if *n == expected),
+        )
+        .await?;
+        blocks += 1;
+        sent += chunk.len() as u64;
+        progress(sent, total);
+
+        if chunk.len() < block_size as usize {
+            break;
+        }
+    }
+
+    Ok(TransferStats {
+        bytes: sent,
+        blocks,
+        block_size,
+        retransmits,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Whether a TFTP server answers at `server`. Reads a file that doesn't
+/// exist: any reply, usually "file not found", means something is there.
+pub async fn probe(server: SocketAddr, timeout: Duration) -> bool {
+    let Ok(socket) = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await else {
+        return false;
+    };
+    let options = TftpOptions {
+        timeout,
+        retries: 1,
+        ..TftpOptions::default()
+    };
+    let mut retransmits = 0;
+    let result = exchange(
+        &socket,
+        &request(OP_RRQ, "vivaspot-probe", &[]),
+        server,
+        None,
+        &options,
+        &mut retransmits,
+        |reply| matches!(reply, Packet::Data | Packet::Oack(_) | Packet::Other),
+    )
+    .await;
+    !matches!(result, Err(TftpError::Timeout(_) | TftpError::Io(_)))
+}
+
+/// Whether `local` can reach `device` directly: same /24, which is what
+/// recovery mode needs since the bootloader has no gateway.
+pub fn same_subnet(local: IpAddr, device: IpAddr) -> bool {
+    match (local, device) {
+        (IpAddr::V4(local), IpAddr::V4(device)) => local.octets()[..3] == device.octets()[..3],
+        _ => false,
+    }
+}
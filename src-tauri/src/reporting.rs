@@ -0,0 +1,163 @@
+/// Durable queue of adoption reports for the VivaSpot back office.
+///
+/// Every adoption attempt is posted to the API straight away. If that fails
+/// (no internet on site, API cold-starting) the report is appended to a JSON
+/// file in the app data directory and retried by a background task until
+/// it goes through, so site dashboards eventually see every attempt.
+/// Reports the API rejects outright (a 4xx) are dropped instead, since
+/// retrying them can't help and would hold up every report behind them.
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::api::{AdoptionReport, ApiClient};
+
+pub const QUEUE_FILE_NAME: &str = "report_queue.json";
+/// How often queued reports are retried.
+pub const RETRY_INTERVAL_SECS: u64 = 60;
+/// Oldest reports are dropped beyond this so a long offline stretch can't
+/// grow the file without bound.
+pub const MAX_QUEUED_REPORTS: usize = 500;
+
+/// Device and site details the frontend passes along with an adoption.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdoptionContext {
+    pub setup_code: String,
+    pub site_id: String,
+    pub mac: String,
+    pub model: String,
+    pub firmware: String,
+}
+
+impl AdoptionContext {
+    /// Build the report for one attempt. `error_code` is `None` on success.
+    pub fn report(&self, error_code: Option<&str>) -> AdoptionReport {
+        let now = chrono::Utc::now();
+        AdoptionReport {
+            id: format!("{}-{}", self.mac.replace(':', ""), now.timestamp_millis()),
+            setup_code: self.setup_code.clone(),
+            site_id: self.site_id.clone(),
+            mac: self.mac.clone(),
+            model: self.model.clone(),
+            firmware: self.firmware.clone(),
+            result: if error_code.is_none() {
+                "success"
+            } else {
+                "failed"
+            }
+            .to_string(),
+            error_code: error_code.map(str::to_string),
+            attempted_at: now.to_rfc3339(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+pub struct ReportQueue {
+    path: PathBuf,
+    pending: Mutex<Vec<AdoptionReport>>,
+}
+
+impl ReportQueue {
+    /// Load any reports left over from a previous run.
+    pub fn load(path: PathBuf) -> Self {
+        let pending = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!(
+                    "Discarding unreadable report queue {}: {}",
+                    path.display(),
+                    e
+                );
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        ReportQueue {
+            path,
+            pending: Mutex::new(pending),
+        }
+    }
+
+    /// Reports waiting to be delivered, oldest first.
+    pub fn pending(&self) -> Vec<AdoptionReport> {
+        self.pending.lock().unwrap().clone()
+    }
+
+    /// Append a report that couldn't be delivered.
+    pub fn push(&self, report: AdoptionReport) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.push(report);
+        if pending.len() > MAX_QUEUED_REPORTS {
+            let excess = pending.len() - MAX_QUEUED_REPORTS;
+            log::warn!("Report queue full, dropping {} oldest report(s)", excess);
+            pending.drain(..excess);
+        }
+        persist(&self.path, &pending);
+    }
+
+    /// Try to deliver every queued report, oldest first. Stops at the first
+    /// transient failure, since the rest would fail the same way; rejected
+    /// reports are dropped. Returns how many were sent.
+    pub async fn flush(&self, client: &ApiClient) -> usize {
+        let snapshot = self.pending.lock().unwrap().clone();
+        if snapshot.is_empty() {
+            return 0;
+        }
+
+        let mut sent = 0;
+        let mut done = Vec::new();
+        for report in &snapshot {
+            match client.report_adoption(report).await {
+                Ok(()) => sent += 1,
+                Err(e) if e.is_transient() => {
+                    log::warn!("Queued report {} still undeliverable: {}", report.id, e);
+                    break;
+                }
+                Err(e) => log::warn!("Dropping queued report {}, rejected: {}", report.id, e),
+            }
+            done.push(report.id.clone());
+        }
+
+        if !done.is_empty() {
+            let mut pending = self.pending.lock().unwrap();
+            pending.retain(|r| !done.contains(&r.id));
+            persist(&self.path, &pending);
+            log::info!(
+                "Delivered {} queued report(s), {} remaining",
+                sent,
+                pending.len()
+            );
+        }
+        sent
+    }
+}
+
+/// Post a report now, queueing it for retry if the API can't be reached.
+pub async fn submit(client: &ApiClient, queue: &ReportQueue, report: AdoptionReport) {
+    match client.report_adoption(&report).await {
+        Ok(()) => {}
+        Err(e) if e.is_transient() => {
+            log::warn!(
+                "Failed to report adoption of {}, queueing: {}",
+                report.mac,
+                e
+            );
+            queue.push(report);
+        }
+        Err(e) => log::warn!("Adoption report for {} was rejected: {}", report.mac, e),
+    }
+}
+
+fn persist(path: &Path, pending: &[AdoptionReport]) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| {
+            let json = serde_json::to_string_pretty(pending).map_err(std::io::Error::other)?;
+            std::fs::write(path, json)
+        });
+    if let Err(e) = result {
+        log::error!("Failed to persist report queue {}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,62 @@
+/// Factory reset over SSH.
+///
+/// A device adopted by another controller has to be reset before it can
+/// be adopted again. `syswrapper.sh restore-default` is the usual way to do
+/// that from a shell, with `set-default` on older firmware. Both reboot
+/// the device straight away, which would cut the SSH session before it
+/// can report anything, so the reset runs in the background after a short
+/// delay and the command only reports which tool it's about to use.
+///
+/// Afterwards the caller watches discovery until the device answers again
+/// with its default flag set.
+use serde::Serialize;
+
+use crate::ssh::{background_after, REBOOT_DELAY_SECS};
+
+/// How long to wait for the device to come back after a reset.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+/// Pause between discovery scans while waiting.
+pub const POLL_INTERVAL_SECS: u64 = 5;
+
+const MARKER: &str = "VS_RESET=";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResetMethod {
+    RestoreDefault,
+    SetDefault,
+}
+
+impl ResetMethod {
+    fn marker(&self) -> &'static str {
+        match self {
+            ResetMethod::RestoreDefault => "restore-default",
+            ResetMethod::SetDefault => "set-default",
+        }
+    }
+}
+
+/// Shell command that schedules the reset and says which tool it used.
+pub fn reset_command() -> String {
+    format!(
+        "if command -v syswrapper.sh >/dev/null 2>&1; then echo {MARKER}{}; {} \
+         elif command -v set-default >/dev/null 2>&1; then echo {MARKER}{}; {} \
+         else echo {MARKER}none; fi",
+        ResetMethod::RestoreDefault.marker(),
+        background_after(REBOOT_DELAY_SECS, "syswrapper.sh restore-default"),
+        ResetMethod::SetDefault.marker(),
+        background_after(REBOOT_DELAY_SECS, "set-default"),
+    )
+}
+
+/// Which tool the reset was scheduled with, from [`reset_command`]'s output.
+pub fn parse_output(output: &str) -> Result<ResetMethod, String> {
+    let marker = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(MARKER))
+        .ok_or_else(|| format!("Unexpected output: {}", output.trim()))?;
+    [ResetMethod::RestoreDefault, ResetMethod::SetDefault]
+        .into_iter()
+        .find(|method| method.marker() == marker)
+        .ok_or_else(|| "This firmware has no factory reset command".to_string())
+}
@@ -0,0 +1,138 @@
+/// Persistent app settings stored as JSON in the app config directory.
+///
+/// Holds the named API environment profiles (production, staging, local)
+/// and which one is active. GUI launches can't set `VIVASPOT_API_URL`, so
+/// this is how testers point the app at staging — either through the
+/// settings commands or a `vivaspot://...&profile=staging` deep link, which
+/// the frontend only applies after the user confirms it.
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::api::DEFAULT_API_BASE;
+
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+const DEFAULT_PROFILE: &str = "production";
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_READ_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub name: String,
+    pub api_base: String,
+}
+
+/// HTTP client options for networks with intercepting proxies or slow links.
+/// System proxy settings are always honoured; `proxy_url` overrides them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    pub proxy_url: Option<String>,
+    /// PEM file with extra root CAs (e.g. an enterprise proxy's private CA)
+    pub ca_bundle_path: Option<String>,
+    pub connect_timeout_secs: u64,
+    pub read_timeout_secs: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            proxy_url: None,
+            ca_bundle_path: None,
+            connect_timeout_secs: DEFAULT_CONNECT_TIMEOUT_SECS,
+            read_timeout_secs: DEFAULT_READ_TIMEOUT_SECS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    pub active_profile: String,
+    pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub network: NetworkSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles: vec![
+                Profile {
+                    name: DEFAULT_PROFILE.to_string(),
+                    api_base: DEFAULT_API_BASE.to_string(),
+                },
+                Profile {
+                    name: "staging".to_string(),
+                    api_base: "https://ubiquitywizard-staging.onrender.com".to_string(),
+                },
+                Profile {
+                    name: "local".to_string(),
+                    api_base: "http://localhost:3000".to_string(),
+                },
+            ],
+            network: NetworkSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// The active profile, falling back to the first one if the stored
+    /// name no longer exists.
+    pub fn active(&self) -> Option<&Profile> {
+        self.profiles
+            .iter()
+            .find(|p| p.name == self.active_profile)
+            .or_else(|| self.profiles.first())
+    }
+
+    /// Base URL for the VivaSpot API.
+    /// `VIVASPOT_API_URL` still wins so developers can override from a shell.
+    pub fn api_base(&self) -> String {
+        if let Ok(url) = std::env::var("VIVASPOT_API_URL") {
+            return url;
+        }
+        self.active()
+            .map(|p| p.api_base.clone())
+            .unwrap_or_else(|| DEFAULT_API_BASE.to_string())
+    }
+
+    /// Switch to the named profile.
+    pub fn set_active(&mut self, name: &str) -> Result<(), String> {
+        if !self.profiles.iter().any(|p| p.name == name) {
+            return Err(format!("Unknown profile: {}", name));
+        }
+        self.active_profile = name.to_string();
+        log::info!("Active API profile set to {}", name);
+        Ok(())
+    }
+}
+
+/// Load settings from `path`. Missing or unreadable files yield defaults
+/// so a corrupt file never stops the app from starting.
+pub fn load(path: &Path) -> Settings {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid settings file {}: {}", path.display(), e);
+            Settings::default()
+        }),
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to read settings file {}: {}", path.display(), e);
+            }
+            Settings::default()
+        }
+    }
+}
+
+/// Write settings to `path`, creating the config directory if needed.
+pub fn save(path: &Path, settings: &Settings) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write settings: {}", e))
+}
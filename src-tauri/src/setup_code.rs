@@ -0,0 +1,69 @@
+/// Local normalisation and format checks for VivaSpot setup codes.
+///
+/// Codes look like `VS-7K2M`. Technicians type them by hand (or paste
+/// them from a chat message), so case, whitespace and dash variants are
+/// normalised, a missing `VS-` prefix is added, and anything that can't be
+/// a setup code is rejected without a network round trip.
+use crate::api::ApiError;
+
+pub const PREFIX: &str = "VS";
+const BODY_LEN: usize = 4;
+/// Set once the backend issues codes with a trailing Luhn mod 36 check
+/// character (`VS-7K2MD`). Typos are then caught locally.
+const HAS_CHECK_CHAR: bool = false;
+const ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Dashes people actually type or paste: ASCII hyphen, Unicode hyphens,
+/// en/em dashes and minus.
+fn is_dash(c: char) -> bool {
+    matches!(c, '-' | '\u{2010}'..='\u{2015}' | '\u{2212}')
+}
+
+/// Normalise `raw` to canonical `VS-XXXX` form, or explain why it isn't a
+/// setup code.
+pub fn normalize(raw: &str) -> Result<String, ApiError> {
+    let compact: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && !is_dash(*c))
+        .flat_map(char::to_uppercase)
+        .collect();
+
+    let body_len = BODY_LEN + usize::from(HAS_CHECK_CHAR);
+    let body = if compact.len() == PREFIX.len() + body_len {
+        compact.strip_prefix(PREFIX).unwrap_or(&compact)
+    } else {
+        &compact
+    };
+
+    if body.len() != body_len || !body.bytes().all(|b| ALPHABET.contains(&b)) {
+        return Err(ApiError::InvalidCode(format!(
+            "\"{}\" isn't a valid setup code. Codes look like VS-7K2M.",
+            raw.trim()
+        )));
+    }
+
+    if HAS_CHECK_CHAR && !luhn_mod36_valid(body) {
+        return Err(ApiError::InvalidCode(
+            "That setup code has a typo. Check each character and try again.".to_string(),
+        ));
+    }
+
+    Ok(format!("{}-{}", PREFIX, body))
+}
+
+/// Luhn mod N over the 36-character code alphabet. The last character is
+/// the check character.
+pub fn luhn_mod36_valid(body: &str) -> bool {
+    let n = ALPHABET.len();
+    let mut factor = 1;
+    let mut sum = 0;
+    for b in body.bytes().rev() {
+        let Some(value) = ALPHABET.iter().position(|&a| a == b) else {
+            return false;
+        };
+        let addend = factor * value;
+        sum += addend / n + addend % n;
+        factor = if factor == 2 { 1 } else { 2 };
+    }
+    sum % n == 0
+}
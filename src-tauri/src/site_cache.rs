@@ -0,0 +1,131 @@
+/// Cache of validated setup codes.
+///
+/// A technician who restarts the app mid-job, or whose venue loses internet
+/// after the code was checked, shouldn't lose the site context. Each
+/// successful `/api/setup-code` response is stored in a JSON file in the app
+/// data directory with the expiry the API returned, and reused for that code
+/// whenever the API can't be reached, until it expires.
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::api::SetupCodeResponse;
+
+pub const CACHE_FILE_NAME: &str = "site_cache.json";
+/// Used when the API response carries no `expiresAt`.
+const DEFAULT_TTL_HOURS: i64 = 12;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedSite {
+    /// Normalised setup code, e.g. `VS-7K2M`
+    pub code: String,
+    /// API the code was validated against, so profiles don't mix
+    pub api_base: String,
+    pub site: SetupCodeResponse,
+    pub validated_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct SiteCache {
+    path: PathBuf,
+    entries: Mutex<Vec<CachedSite>>,
+}
+
+impl SiteCache {
+    /// Load the cache, dropping anything that expired while the app was closed.
+    pub fn load(path: PathBuf) -> Self {
+        let entries: Vec<CachedSite> = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("Discarding unreadable site cache {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        let cache = SiteCache {
+            path,
+            entries: Mutex::new(entries),
+        };
+        cache.prune(Utc::now());
+        cache
+    }
+
+    /// The cached site for `code`, if it hasn't expired.
+    pub fn get(&self, api_base: &str, code: &str) -> Option<CachedSite> {
+        let now = Utc::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.api_base == api_base && e.code == code && e.expires_at > now)
+            .cloned()
+    }
+
+    /// Remember a freshly validated code, replacing any older entry for it.
+    pub fn insert(&self, api_base: &str, code: &str, site: &SetupCodeResponse) -> CachedSite {
+        let now = Utc::now();
+        let entry = CachedSite {
+            code: code.to_string(),
+            api_base: api_base.to_string(),
+            site: site.clone(),
+            validated_at: now,
+            expires_at: site
+                .expires_at
+                .unwrap_or_else(|| now + Duration::hours(DEFAULT_TTL_HOURS)),
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|e| !(e.api_base == api_base && e.code == code));
+        entries.push(entry.clone());
+        persist(&self.path, &entries);
+        entry
+    }
+
+    /// Unexpired entries, most recently validated first.
+    pub fn list(&self) -> Vec<CachedSite> {
+        self.prune(Utc::now());
+        let mut entries = self.entries.lock().unwrap().clone();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.validated_at));
+        entries
+    }
+
+    /// Forget one code, or every code if `code` is `None`. Returns how many
+    /// entries were removed.
+    pub fn clear(&self, code: Option<&str>) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        match code {
+            Some(code) => entries.retain(|e| e.code != code),
+            None => entries.clear(),
+        }
+        let removed = before - entries.len();
+        if removed > 0 {
+            persist(&self.path, &entries);
+        }
+        removed
+    }
+
+    fn prune(&self, now: DateTime<Utc>) {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|e| e.expires_at > now);
+        if entries.len() != before {
+            log::info!("Dropped {} expired cached site(s)", before - entries.len());
+            persist(&self.path, &entries);
+        }
+    }
+}
+
+fn persist(path: &Path, entries: &[CachedSite]) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| {
+            let json = serde_json::to_string_pretty(entries).map_err(std::io::Error::other)?;
+            std::fs::write(path, json)
+        });
+    if let Err(e) = result {
+        log::error!("Failed to persist site cache {}: {}", path.display(), e);
+    }
+}
@@ -1,3 +1,8 @@
+use russh::kex;
+use russh::Preferred;
+use russh::*;
+use russh_keys::ssh_key::{Algorithm, EcdsaCurve};
+use serde::Serialize;
 /// SSH client for executing set-inform on UniFi APs.
 ///
 /// Flow (see design doc §4.5.4):
@@ -8,15 +13,13 @@
 /// 5. Disconnect
 use std::borrow::Cow;
 use std::sync::Arc;
-use russh::*;
-use russh::kex;
-use russh::Preferred;
-use russh_keys::ssh_key::{Algorithm, EcdsaCurve};
 
 const SSH_PORT: u16 = 22;
 const DEFAULT_USERNAME: &str = "ubnt";
 const DEFAULT_PASSWORD: &str = "ubnt";
 const CONNECT_TIMEOUT_SECS: u64 = 10;
+/// How long a command may run once authenticated, as with system SSH.
+const COMMAND_TIMEOUT_SECS: u64 = 60;
 
 #[derive(Debug)]
 pub enum SshError {
@@ -24,6 +27,8 @@ pub enum SshError {
     ConnectionTimeout(String),
     AuthFailed(String),
     CommandFailed(String),
+    /// Logged in, but the command was still running when it was cut off
+    CommandTimeout(String),
     Other(String),
 }
 
@@ -34,6 +39,7 @@ impl std::fmt::Display for SshError {
             SshError::ConnectionTimeout(msg) => write!(f, "Connection timeout: {}", msg),
             SshError::AuthFailed(msg) => write!(f, "Authentication failed: {}", msg),
             SshError::CommandFailed(msg) => write!(f, "Command failed: {}", msg),
+            SshError::CommandTimeout(msg) => write!(f, "Command timed out: {}", msg),
             SshError::Other(msg) => write!(f, "SSH error: {}", msg),
         }
     }
@@ -61,82 +67,83 @@ pub async fn set_inform(
     inform_url: &str,
     custom_password: Option<&str>,
 ) -> Result<String, SshError> {
-    let password = custom_password.unwrap_or(DEFAULT_PASSWORD);
+    log::info!("Executing set-inform on {}...", ip);
+    let output = run_command(ip, &set_inform_command(inform_url), custom_password).await?;
 
-    log::info!("Connecting to {} via SSH...", ip);
+    log::info!("set-inform output: {}", output);
 
-    // Configure SSH for compatibility with UniFi APs (Dropbear SSH).
-    //
-    // IMPORTANT: russh 0.48 has a bug where it always verifies RSA signatures
-    // using SHA-1 (via sig_workaround.rs), regardless of the negotiated host key
-    // algorithm. If rsa-sha2-256 is negotiated, the server signs with SHA-256
-    // but russh verifies with SHA-1 → "Wrong server signature".
-    //
-    // Workaround: only offer ssh-rsa (SHA-1) for host keys so both sides
-    // agree on SHA-1 signing. Also include legacy kex algorithms.
-    let mut config = client::Config::default();
-    config.preferred = Preferred {
-        kex: Cow::Owned(vec![
-            kex::CURVE25519,
-            kex::CURVE25519_PRE_RFC_8731,
-            kex::DH_G16_SHA512,
-            kex::DH_G14_SHA256,
-            kex::DH_G14_SHA1,
-            kex::DH_G1_SHA1,
-            kex::EXTENSION_SUPPORT_AS_CLIENT,
-            kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
-        ]),
-        key: Cow::Owned(vec![
-            // ONLY offer ssh-rsa (SHA-1) for RSA keys due to the russh bug above.
-            // Do NOT include rsa-sha2-256 or rsa-sha2-512.
-            Algorithm::Rsa { hash: None },
-            Algorithm::Ed25519,
-            Algorithm::Ecdsa { curve: EcdsaCurve::NistP256 },
-            Algorithm::Ecdsa { curve: EcdsaCurve::NistP384 },
-            Algorithm::Ecdsa { curve: EcdsaCurve::NistP521 },
-        ]),
-        ..config.preferred
-    };
-    let config = Arc::new(config);
+    // The set-inform command typically outputs something like:
+    // "Adoption request sent to http://...  Firmware 'BZ.xxx.vX.X.X.xxx.xxx'  AP-ID[...]"
+    // Any output without "error" is generally success
+    if output.to_lowercase().contains("error") && !output.to_lowercase().contains("inform") {
+        return Err(SshError::CommandFailed(format!(
+            "set-inform returned an error: {}",
+            output
+        )));
+    }
 
-    let addr = format!("{}:{}", ip, SSH_PORT);
+    Ok(output)
+}
 
-    let mut handle = tokio::time::timeout(
-        std::time::Duration::from_secs(CONNECT_TIMEOUT_SECS),
-        client::connect(config, &addr, ClientHandler),
+/// Shell command that runs set-inform on any firmware. It may not be in
+/// PATH everywhere, so fall back through the known locations.
+pub fn set_inform_command(inform_url: &str) -> String {
+    format!(
+        "set-inform {url} 2>/dev/null || mca-cli-op set-inform {url} 2>/dev/null || /usr/bin/mca-cli-op set-inform {url} 2>/dev/null || syswrapper.sh set-inform {url} 2>/dev/null || /usr/bin/syswrapper.sh set-inform {url}",
+        url = inform_url
     )
-    .await
-    .map_err(|_| SshError::ConnectionTimeout(format!("Timed out connecting to {}", ip)))?
-    .map_err(|e| {
-        let msg = e.to_string();
-        if msg.contains("refused") {
-            SshError::ConnectionRefused(format!("Connection refused at {}", ip))
-        } else {
-            SshError::Other(format!("Failed to connect to {}: {}", ip, msg))
-        }
-    })?;
+}
 
-    log::info!("Connected to {}, authenticating...", ip);
+/// Delay before a backgrounded command that reboots the device, so the
+/// SSH session has time to close first.
+pub const REBOOT_DELAY_SECS: u64 = 2;
 
-    let auth_result = handle
-        .authenticate_password(DEFAULT_USERNAME, password)
-        .await
-        .map_err(|e| SshError::Other(format!("Auth error: {}", e)))?;
+/// Shell fragment that runs `cmd` in the background after `delay_secs`.
+/// It ignores the hangup sent when the session closes and holds none of
+/// the channel's file descriptors, so the session can end while it's still
+/// running — which it must for anything that reboots the device. Output is
+/// discarded unless `cmd` redirects its own.
+pub fn background_after(delay_secs: u64, cmd: &str) -> String {
+    let sleep = if delay_secs > 0 {
+        format!("sleep {delay_secs}; ")
+    } else {
+        String::new()
+    };
+    format!("(trap '' HUP; {sleep}{cmd}) </dev/null >/dev/null 2>&1 &")
+}
 
-    if !auth_result {
-        return Err(SshError::AuthFailed(format!(
-            "Authentication failed for {} — password may have been changed from factory default",
-            ip
-        )));
-    }
+/// A piece of a running command's output, in the order it arrived. Every
+/// run ends with exactly one `ExitStatus` or `Failed`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "camelCase")]
+pub enum OutputChunk {
+    Stdout(String),
+    Stderr(String),
+    ExitStatus(u32),
+    /// The command couldn't be run, or ended without an exit status
+    Failed(String),
+}
 
-    log::info!("Authenticated to {}, executing set-inform...", ip);
+/// Run a shell command on an AP and return its combined stdout and
+/// stderr, trimmed.
+pub async fn run_command(
+    ip: &str,
+    command: &str,
+    custom_password: Option<&str>,
+) -> Result<String, SshError> {
+    run_command_streaming(ip, command, custom_password, |_| {}).await
+}
 
-    // Try multiple command paths — set-inform may not be in PATH on all firmware
-    let command = format!(
-        "set-inform {url} 2>/dev/null || mca-cli-op set-inform {url} 2>/dev/null || /usr/bin/mca-cli-op set-inform {url} 2>/dev/null || syswrapper.sh set-inform {url} 2>/dev/null || /usr/bin/syswrapper.sh set-inform {url}",
-        url = inform_url
-    );
+/// [`run_command`], also handing each piece of output to `on_chunk` as it
+/// arrives so long-running commands can show progress. Like system SSH, it
+/// gives up with [`SshError::CommandTimeout`] after `COMMAND_TIMEOUT_SECS`.
+pub async fn run_command_streaming(
+    ip: &str,
+    command: &str,
+    custom_password: Option<&str>,
+    mut on_chunk: impl FnMut(OutputChunk),
+) -> Result<String, SshError> {
+    let handle = connect(ip, custom_password).await?;
 
     let mut channel = handle
         .channel_open_session()
@@ -150,32 +157,295 @@ pub async fn set_inform(
 
     // Read response
     let mut output = String::new();
-    while let Some(msg) = channel.wait().await {
+    let mut exited = false;
+    let deadline =
+        tokio::time::Instant::now() + std::time::Duration::from_secs(COMMAND_TIMEOUT_SECS);
+    loop {
+        let msg = match tokio::time::timeout_at(deadline, channel.wait()).await {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            Err(_) => {
+                return Err(SshError::CommandTimeout(format!(
+                    "The command on {} didn't finish within {} seconds",
+                    ip, COMMAND_TIMEOUT_SECS,
+                )));
+            }
+        };
         match msg {
             ChannelMsg::Data { data } => {
-                output.push_str(&String::from_utf8_lossy(&data));
+                let text = String::from_utf8_lossy(&data);
+                output.push_str(&text);
+                on_chunk(OutputChunk::Stdout(text.into_owned()));
             }
             ChannelMsg::ExtendedData { data, .. } => {
-                output.push_str(&String::from_utf8_lossy(&data));
+                let text = String::from_utf8_lossy(&data);
+                output.push_str(&text);
+                on_chunk(OutputChunk::Stderr(text.into_owned()));
             }
             ChannelMsg::ExitStatus { exit_status } => {
-                log::info!("set-inform exit status: {}", exit_status);
+                log::info!("Command on {} exited with status {}", ip, exit_status);
+                exited = true;
+                on_chunk(OutputChunk::ExitStatus(exit_status));
+            }
+            ChannelMsg::ExitSignal { signal_name, .. } => {
+                log::info!("Command on {} was killed by {:?}", ip, signal_name);
+                exited = true;
+                on_chunk(OutputChunk::Failed(format!(
+                    "The command was killed by {:?}",
+                    signal_name
+                )));
             }
             _ => {}
         }
     }
+    if !exited {
+        on_chunk(OutputChunk::Failed(
+            "The connection closed before the command finished".to_string(),
+        ));
+    }
 
-    log::info!("set-inform output: {}", output.trim());
+    Ok(output.trim().to_string())
+}
 
-    // The set-inform command typically outputs something like:
-    // "Adoption request sent to http://...  Firmware 'BZ.xxx.vX.X.X.xxx.xxx'  AP-ID[...]"
-    // Any output without "error" is generally success
-    if output.to_lowercase().contains("error") && !output.to_lowercase().contains("inform") {
-        return Err(SshError::CommandFailed(format!(
-            "set-inform returned an error: {}",
-            output.trim()
+/// Terminal type requested for interactive shells; busybox and the AP's
+/// tools handle it fine.
+const SHELL_TERM: &str = "xterm-256color";
+
+enum ShellInput {
+    Data(Vec<u8>),
+    Resize { cols: u32, rows: u32 },
+    Close,
+}
+
+/// Output from an interactive shell.
+#[derive(Debug)]
+pub enum ShellEvent {
+    /// Raw terminal output, stdout and stderr interleaved as the PTY sent them
+    Output(Vec<u8>),
+    /// The shell exited or the connection dropped. Always the last event.
+    Closed { exit_status: Option<u32> },
+}
+
+/// An interactive shell on a PTY. Output is delivered to the callback
+/// given to [`open_shell`]; keystrokes and resizes go in through here.
+pub struct ShellSession {
+    input: tokio::sync::mpsc::UnboundedSender<ShellInput>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ShellSession {
+    pub fn write(&self, data: Vec<u8>) -> Result<(), SshError> {
+        self.send(ShellInput::Data(data))
+    }
+
+    pub fn resize(&self, cols: u32, rows: u32) -> Result<(), SshError> {
+        self.send(ShellInput::Resize { cols, rows })
+    }
+
+    fn send(&self, input: ShellInput) -> Result<(), SshError> {
+        self.input
+            .send(input)
+            .map_err(|_| SshError::Other("The terminal session has ended".to_string()))
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Close the channel and disconnect, giving the server a moment to
+    /// acknowledge before the session is dropped.
+    pub async fn close(mut self) {
+        if self.send(ShellInput::Close).is_ok() {
+            let _ = tokio::time::timeout(
+                std::time::Duration::from_secs(CONNECT_TIMEOUT_SECS),
+                &mut self.task,
+            )
+            .await;
+        }
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Open an interactive shell with a `cols` x `rows` PTY, using the same
+/// connection settings as [`run_command`]. `on_event` is called from a
+/// background task for every chunk of output and once when the shell ends.
+pub async fn open_shell(
+    ip: &str,
+    custom_password: Option<&str>,
+    cols: u32,
+    rows: u32,
+    mut on_event: impl FnMut(ShellEvent) + Send + 'static,
+) -> Result<ShellSession, SshError> {
+    let handle = connect(ip, custom_password).await?;
+
+    let mut channel = handle
+        .channel_open_session()
+        .await
+        .map_err(|e| SshError::Other(format!("Failed to open channel: {}", e)))?;
+    channel
+        .request_pty(true, SHELL_TERM, cols, rows, 0, 0, &[])
+        .await
+        .map_err(|e| SshError::Other(format!("Failed to request a terminal: {}", e)))?;
+    expect_success(&mut channel, "terminal").await?;
+    channel
+        .request_shell(true)
+        .await
+        .map_err(|e| SshError::Other(format!("Failed to start a shell: {}", e)))?;
+    expect_success(&mut channel, "shell").await?;
+    log::info!("Interactive shell open on {} ({}x{})", ip, cols, rows);
+
+    let (input, mut inputs) = tokio::sync::mpsc::unbounded_channel();
+    let ip = ip.to_string();
+    let task = tokio::spawn(async move {
+        let mut exit_status = None;
+        loop {
+            tokio::select! {
+                msg = channel.wait() => match msg {
+                    Some(ChannelMsg::Data { data }) | Some(ChannelMsg::ExtendedData { data, .. }) => {
+                        on_event(ShellEvent::Output(data.to_vec()));
+                    }
+                    Some(ChannelMsg::ExitStatus { exit_status: status }) => exit_status = Some(status),
+                    Some(_) => {}
+                    None => break,
+                },
+                input = inputs.recv() => match input {
+                    Some(ShellInput::Data(data)) => {
+                        if let Err(e) = channel.data(&data[..]).await {
+                            log::warn!("Failed to send input to {}: {}", ip, e);
+                            break;
+                        }
+                    }
+                    Some(ShellInput::Resize { cols, rows }) => {
+                        if let Err(e) = channel.window_change(cols, rows, 0, 0).await {
+                            log::warn!("Failed to resize terminal on {}: {}", ip, e);
+                        }
+                    }
+                    Some(ShellInput::Close) | None => {
+                        let _ = channel.close().await;
+                        break;
+                    }
+                },
+            }
+        }
+        let _ = handle.disconnect(Disconnect::ByApplication, "", "en").await;
+        log::info!("Interactive shell on {} closed", ip);
+        on_event(ShellEvent::Closed { exit_status });
+    });
+
+    Ok(ShellSession { input, task })
+}
+
+/// Wait for the server's answer to a channel request sent with
+/// `want_reply`.
+async fn expect_success(channel: &mut Channel<client::Msg>, request: &str) -> Result<(), SshError> {
+    loop {
+        match channel.wait().await {
+            Some(ChannelMsg::Success) => return Ok(()),
+            Some(ChannelMsg::Failure) => {
+                return Err(SshError::CommandFailed(format!(
+                    "The device refused the {} request",
+                    request
+                )))
+            }
+            Some(_) => {}
+            None => {
+                return Err(SshError::Other(format!(
+                    "Channel closed before the {} request was answered",
+                    request
+                )))
+            }
+        }
+    }
+}
+
+/// Connect and authenticate as `ubnt`, with the factory-default password
+/// unless a custom one is provided.
+async fn connect(
+    ip: &str,
+    custom_password: Option<&str>,
+) -> Result<client::Handle<ClientHandler>, SshError> {
+    let password = custom_password.unwrap_or(DEFAULT_PASSWORD);
+
+    log::info!("Connecting to {} via SSH...", ip);
+
+    let addr = format!("{}:{}", ip, SSH_PORT);
+
+    let mut handle = tokio::time::timeout(
+        std::time::Duration::from_secs(CONNECT_TIMEOUT_SECS),
+        client::connect(client_config(), &addr, ClientHandler),
+    )
+    .await
+    .map_err(|_| SshError::ConnectionTimeout(format!("Timed out connecting to {}", ip)))?
+    .map_err(|e| {
+        let msg = e.to_string();
+        if msg.contains("refused") {
+            SshError::ConnectionRefused(format!("Connection refused at {}", ip))
+        } else {
+            SshError::Other(format!("Failed to connect to {}: {}", ip, msg))
+        }
+    })?;
+
+    log::info!("Connected to {}, authenticating...", ip);
+
+    let auth_result = handle
+        .authenticate_password(DEFAULT_USERNAME, password)
+        .await
+        .map_err(|e| SshError::Other(format!("Auth error: {}", e)))?;
+
+    if !auth_result {
+        return Err(SshError::AuthFailed(format!(
+            "Authentication failed for {} — password may have been changed from factory default",
+            ip
         )));
     }
 
-    Ok(output.trim().to_string())
+    log::info!("Authenticated to {}", ip);
+    Ok(handle)
+}
+
+/// Configure SSH for compatibility with UniFi APs (Dropbear SSH).
+///
+/// IMPORTANT: russh 0.48 has a bug where it always verifies RSA signatures
+/// using SHA-1 (via sig_workaround.rs), regardless of the negotiated host key
+/// algorithm. If rsa-sha2-256 is negotiated, the server signs with SHA-256
+/// but russh verifies with SHA-1 → "Wrong server signature".
+///
+/// Workaround: only offer ssh-rsa (SHA-1) for host keys so both sides
+/// agree on SHA-1 signing. Also include legacy kex algorithms.
+fn client_config() -> Arc<client::Config> {
+    let mut config = client::Config::default();
+    config.preferred = Preferred {
+        kex: Cow::Owned(vec![
+            kex::CURVE25519,
+            kex::CURVE25519_PRE_RFC_8731,
+            kex::DH_G16_SHA512,
+            kex::DH_G14_SHA256,
+            kex::DH_G14_SHA1,
+            kex::DH_G1_SHA1,
+            kex::EXTENSION_SUPPORT_AS_CLIENT,
+            kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
+        ]),
+        key: Cow::Owned(vec![
+            // ONLY offer ssh-rsa (SHA-1) for RSA keys due to the russh bug above.
+            // Do NOT include rsa-sha2-256 or rsa-sha2-512.
+            Algorithm::Rsa { hash: None },
+            Algorithm::Ed25519,
+            Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP256,
+            },
+            Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP384,
+            },
+            Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP521,
+            },
+        ]),
+        ..config.preferred
+    };
+    Arc::new(config)
 }
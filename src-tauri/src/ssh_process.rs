@@ -7,12 +7,27 @@
 /// Password authentication is handled via an `expect` script (macOS ships
 /// with expect pre-installed as part of the developer tools / Tcl).
 use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 use tokio::process::Command;
 
+use crate::ssh::OutputChunk;
+
 const SSH_PORT: u16 = 22;
 const DEFAULT_USERNAME: &str = "ubnt";
 const DEFAULT_PASSWORD: &str = "ubnt";
 const CONNECT_TIMEOUT_SECS: u64 = 10;
+/// How long a command may run once authenticated (checks, log collection).
+const COMMAND_TIMEOUT_SECS: u64 = 60;
+/// Printed by the expect script with the exit status once the password
+/// has been accepted, i.e. once the command has run.
+const EXIT_MARKER: &str = "VS_EXIT=";
+/// Tail of ssh's password prompt, which expect answers.
+const PASSWORD_PROMPT: &str = "assword:";
+/// What ssh itself exits with when the connection fails or drops.
+const SSH_ERROR_STATUS: u32 = 255;
+
+static SCRIPT_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug)]
 pub enum SshError {
@@ -20,6 +35,8 @@ pub enum SshError {
     ConnectionTimeout(String),
     AuthFailed(String),
     CommandFailed(String),
+    /// Logged in, but the command was still running when it was cut off
+    CommandTimeout(String),
     Other(String),
 }
 
@@ -30,6 +47,7 @@ impl std::fmt::Display for SshError {
             SshError::ConnectionTimeout(msg) => write!(f, "Connection timeout: {}", msg),
             SshError::AuthFailed(msg) => write!(f, "Authentication failed: {}", msg),
             SshError::CommandFailed(msg) => write!(f, "Command failed: {}", msg),
+            SshError::CommandTimeout(msg) => write!(f, "Command timed out: {}", msg),
             SshError::Other(msg) => write!(f, "SSH error: {}", msg),
         }
     }
@@ -41,14 +59,60 @@ pub async fn set_inform(
     ip: &str,
     inform_url: &str,
     custom_password: Option<&str>,
+) -> Result<String, SshError> {
+    let output = run_command(
+        ip,
+        &crate::ssh::set_inform_command(inform_url),
+        custom_password,
+    )
+    .await?;
+
+    log::info!("set-inform result: {}", output);
+
+    // Check for errors in the output
+    if output.to_lowercase().contains("error") && !output.to_lowercase().contains("inform") {
+        return Err(SshError::CommandFailed(format!(
+            "set-inform returned an error: {}",
+            output,
+        )));
+    }
+
+    Ok(output)
+}
+
+/// Run a shell command on an AP via the system ssh + expect and return
+/// its output with expect's own chatter filtered out.
+pub async fn run_command(
+    ip: &str,
+    ssh_command: &str,
+    custom_password: Option<&str>,
+) -> Result<String, SshError> {
+    run_command_streaming(ip, ssh_command, custom_password, true, |_| {}).await
+}
+
+/// [`run_command`] for commands whose text or output carries secrets
+/// (config backups and restores hold WPA keys and admin hashes): expect's
+/// output is kept out of the log entirely.
+pub async fn run_command_quiet(
+    ip: &str,
+    ssh_command: &str,
+    custom_password: Option<&str>,
+) -> Result<String, SshError> {
+    run_command_streaming(ip, ssh_command, custom_password, false, |_| {}).await
+}
+
+/// [`run_command`], also handing each line of output to `on_chunk` as it
+/// arrives. ssh runs on expect's terminal, which merges the remote stderr
+/// into stdout, so every line is reported as stdout. Unless `log_output` is
+/// set, expect's output (which echoes the command) isn't logged.
+pub async fn run_command_streaming(
+    ip: &str,
+    ssh_command: &str,
+    custom_password: Option<&str>,
+    log_output: bool,
+    mut on_chunk: impl FnMut(OutputChunk),
 ) -> Result<String, SshError> {
     let password = custom_password.unwrap_or(DEFAULT_PASSWORD);
-    // Try multiple command paths — on some firmware versions set-inform
-    // isn't in PATH. The mca-cli-op command is the standard way on managed APs.
-    let ssh_command = format!(
-        "set-inform {url} 2>/dev/null || mca-cli-op set-inform {url} 2>/dev/null || /usr/bin/mca-cli-op set-inform {url} 2>/dev/null || syswrapper.sh set-inform {url} 2>/dev/null || /usr/bin/syswrapper.sh set-inform {url}",
-        url = inform_url
-    );
 
     log::info!("Connecting to {} via system SSH (expect)...", ip);
 
@@ -67,15 +131,21 @@ spawn ssh -o StrictHostKeyChecking=no \
     {user}@{host} "{cmd}"
 
 expect {{
-    "assword:" {{
+    "{prompt}" {{
         send "{pass}\r"
+        set timeout {command_timeout}
         expect {{
-            "assword:" {{
+            "{prompt}" {{
                 puts stderr "AUTH_FAILED"
                 exit 1
             }}
+            timeout {{
+                puts stderr "COMMAND_TIMEOUT"
+                exit 124
+            }}
             eof {{
                 catch wait result
+                puts stderr "{exit_marker}[lindex $result 3]"
                 exit [lindex $result 3]
             }}
         }}
@@ -95,16 +165,24 @@ expect {{
 }}
 "#,
         timeout = CONNECT_TIMEOUT_SECS,
+        command_timeout = COMMAND_TIMEOUT_SECS,
+        exit_marker = EXIT_MARKER,
+        prompt = PASSWORD_PROMPT,
         port = SSH_PORT,
         user = DEFAULT_USERNAME,
         host = ip,
-        cmd = ssh_command.replace('"', r#"\""#),
-        pass = password.replace('\\', r"\\").replace('"', r#"\""#),
+        cmd = tcl_escape(ssh_command),
+        pass = tcl_escape(password),
     );
 
-    // Write the expect script to a temp file
+    // Write the expect script to a temp file. Several commands can be in
+    // flight at once (e.g. checks on multiple APs), so each gets its own.
     let script_dir = std::env::temp_dir();
-    let script_path = script_dir.join("vivaspot_ssh.exp");
+    let script_path = script_dir.join(format!(
+        "vivaspot_ssh_{}_{}.exp",
+        std::process::id(),
+        SCRIPT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
 
     tokio::fs::write(&script_path, &expect_script)
         .await
@@ -120,34 +198,58 @@ expect {{
 
     // Run the expect script
     let output = tokio::time::timeout(
-        std::time::Duration::from_secs(CONNECT_TIMEOUT_SECS + 10),
-        Command::new("expect")
-            .arg(script_path.to_str().unwrap_or(""))
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output(),
+        std::time::Duration::from_secs(CONNECT_TIMEOUT_SECS + COMMAND_TIMEOUT_SECS + 10),
+        run_expect(&script_path, &mut on_chunk),
     )
     .await
-    .map_err(|_| SshError::ConnectionTimeout(format!("Timed out connecting to {}", ip)))?
-    .map_err(|e| SshError::Other(format!("Failed to run expect: {}", e)))?;
+    .map_err(|_| SshError::ConnectionTimeout(format!("Timed out connecting to {}", ip)))
+    .and_then(|result| result.map_err(|e| SshError::Other(format!("Failed to run expect: {}", e))));
 
     // Clean up
     let _ = tokio::fs::remove_file(&script_path).await;
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let (status, stdout, stderr) = output?;
 
-    log::info!("expect stdout: {}", stdout.trim());
-    log::info!("expect stderr: {}", stderr.trim());
+    if log_output {
+        log::debug!(
+            "expect stdout: {}",
+            crate::diagnostics::redact(stdout.trim())
+        );
+        log::debug!(
+            "expect stderr: {}",
+            crate::diagnostics::redact(stderr.trim())
+        );
+    }
 
-    if !output.status.success() {
+    let exit_status = stderr
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(EXIT_MARKER))
+        .and_then(|code| code.parse::<u32>().ok());
+    if !status.success() {
+        if stderr.contains("COMMAND_TIMEOUT") {
+            return Err(SshError::CommandTimeout(format!(
+                "The command on {} didn't finish within {} seconds",
+                ip, COMMAND_TIMEOUT_SECS,
+            )));
+        }
         if stderr.contains("AUTH_FAILED") || stdout.contains("Permission denied") {
             return Err(SshError::AuthFailed(format!(
                 "Authentication failed for {} — password may have been changed from factory default",
                 ip,
             )));
         }
+        // Once logged in, a non-zero status is the command's own answer,
+        // as it is with russh; only ssh's 255 means the session broke.
+        match exit_status {
+            Some(SSH_ERROR_STATUS) => {
+                return Err(SshError::CommandFailed(format!(
+                    "Lost the connection to {} while the command was running",
+                    ip,
+                )));
+            }
+            Some(_) => return Ok(finish(&stdout, exit_status, &mut on_chunk)),
+            None => {}
+        }
         if stderr.contains("CONNECTION_REFUSED") || stdout.contains("Connection refused") {
             return Err(SshError::ConnectionRefused(format!(
                 "Connection refused at {}",
@@ -169,30 +271,82 @@ expect {{
         )));
     }
 
-    // Filter out expect's echoed output (the spawn line, password prompt, etc.)
-    // The actual set-inform output is what comes after the password was sent.
-    let useful_output: String = stdout
-        .lines()
-        .filter(|line| {
-            !line.starts_with("spawn ")
-                && !line.contains("assword:")
-                && !line.contains("Warning: Permanently added")
-                && !line.trim().is_empty()
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+    Ok(finish(&stdout, exit_status, &mut on_chunk))
+}
 
-    log::info!("set-inform result: {}", useful_output.trim());
+/// Report the exit status and return the command's output.
+fn finish(
+    stdout: &str,
+    exit_status: Option<u32>,
+    on_chunk: &mut impl FnMut(OutputChunk),
+) -> String {
+    on_chunk(OutputChunk::ExitStatus(exit_status.unwrap_or(0)));
+    command_output(stdout).trim().to_string()
+}
 
-    // Check for errors in the output
-    if useful_output.to_lowercase().contains("error")
-        && !useful_output.to_lowercase().contains("inform")
-    {
-        return Err(SshError::CommandFailed(format!(
-            "set-inform returned an error: {}",
-            useful_output.trim(),
-        )));
-    }
+/// What the command printed: everything after the line with the password
+/// prompt. Before it come expect's and ssh's own lines (the spawn line,
+/// host key warnings); after it the output is passed through untouched, so
+/// blank lines in e.g. a config file survive.
+fn command_output(stdout: &str) -> &str {
+    stdout
+        .find(PASSWORD_PROMPT)
+        .and_then(|start| stdout[start..].split_once('\n'))
+        .map_or("", |(_, rest)| rest)
+}
 
-    Ok(useful_output.trim().to_string())
+/// Run the expect script, passing command output to `on_chunk` line by
+/// line. Returns the exit status and the full stdout and stderr.
+async fn run_expect(
+    script_path: &std::path::Path,
+    on_chunk: &mut impl FnMut(OutputChunk),
+) -> std::io::Result<(std::process::ExitStatus, String, String)> {
+    let mut child = Command::new("expect")
+        .arg(script_path.to_str().unwrap_or(""))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdout_pipe = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+    let read_stdout = async {
+        let mut stdout = String::new();
+        let mut line = Vec::new();
+        let mut logged_in = false;
+        while stdout_pipe.read_until(b'\n', &mut line).await? > 0 {
+            let text = String::from_utf8_lossy(&line);
+            // Same split as [`command_output`]
+            if logged_in {
+                on_chunk(OutputChunk::Stdout(text.replace("\r\n", "\n")));
+            } else {
+                logged_in = text.contains(PASSWORD_PROMPT);
+            }
+            stdout.push_str(&text);
+            line.clear();
+        }
+        Ok::<_, std::io::Error>(stdout)
+    };
+    let read_stderr = async {
+        let mut stderr = Vec::new();
+        stderr_pipe.read_to_end(&mut stderr).await?;
+        Ok(String::from_utf8_lossy(&stderr).into_owned())
+    };
+    let (stdout, stderr) = tokio::try_join!(read_stdout, read_stderr)?;
+    let status = child.wait().await?;
+    Ok((status, stdout, stderr))
+}
+
+/// Escape a value for a double-quoted Tcl string so `$`, `[` and quotes in
+/// shell commands or passwords reach ssh literally.
+fn tcl_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
@@ -0,0 +1,91 @@
+//! Parsing the AP's `info` output for two-phase adoption.
+use app_lib::adoption::{parse_info, AdoptionStage};
+
+const INFO: &str = "
+Model:       UAP-AC-Lite
+Version:     6.5.28.14491
+MAC Address: fc:ec:da:11:22:33
+IP Address:  192.168.1.20
+Hostname:    UAP-AC-Lite
+Uptime:      1234 seconds
+
+Status:      Adopting (http://unifi.example.com:8080/inform)
+";
+
+#[test]
+fn info_fields_are_parsed() {
+    let info = parse_info(INFO).unwrap();
+
+    assert_eq!(info.model.as_deref(), Some("UAP-AC-Lite"));
+    assert_eq!(info.version.as_deref(), Some("6.5.28.14491"));
+    assert_eq!(info.mac.as_deref(), Some("fc:ec:da:11:22:33"));
+    assert_eq!(info.ip.as_deref(), Some("192.168.1.20"));
+    assert_eq!(info.status, "Adopting");
+    assert_eq!(
+        info.inform_url.as_deref(),
+        Some("http://unifi.example.com:8080/inform")
+    );
+    assert_eq!(info.stage, AdoptionStage::Adopting);
+}
+
+#[test]
+fn statuses_are_classified() {
+    let cases = [
+        ("Not Adopted", AdoptionStage::NotAdopted, false),
+        (
+            "Not Adopted (http://unifi:8080/inform)",
+            AdoptionStage::NotAdopted,
+            false,
+        ),
+        (
+            "Adopting (http://unifi:8080/inform)",
+            AdoptionStage::Adopting,
+            true,
+        ),
+        (
+            "Provisioning (http://unifi:8080/inform)",
+            AdoptionStage::Provisioning,
+            true,
+        ),
+        (
+            "Connected (http://unifi:8080/inform)",
+            AdoptionStage::Connected,
+            true,
+        ),
+        (
+            "Server Reject (http://unifi:8080/inform)",
+            AdoptionStage::Rejected,
+            false,
+        ),
+        (
+            "Unknown[11] (http://unifi:8080/inform)",
+            AdoptionStage::Other,
+            false,
+        ),
+        (
+            "Timeout (http://unifi:8080/inform)",
+            AdoptionStage::Other,
+            false,
+        ),
+    ];
+
+    for (status, stage, accepted) in cases {
+        let info = parse_info(&format!("Model: U7LT\nStatus: {}", status)).unwrap();
+        assert_eq!(info.stage, stage, "status: {}", status);
+        assert_eq!(info.stage.is_accepted(), accepted, "status: {}", status);
+    }
+}
+
+#[test]
+fn status_without_url_has_no_inform_url() {
+    let info = parse_info("Status:      Not Adopted").unwrap();
+
+    assert_eq!(info.status, "Not Adopted");
+    assert_eq!(info.inform_url, None);
+}
+
+#[test]
+fn output_without_status_is_rejected() {
+    assert!(parse_info("sh: info: not found").is_none());
+    assert!(parse_info("").is_none());
+}
@@ -0,0 +1,355 @@
+//! Integration tests for the VivaSpot API client against the mock server.
+use std::time::Duration;
+
+use app_lib::api::{ApiClient, ApiError, RetryPolicy};
+use app_lib::mock_api::{MockApi, MockResponse, EXPIRED_CODE, VALID_CODE};
+use app_lib::settings::NetworkSettings;
+use serde_json::json;
+
+/// Client with fast retries so transient-failure tests don't crawl.
+fn client_for(mock: &MockApi) -> ApiClient {
+    ApiClient::new(&mock.base_url(), &NetworkSettings::default())
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_secs(2),
+        })
+}
+
+#[tokio::test]
+async fn valid_code_returns_site() {
+    let mock = MockApi::start().await.unwrap().with_default_routes();
+
+    let site = client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap();
+
+    assert_eq!(site.site_id, "mock-site");
+    assert_eq!(site.site_name, "Mock Site");
+    assert!(site.inform_url.ends_with(":8080/inform"));
+    assert!(site.expires_at.is_some_and(|at| at > chrono::Utc::now()));
+    assert_eq!(
+        mock.requests()[0].query_param("code").as_deref(),
+        Some(VALID_CODE)
+    );
+}
+
+#[tokio::test]
+async fn missing_expiry_is_tolerated() {
+    let mock = MockApi::start().await.unwrap();
+    mock.respond(
+        "/api/setup-code",
+        MockResponse::json(
+            200,
+            json!({ "informUrl": "http://10.0.0.1:8080/inform", "siteId": "s", "siteName": "S" }),
+        ),
+    );
+
+    let site = client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap();
+
+    assert_eq!(site.expires_at, None);
+}
+
+#[tokio::test]
+async fn unknown_code_is_invalid() {
+    let mock = MockApi::start().await.unwrap().with_default_routes();
+
+    let err = client_for(&mock)
+        .validate_setup_code("VS-NOPE")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::InvalidCode(msg) if msg == "Invalid setup code"));
+}
+
+#[tokio::test]
+async fn expired_code_is_reported_as_expired() {
+    let mock = MockApi::start().await.unwrap().with_default_routes();
+
+    let err = client_for(&mock)
+        .validate_setup_code(EXPIRED_CODE)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::ExpiredCode(_)));
+}
+
+#[tokio::test]
+async fn unexpected_status_is_other() {
+    let mock = MockApi::start().await.unwrap();
+    mock.respond("/api/setup-code", MockResponse::text(418, "teapot"));
+
+    let err = client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::Other(msg) if msg.contains("418")));
+}
+
+#[tokio::test]
+async fn malformed_success_body_is_a_parse_error() {
+    let mock = MockApi::start().await.unwrap();
+    mock.respond("/api/setup-code", MockResponse::malformed(200));
+
+    let err = client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::Other(msg) if msg.starts_with("Failed to parse response")));
+}
+
+#[tokio::test]
+async fn malformed_404_body_is_a_parse_error() {
+    let mock = MockApi::start().await.unwrap();
+    mock.respond("/api/setup-code", MockResponse::malformed(404));
+
+    let err = client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::Other(msg) if msg.starts_with("Failed to parse error")));
+}
+
+#[tokio::test]
+async fn slow_response_times_out_as_network_error() {
+    let mock = MockApi::start().await.unwrap();
+    mock.respond(
+        "/api/setup-code",
+        MockResponse::json(200, json!({})).delayed(Duration::from_secs(3)),
+    );
+    let network = NetworkSettings {
+        read_timeout_secs: 1,
+        ..NetworkSettings::default()
+    };
+    let client = ApiClient::new(&mock.base_url(), &network)
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        });
+
+    let err = client.validate_setup_code(VALID_CODE).await.unwrap_err();
+
+    assert!(matches!(err, ApiError::NetworkError(_)));
+}
+
+#[tokio::test]
+async fn unreachable_server_is_network_error() {
+    let mock = MockApi::start().await.unwrap();
+    let base_url = mock.base_url();
+    drop(mock);
+    // Give the aborted listener a moment to release the port
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let client = ApiClient::new(&base_url, &NetworkSettings::default())
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 2,
+            base_delay: Duration::from_millis(10),
+            ..RetryPolicy::default()
+        });
+
+    let err = client.validate_setup_code(VALID_CODE).await.unwrap_err();
+
+    assert!(matches!(err, ApiError::NetworkError(_)));
+}
+
+#[tokio::test]
+async fn queued_responses_are_served_in_order() {
+    let mock = MockApi::start().await.unwrap();
+    mock.enqueue("/api/setup-code", MockResponse::text(400, "bad request"));
+    mock.enqueue(
+        "/api/setup-code",
+        MockResponse::json(
+            200,
+            json!({ "informUrl": "http://c:8080/inform", "siteId": "s", "siteName": "S" }),
+        ),
+    );
+    let client = client_for(&mock);
+
+    assert!(client.validate_setup_code(VALID_CODE).await.is_err());
+    assert!(client.validate_setup_code(VALID_CODE).await.is_ok());
+    assert!(client.validate_setup_code(VALID_CODE).await.is_ok());
+}
+
+#[tokio::test]
+async fn inventory_and_reports_round_trip() {
+    let mock = MockApi::start().await.unwrap().with_default_routes();
+    let client = client_for(&mock);
+
+    let inventory = client.fetch_site_inventory("mock-site").await.unwrap();
+    assert_eq!(inventory.devices.len(), 2);
+
+    // Site IDs are escaped as a single path segment
+    assert!(client.fetch_site_inventory("a/b c").await.is_ok());
+    let requests = mock.requests();
+    assert_eq!(
+        requests.last().unwrap().path,
+        "/api/sites/a%2Fb%20c/devices"
+    );
+
+    let report = app_lib::api::AdoptionReport {
+        id: "AABBCCDDEEFF-1".to_string(),
+        setup_code: VALID_CODE.to_string(),
+        site_id: "mock-site".to_string(),
+        mac: "AA:BB:CC:DD:EE:FF".to_string(),
+        model: "U7LT".to_string(),
+        firmware: "6.6.55".to_string(),
+        result: "success".to_string(),
+        error_code: None,
+        attempted_at: "2026-01-01T00:00:00Z".to_string(),
+        app_version: "test".to_string(),
+    };
+    client.report_adoption(&report).await.unwrap();
+
+    let posted = mock.requests().pop().unwrap();
+    assert_eq!(posted.method, "POST");
+    let body: serde_json::Value = serde_json::from_slice(&posted.body).unwrap();
+    assert_eq!(body["mac"], "AA:BB:CC:DD:EE:FF");
+}
+
+#[tokio::test]
+async fn code_is_normalised_before_sending() {
+    let mock = MockApi::start().await.unwrap().with_default_routes();
+
+    let site = client_for(&mock)
+        .validate_setup_code(" vs test ")
+        .await
+        .unwrap();
+
+    assert_eq!(site.site_id, "mock-site");
+    assert_eq!(mock.requests()[0].query, "code=VS-TEST");
+}
+
+#[tokio::test]
+async fn malformed_code_never_reaches_the_network() {
+    let mock = MockApi::start().await.unwrap().with_default_routes();
+
+    let err = client_for(&mock)
+        .validate_setup_code("VS-7K&M")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ApiError::InvalidCode(_)));
+    assert!(mock.requests().is_empty());
+}
+
+fn site_response() -> MockResponse {
+    MockResponse::json(
+        200,
+        json!({ "informUrl": "http://c:8080/inform", "siteId": "s", "siteName": "S" }),
+    )
+}
+
+#[tokio::test]
+async fn cold_start_502_is_retried() {
+    let mock = MockApi::start().await.unwrap();
+    mock.enqueue("/api/setup-code", MockResponse::text(502, "Bad Gateway"));
+    mock.enqueue("/api/setup-code", MockResponse::text(503, "waking up"));
+    mock.enqueue("/api/setup-code", site_response());
+
+    let site = client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap();
+
+    assert_eq!(site.site_id, "s");
+    assert_eq!(mock.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn persistent_5xx_becomes_server_error_with_message() {
+    let mock = MockApi::start().await.unwrap();
+    mock.respond(
+        "/api/setup-code",
+        MockResponse::json(500, json!({ "error": "database unavailable" })),
+    );
+
+    let err = client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(err, ApiError::ServerError { status: 500, ref message } if message == "database unavailable")
+    );
+    assert_eq!(mock.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn retry_after_is_honoured_on_429() {
+    let mock = MockApi::start().await.unwrap();
+    mock.enqueue(
+        "/api/setup-code",
+        MockResponse::text(429, "slow down").with_header("Retry-After", "1"),
+    );
+    mock.enqueue("/api/setup-code", site_response());
+
+    let started = std::time::Instant::now();
+    client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn long_retry_after_gives_up_with_rate_limited() {
+    let mock = MockApi::start().await.unwrap();
+    mock.respond(
+        "/api/setup-code",
+        MockResponse::json(429, json!({ "error": "Too many requests" }))
+            .with_header("Retry-After", "120"),
+    );
+
+    let err = client_for(&mock)
+        .validate_setup_code(VALID_CODE)
+        .await
+        .unwrap_err();
+
+    match err {
+        ApiError::RateLimited {
+            status,
+            retry_after,
+            message,
+        } => {
+            assert_eq!(status, 429);
+            assert_eq!(retry_after, Some(Duration::from_secs(120)));
+            assert_eq!(message, "Too many requests");
+        }
+        other => panic!("expected RateLimited, got {:?}", other),
+    }
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn reports_are_not_retried_inline() {
+    let mock = MockApi::start().await.unwrap();
+    mock.respond("/api/adoption-report", MockResponse::text(503, "waking up"));
+    let client = client_for(&mock);
+    let report = app_lib::api::AdoptionReport {
+        id: "x".to_string(),
+        setup_code: VALID_CODE.to_string(),
+        site_id: "s".to_string(),
+        mac: "AA:BB:CC:DD:EE:FF".to_string(),
+        model: "U7LT".to_string(),
+        firmware: "6.6.55".to_string(),
+        result: "failed".to_string(),
+        error_code: Some("auth_failed".to_string()),
+        attempted_at: "2026-01-01T00:00:00Z".to_string(),
+        app_version: "test".to_string(),
+    };
+
+    let err = client.report_adoption(&report).await.unwrap_err();
+
+    assert!(matches!(err, ApiError::ServerError { status: 503, .. }));
+    assert_eq!(mock.requests().len(), 1);
+}
@@ -0,0 +1,143 @@
+//! Offline setup bundle verification.
+use app_lib::bundle::{BundleError, SignedBundle};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::{Duration, TimeZone, Utc};
+use ed25519_dalek::{Signer, SigningKey};
+use serde_json::json;
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7u8; 32])
+}
+
+fn public_key() -> [u8; 32] {
+    signing_key().verifying_key().to_bytes()
+}
+
+fn payload_json() -> Vec<u8> {
+    json!({
+        "informUrl": "http://controller.vivaspot.com:8080/inform",
+        "siteId": "site-42",
+        "siteName": "Harbour Hotel",
+        "expiresAt": "2030-01-01T00:00:00Z",
+    })
+    .to_string()
+    .into_bytes()
+}
+
+fn sign(payload: &[u8]) -> SignedBundle {
+    SignedBundle {
+        payload: URL_SAFE_NO_PAD.encode(payload),
+        signature: URL_SAFE_NO_PAD.encode(signing_key().sign(payload).to_bytes()),
+    }
+}
+
+fn now() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2029, 6, 1, 12, 0, 0).unwrap()
+}
+
+#[test]
+fn valid_bundle_opens() {
+    let site = sign(&payload_json())
+        .open_with_key(&public_key(), now())
+        .unwrap();
+
+    assert_eq!(site.site_id, "site-42");
+    assert_eq!(site.site_name, "Harbour Hotel");
+    assert_eq!(
+        site.inform_url,
+        "http://controller.vivaspot.com:8080/inform"
+    );
+}
+
+#[test]
+fn tampered_payload_is_rejected() {
+    let mut bundle = sign(&payload_json());
+    let forged = String::from_utf8(payload_json())
+        .unwrap()
+        .replace("controller.vivaspot.com", "evil.example.com");
+    bundle.payload = URL_SAFE_NO_PAD.encode(forged);
+
+    let err = bundle.open_with_key(&public_key(), now()).unwrap_err();
+
+    assert!(matches!(err, BundleError::BadSignature));
+}
+
+#[test]
+fn bundle_signed_by_another_key_is_rejected() {
+    let other = SigningKey::from_bytes(&[9u8; 32])
+        .verifying_key()
+        .to_bytes();
+
+    let err = sign(&payload_json())
+        .open_with_key(&other, now())
+        .unwrap_err();
+
+    assert!(matches!(err, BundleError::BadSignature));
+}
+
+#[test]
+fn expired_bundle_is_rejected() {
+    let later = Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(1);
+
+    let err = sign(&payload_json())
+        .open_with_key(&public_key(), later)
+        .unwrap_err();
+
+    assert!(matches!(err, BundleError::Expired(_)));
+}
+
+#[test]
+fn file_format_accepts_standard_base64() {
+    let payload = payload_json();
+    let text = json!({
+        "payload": STANDARD.encode(&payload),
+        "signature": STANDARD.encode(signing_key().sign(&payload).to_bytes()),
+    })
+    .to_string();
+
+    let bundle = SignedBundle::from_json(&text).unwrap();
+
+    assert!(bundle.open_with_key(&public_key(), now()).is_ok());
+}
+
+#[test]
+fn deep_link_round_trips() {
+    let bundle = sign(&payload_json());
+    let link = format!(
+        "vivaspot://bundle?payload={}&signature={}",
+        bundle.payload, bundle.signature
+    );
+
+    let parsed = SignedBundle::from_deep_link(&url::Url::parse(&link).unwrap()).unwrap();
+
+    assert!(parsed.open_with_key(&public_key(), now()).is_ok());
+}
+
+#[test]
+fn deep_link_without_signature_is_malformed() {
+    let url = url::Url::parse("vivaspot://bundle?payload=abc").unwrap();
+
+    assert!(matches!(
+        SignedBundle::from_deep_link(&url),
+        Err(BundleError::Malformed(_))
+    ));
+}
+
+#[test]
+fn unsigned_garbage_is_malformed() {
+    assert!(matches!(
+        SignedBundle::from_json("not json"),
+        Err(BundleError::Malformed(_))
+    ));
+}
+
+/// Without `VIVASPOT_BUNDLE_PUBLIC_KEY`, debug builds embed this test key
+#[cfg(debug_assertions)]
+#[test]
+fn development_builds_trust_the_test_key() {
+    if option_env!("VIVASPOT_BUNDLE_PUBLIC_KEY").is_some() {
+        return;
+    }
+    assert_eq!(sign(&payload_json()).open().unwrap().site_id, "site-42");
+}
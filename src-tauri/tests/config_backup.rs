@@ -0,0 +1,129 @@
+//! Config snapshots: reading them off the device, storing them and
+//! writing them back.
+use app_lib::config_backup::{
+    self, BackupStore, ConfigBackup, ConfigFile, MGMT_CFG_PATH, SYSTEM_CFG_PATH,
+};
+
+const SYSTEM_CFG: &str = "aaa.1.br.devname=br0\n\nwireless.1.ssid=Cafe 100% 'guest'\n\
+                          users.1.password=$1$abc\\def\n\n";
+// No final newline, which has to survive the round trip too
+const MGMT_CFG: &str = "mgmt.is_default=false\nmgmt.servers.1.url=http://10.0.0.2:8080/inform";
+
+fn backup_output(mgmt: Option<&str>) -> String {
+    let mut output = format!(
+        "VS_MAC=fc:ec:da:11:22:33\r\nVS_FILE={}\n{}\nVS_END\nVS_FILE={}\n",
+        SYSTEM_CFG_PATH, SYSTEM_CFG, MGMT_CFG_PATH
+    );
+    match mgmt {
+        Some(contents) => output.push_str(&format!("{}\nVS_END\n", contents)),
+        None => output.push_str("VS_MISSING\n"),
+    }
+    output
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("vs-config-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn backup_output_is_split_into_files() {
+    let (mac, files) = config_backup::parse_backup(&backup_output(Some(MGMT_CFG))).unwrap();
+    assert_eq!(mac.as_deref(), Some("fc:ec:da:11:22:33"));
+    assert_eq!(
+        files,
+        vec![
+            ConfigFile {
+                path: SYSTEM_CFG_PATH.to_string(),
+                contents: SYSTEM_CFG.to_string(),
+            },
+            ConfigFile {
+                path: MGMT_CFG_PATH.to_string(),
+                contents: MGMT_CFG.to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn missing_files_are_skipped_but_system_cfg_is_required() {
+    let (_, files) = config_backup::parse_backup(&backup_output(None)).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, SYSTEM_CFG_PATH);
+
+    let output = format!("VS_MAC=\nVS_FILE={}\nVS_MISSING\n", SYSTEM_CFG_PATH);
+    assert!(config_backup::parse_backup(&output).is_err());
+}
+
+#[test]
+fn store_lists_and_loads_by_mac() {
+    let dir = temp_dir("store");
+    let store = BackupStore::new(dir.clone());
+    let (_, files) = config_backup::parse_backup(&backup_output(Some(MGMT_CFG))).unwrap();
+    let backup = ConfigBackup::new("fc-ec-da-11-22-33", "192.168.1.20", "set-inform", files);
+    assert_eq!(backup.mac, "FC:EC:DA:11:22:33");
+    assert!(backup.id.ends_with("-set-inform"));
+    store.save(&backup).unwrap();
+
+    let listed = store.list("fcecda112233");
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, backup.id);
+    assert_eq!(listed[0].files, vec![SYSTEM_CFG_PATH, MGMT_CFG_PATH]);
+    assert!(store.list("FC:EC:DA:44:55:66").is_empty());
+
+    let loaded = store.load("FC:EC:DA:11:22:33", &backup.id).unwrap();
+    assert_eq!(loaded.files, backup.files);
+    assert!(store.load("FC:EC:DA:11:22:33", "../other").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn restore_writes_files_back_exactly() {
+    let (_, files) = config_backup::parse_backup(&backup_output(Some(MGMT_CFG))).unwrap();
+    let backup = ConfigBackup::new("FC:EC:DA:11:22:33", "192.168.1.20", "upgrade", files);
+    let command = config_backup::restore_command(&backup).unwrap();
+    assert!(command.contains("cfgmtd -w -p /etc/"));
+
+    // Run it locally with the device paths and tools swapped out
+    let dir = temp_dir("restore");
+    let command = command
+        .replace(
+            "/var/etc/persistent/cfg",
+            &dir.join("cfg").display().to_string(),
+        )
+        .replace(
+            "/tmp/system.cfg",
+            &dir.join("system.cfg").display().to_string(),
+        )
+        .replace("cfgmtd -w -p /etc/", "true")
+        .replace("reboot", "true");
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .output()
+        .unwrap();
+    config_backup::parse_restore(&String::from_utf8_lossy(&output.stdout)).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(dir.join("system.cfg")).unwrap(),
+        SYSTEM_CFG
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("cfg/mgmt")).unwrap(),
+        MGMT_CFG
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn restore_only_touches_config_files() {
+    let files = vec![ConfigFile {
+        path: "/etc/passwd".to_string(),
+        contents: "root::0:0::/:/bin/sh\n".to_string(),
+    }];
+    let backup = ConfigBackup::new("FC:EC:DA:11:22:33", "192.168.1.20", "upgrade", files);
+    assert!(config_backup::restore_command(&backup).is_err());
+
+    assert!(config_backup::parse_restore("VS_RESTORE=failed\n").is_err());
+}
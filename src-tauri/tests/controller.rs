@@ -0,0 +1,267 @@
+//! UniFi controller client against a local stand-in for both classic and
+//! UniFi OS controllers.
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use app_lib::controller::{ControllerClient, ControllerError, ControllerKind, STATE_CONNECTED};
+use app_lib::local_http::{self, HttpRequest, HttpResponse};
+use app_lib::settings::NetworkSettings;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+const MAC: &str = "fc:ec:da:11:22:33";
+
+struct FakeController {
+    unifi_os: bool,
+    /// States the pending AP goes through once adopted
+    progression: Vec<u32>,
+    adopted: bool,
+    adopt_requests: Vec<Value>,
+}
+
+impl FakeController {
+    fn prefix(&self) -> &'static str {
+        if self.unifi_os {
+            "/proxy/network"
+        } else {
+            ""
+        }
+    }
+
+    fn device(&self) -> Value {
+        let state = if self.adopted { self.progression[0] } else { 2 };
+        json!({ "mac": MAC, "model": "U7LT", "ip": "192.168.1.20", "state": state, "adopted": self.adopted })
+    }
+
+    fn handle(&mut self, request: &HttpRequest) -> HttpResponse {
+        let path = request.path.as_str();
+        let login_path = if self.unifi_os {
+            "/api/auth/login"
+        } else {
+            "/api/login"
+        };
+        // UniFi OS serves its console at / with a CSRF token; classic
+        // controllers redirect to /manage
+        if request.method == "GET" && path == "/" {
+            return if self.unifi_os {
+                HttpResponse::text(200, "<html>UniFi OS</html>")
+                    .with_header("X-CSRF-Token", "csrf-0")
+            } else {
+                HttpResponse::text(302, "").with_header("Location", "/manage")
+            };
+        }
+        if request.method == "GET" && path == "/manage" && !self.unifi_os {
+            return HttpResponse::text(200, "<html>UniFi Network</html>");
+        }
+        if request.method == "POST" && path == "/api/auth/login" && !self.unifi_os {
+            return HttpResponse::json(
+                401,
+                &json!({ "meta": { "rc": "error", "msg": "api.err.LoginRequired" } }),
+            );
+        }
+        if request.method == "POST" && path == login_path {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            if body["username"] != "admin" || body["password"] != "secret" {
+                return HttpResponse::json(
+                    401,
+                    &json!({ "meta": { "rc": "error", "msg": "api.err.Invalid" }, "data": [] }),
+                );
+            }
+            let response = HttpResponse::json(200, &json!({ "meta": { "rc": "ok" }, "data": [] }));
+            return if self.unifi_os {
+                response
+                    .with_header("Set-Cookie", "TOKEN=os-session; Path=/; HttpOnly")
+                    .with_header("X-CSRF-Token", "csrf-1")
+            } else {
+                response.with_header("Set-Cookie", "unifises=classic-session; Path=/")
+            };
+        }
+
+        let Some(api_path) = path
+            .strip_prefix(self.prefix())
+            .and_then(|p| p.strip_prefix("/api/s/default/"))
+        else {
+            return HttpResponse::text(404, "Not Found");
+        };
+        let session = if self.unifi_os {
+            "TOKEN=os-session"
+        } else {
+            "unifises=classic-session"
+        };
+        if !request
+            .header("cookie")
+            .is_some_and(|c| c.contains(session))
+        {
+            return HttpResponse::json(
+                401,
+                &json!({ "meta": { "rc": "error", "msg": "api.err.LoginRequired" } }),
+            );
+        }
+
+        match (request.method.as_str(), api_path) {
+            ("GET", "stat/device") => HttpResponse::json(
+                200,
+                &json!({ "meta": { "rc": "ok" }, "data": [
+                    self.device(),
+                    { "mac": "fc:ec:da:99:99:99", "model": "U6LR", "state": 1, "adopted": true },
+                ] }),
+            ),
+            ("GET", p) if p == format!("stat/device/{}", MAC) => {
+                let device = self.device();
+                if self.adopted && self.progression.len() > 1 {
+                    self.progression.remove(0);
+                }
+                HttpResponse::json(200, &json!({ "meta": { "rc": "ok" }, "data": [device] }))
+            }
+            ("POST", "cmd/devmgr") => {
+                if self.unifi_os && request.header("x-csrf-token") != Some("csrf-1") {
+                    return HttpResponse::text(403, "Forbidden");
+                }
+                let body: Value = serde_json::from_slice(&request.body).unwrap();
+                self.adopt_requests.push(body.clone());
+                if body["mac"] != MAC {
+                    return HttpResponse::json(
+                        400,
+                        &json!({ "meta": { "rc": "error", "msg": "api.err.UnknownDevice" } }),
+                    );
+                }
+                self.adopted = true;
+                HttpResponse::json(200, &json!({ "meta": { "rc": "ok" }, "data": [] }))
+            }
+            _ => HttpResponse::text(404, "Not Found"),
+        }
+    }
+}
+
+async fn start(unifi_os: bool) -> (String, Arc<Mutex<FakeController>>) {
+    let fake = Arc::new(Mutex::new(FakeController {
+        unifi_os,
+        progression: vec![7, 5, 1],
+        adopted: false,
+        adopt_requests: Vec::new(),
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let state = fake.clone();
+    tokio::spawn(local_http::serve(listener, move |request: HttpRequest| {
+        let response = state.lock().unwrap().handle(&request);
+        async move { response }
+    }));
+    (format!("http://{}", addr), fake)
+}
+
+async fn login(base_url: &str) -> ControllerClient {
+    ControllerClient::login(
+        base_url,
+        "admin",
+        "secret",
+        "default",
+        &NetworkSettings::default(),
+        false,
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn classic_login_lists_pending_devices() {
+    let (url, _) = start(false).await;
+
+    let client = login(&url).await;
+    let pending = client.pending_devices().await.unwrap();
+
+    assert_eq!(client.kind(), ControllerKind::Classic);
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].mac, MAC);
+    assert_eq!(pending[0].state_label(), "pending adoption");
+}
+
+#[tokio::test]
+async fn unifi_os_login_uses_proxy_prefix_and_csrf() {
+    let (url, fake) = start(true).await;
+
+    let client = login(&url).await;
+    client.adopt(MAC).await.unwrap();
+
+    assert_eq!(client.kind(), ControllerKind::UnifiOs);
+    assert_eq!(
+        fake.lock().unwrap().adopt_requests,
+        [json!({ "cmd": "adopt", "mac": MAC })]
+    );
+}
+
+#[tokio::test]
+async fn adoption_is_polled_until_connected() {
+    let (url, _) = start(true).await;
+    let client = login(&url).await;
+
+    client.adopt(&MAC.to_uppercase()).await.unwrap();
+    let device = client
+        .wait_until_connected(MAC, Duration::from_secs(5), Duration::from_millis(10))
+        .await
+        .unwrap();
+
+    assert_eq!(device.state, STATE_CONNECTED);
+}
+
+#[tokio::test]
+async fn polling_gives_up_after_timeout() {
+    let (url, _) = start(false).await;
+    let client = login(&url).await;
+
+    let err = client
+        .wait_until_connected(MAC, Duration::from_millis(50), Duration::from_millis(10))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ControllerError::Timeout(msg) if msg.contains("pending adoption")));
+}
+
+#[tokio::test]
+async fn wrong_password_is_auth_failure() {
+    let (url, _) = start(false).await;
+
+    let result = ControllerClient::login(
+        &url,
+        "admin",
+        "wrong",
+        "default",
+        &NetworkSettings::default(),
+        false,
+    )
+    .await;
+
+    assert!(matches!(result, Err(ControllerError::AuthFailed(_))));
+}
+
+#[tokio::test]
+async fn site_names_that_would_change_the_path_are_refused() {
+    let (url, _) = start(false).await;
+
+    for site in ["", "../admin", "a/b", "def ault", "x?y"] {
+        let result = ControllerClient::login(
+            &url,
+            "admin",
+            "secret",
+            site,
+            &NetworkSettings::default(),
+            false,
+        )
+        .await;
+        assert!(
+            matches!(result, Err(ControllerError::Api(_))),
+            "{:?} should be refused",
+            site
+        );
+    }
+}
+
+#[tokio::test]
+async fn controller_errors_are_surfaced() {
+    let (url, _) = start(false).await;
+    let client = login(&url).await;
+
+    let err = client.adopt("00:11:22:33:44:55").await.unwrap_err();
+
+    assert!(matches!(err, ControllerError::Api(msg) if msg == "api.err.UnknownDevice"));
+}
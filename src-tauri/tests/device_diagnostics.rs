@@ -0,0 +1,98 @@
+//! Splitting the collected command output and writing the archive.
+use std::io::Read;
+
+use app_lib::device_diagnostics::{self, ArchiveManifest, COMMANDS};
+
+/// What the AP prints for `collect_command`, with a session that dies
+/// during `mca-cli-op info`.
+fn sample_output() -> String {
+    [
+        "VS_SECTION=info",
+        "Model:       UAP-AC-Lite",
+        "Status:      Not Adopted",
+        "VS_EXIT=0",
+        "VS_SECTION=messages",
+        "Jan  1 00:00:12 U6-Lite daemon.info mcad: inform failed",
+        "VS_EXIT=0\r",
+        "VS_SECTION=ifconfig",
+        "br0       Link encap:Ethernet  HWaddr FC:EC:DA:11:22:33",
+        "VS_EXIT=0",
+        "VS_SECTION=route",
+        "0.0.0.0         192.168.1.1     0.0.0.0         UG    0      0        0 br0",
+        "VS_EXIT=0",
+        "VS_SECTION=resolv.conf",
+        "cat: can't open '/etc/resolv.conf': No such file or directory",
+        "VS_EXIT=1",
+        "VS_SECTION=mca-cli-op-info",
+        "partial",
+    ]
+    .join("\n")
+}
+
+#[test]
+fn command_runs_every_diagnostic() {
+    let command = device_diagnostics::collect_command();
+    for (name, cmd) in COMMANDS {
+        assert!(command.contains(&format!("echo VS_SECTION={};", name)));
+        assert!(command.contains(cmd));
+    }
+}
+
+#[test]
+fn output_is_split_per_command() {
+    let sections = device_diagnostics::parse_output(&sample_output());
+    assert_eq!(sections.len(), COMMANDS.len());
+
+    assert_eq!(sections[0].name, "info");
+    assert_eq!(
+        sections[0].output,
+        "Model:       UAP-AC-Lite\nStatus:      Not Adopted"
+    );
+    assert_eq!(sections[0].exit_code, Some(0));
+    assert_eq!(sections[1].exit_code, Some(0));
+    assert_eq!(sections[4].name, "resolv.conf");
+    assert_eq!(sections[4].exit_code, Some(1));
+
+    // Cut short: output kept, no exit code; never started: empty
+    assert_eq!(sections[5].output, "partial");
+    assert_eq!(sections[5].exit_code, None);
+    assert_eq!(sections[6].name, "uptime");
+    assert_eq!(sections[6].output, "");
+    assert_eq!(sections[6].exit_code, None);
+}
+
+#[test]
+fn archive_contains_manifest_discovery_and_outputs() {
+    let sections = device_diagnostics::parse_output(&sample_output());
+    let manifest = ArchiveManifest::new("192.168.1.20", None, &sections, "2026-01-18T14:25:01Z");
+    let name = device_diagnostics::archive_name("192.168.1.20", None, "20260118-142501");
+    assert_eq!(name, "192-168-1-20-20260118-142501.zip");
+
+    let dir = std::env::temp_dir().join(format!("vs-device-diag-{}", std::process::id()));
+    let path = dir.join(&name);
+    device_diagnostics::write_archive(&path, &manifest, None, &sections).unwrap();
+
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut read = |name: &str| {
+        let mut contents = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    };
+
+    let manifest: serde_json::Value = serde_json::from_str(&read("manifest.json")).unwrap();
+    assert_eq!(manifest["ip"], "192.168.1.20");
+    assert_eq!(
+        manifest["commands"].as_array().unwrap().len(),
+        COMMANDS.len()
+    );
+    assert_eq!(manifest["commands"][4]["exitCode"], 1);
+    assert_eq!(read("discovery.json"), "null");
+    assert!(read("commands/messages.txt").contains("inform failed"));
+    assert_eq!(read("commands/uptime.txt"), "");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,67 @@
+//! Secrets are stripped from log lines before they reach disk or a bundle.
+use app_lib::diagnostics::redact;
+
+#[test]
+fn credential_pairs_are_redacted() {
+    for (line, secret) in [
+        ("ssh login with password=ubnt2024", "ubnt2024"),
+        ("passwd: hunter2", "hunter2"),
+        ("pass=s3cret&user=ubnt", "s3cret"),
+        ("secret = abc123", "abc123"),
+        ("Cookie: unifises=f00dcafe", "unifises=f00dcafe"),
+        ("Authorization: Bearer eyJhbGciOi", "eyJhbGciOi"),
+        ("authorization: basic dWJudDp1Ym50", "dWJudDp1Ym50"),
+    ] {
+        let redacted = redact(line);
+        assert!(!redacted.contains(secret), "{} -> {}", line, redacted);
+        assert!(redacted.contains("[REDACTED]"), "{} -> {}", line, redacted);
+    }
+}
+
+#[test]
+fn json_and_mixed_case_keys_are_redacted() {
+    let redacted = redact(r#"{"Token": "abc.def.ghi", "site": "cafe"}"#);
+    assert_eq!(redacted, r#"{"Token": "[REDACTED]", "site": "cafe"}"#);
+
+    let redacted = redact("mgmt.authkey=0123456789abcdef0123456789abcdef");
+    assert_eq!(redacted, "mgmt.authkey=[REDACTED]");
+
+    let redacted = redact(r#"inform payload {"x_authkey":"fedcba9876543210"}"#);
+    assert_eq!(redacted, r#"inform payload {"x_authkey":"[REDACTED]"}"#);
+}
+
+#[test]
+fn device_config_secrets_are_redacted() {
+    let config = "wireless.1.ssid=Cafe\n\
+                  wireless.1.security.psk=correct-horse\n\
+                  aaa.1.wpa.psk=battery-staple\n\
+                  users.1.password=$1$xyz$Q2nFvjhRkW\n\
+                  users.1.hash=$6$abc$9vL0s\n\
+                  mgmt.authkey=0123456789abcdef";
+    let redacted = redact(config);
+    for secret in [
+        "correct-horse",
+        "battery-staple",
+        "Q2nFvjhRkW",
+        "9vL0s",
+        "0123456789abcdef",
+    ] {
+        assert!(!redacted.contains(secret), "{}", redacted);
+    }
+    assert!(redacted.contains("wireless.1.ssid=Cafe"));
+    assert!(redacted.contains("aaa.1.wpa.psk=[REDACTED]"));
+}
+
+#[test]
+fn setup_codes_keep_only_the_last_two_characters() {
+    assert_eq!(redact("Validating VS-7K2M"), "Validating VS-**2M");
+    assert_eq!(redact("code vs-ab9x cached"), "code VS-**9x cached");
+}
+
+#[test]
+fn ordinary_lines_are_untouched() {
+    let line = "Discovered UAP-AC-Lite at 192.168.1.20 (FC:EC:DA:11:22:33)";
+    assert_eq!(redact(line), line);
+    // Words that merely contain a key name aren't pairs
+    assert_eq!(redact("passing checks"), "passing checks");
+}
@@ -0,0 +1,19 @@
+//! MAC normalisation shared by everything that matches devices.
+use app_lib::discovery::{normalize_mac, same_mac};
+
+#[test]
+fn macs_normalize_to_discovery_form() {
+    assert_eq!(normalize_mac("fc-ec-da-11-22-33"), "FC:EC:DA:11:22:33");
+    assert_eq!(normalize_mac("fcec.da11.2233"), "FC:EC:DA:11:22:33");
+    assert_eq!(normalize_mac("FCECDA112233"), "FC:EC:DA:11:22:33");
+}
+
+#[test]
+fn mac_confirmation_ignores_case_and_separators() {
+    assert!(same_mac("FC:EC:DA:11:22:33", "fc:ec:da:11:22:33"));
+    assert!(same_mac("FC:EC:DA:11:22:33", "fc-ec-da-11-22-33"));
+    assert!(same_mac("FC:EC:DA:11:22:33", "fcecda112233"));
+    assert!(!same_mac("FC:EC:DA:11:22:33", "fc:ec:da:11:22:34"));
+    assert!(!same_mac("", ""));
+    assert!(!same_mac("FC:EC:DA:11:22:33", "yes"));
+}
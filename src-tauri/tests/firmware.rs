@@ -0,0 +1,108 @@
+//! Firmware image handling and the local firmware server.
+use app_lib::firmware::{
+    is_version, upgrade_command, version_from_name, FirmwareImage, FirmwareServer, LOG_PATH,
+};
+
+const IMAGE_NAME: &str = "BZ.qca956x.v6.5.28.14491.230126.0034.bin";
+
+fn image() -> FirmwareImage {
+    let bytes: Vec<u8> = (0..2 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    FirmwareImage::from_bytes(IMAGE_NAME, bytes).unwrap()
+}
+
+#[test]
+fn version_comes_from_the_file_name() {
+    assert_eq!(
+        version_from_name(IMAGE_NAME).as_deref(),
+        Some("6.5.28.14491")
+    );
+    assert_eq!(
+        version_from_name("UAP6MP.mt7622.v6.6.55.15189.231208.1012.bin").as_deref(),
+        Some("6.6.55.15189")
+    );
+    assert_eq!(version_from_name("firmware.bin"), None);
+    assert_eq!(version_from_name("BZ.v6.bin"), None);
+}
+
+#[test]
+fn discovery_string_matches_expected_version() {
+    assert!(is_version(
+        "BZ.qca956x.v6.5.28.14491.230126.0034",
+        "6.5.28.14491"
+    ));
+    assert!(is_version("6.5.28.14491", "6.5.28.14491"));
+    assert!(!is_version(
+        "BZ.qca956x.v4.3.28.11361.201012.1023",
+        "6.5.28.14491"
+    ));
+}
+
+#[test]
+fn rejects_files_that_are_not_images() {
+    assert!(FirmwareImage::from_bytes("notes.bin", vec![0; 100])
+        .unwrap_err()
+        .contains("isn't a firmware image"));
+
+    let dir = std::env::temp_dir().join(format!("vs-firmware-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("firmware.zip");
+    std::fs::write(&path, vec![0; 2 * 1024 * 1024]).unwrap();
+    assert!(FirmwareImage::load(&path).unwrap_err().contains(".bin"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn served_name_is_url_safe() {
+    let image =
+        FirmwareImage::from_bytes("UAP AC (lite).v6.5.28.14491.bin", vec![0; 1024 * 1024]).unwrap();
+    assert_eq!(image.name, "UAP_AC__lite_.v6.5.28.14491.bin");
+}
+
+#[test]
+fn upgrade_runs_in_the_background_with_a_log() {
+    let command = upgrade_command("http://192.168.1.5:41234/fw.bin");
+    assert!(command.contains("upgrade 'http://192.168.1.5:41234/fw.bin'"));
+    assert!(command.contains(LOG_PATH));
+    assert!(command.contains("VS_UPGRADE=started"));
+    assert!(std::process::Command::new("sh")
+        .args(["-n", "-c", &command])
+        .status()
+        .unwrap()
+        .success());
+}
+
+#[tokio::test]
+async fn server_serves_the_image_and_counts_downloads() {
+    let image = image();
+    let expected = image.bytes.clone();
+    let server = FirmwareServer::start("127.0.0.1:0", image).await.unwrap();
+    let url = server.url("127.0.0.1".parse().unwrap());
+    assert!(url.ends_with(IMAGE_NAME));
+    assert_eq!(server.status().requests, 0);
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.bytes().await.unwrap().as_ref(),
+        expected.as_slice()
+    );
+
+    // Counted once the client has read it all and hung up
+    let mut status = server.status();
+    for _ in 0..50 {
+        if status.completed == 1 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        status = server.status();
+    }
+    assert_eq!(status.requests, 1);
+    assert_eq!(status.completed, 1);
+    assert_eq!(status.last_client.as_deref(), Some("127.0.0.1"));
+
+    let other = reqwest::get(format!("http://{}/other.bin", server.local_addr()))
+        .await
+        .unwrap();
+    assert_eq!(other.status(), 404);
+    assert_eq!(server.status().requests, 1);
+}
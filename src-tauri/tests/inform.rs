@@ -0,0 +1,285 @@
+//! Inform packet decoding, the local inform listener and relay mode, driven
+//! with packets built the way a factory-default AP builds them.
+use app_lib::inform::{
+    local_ip_towards, InformError, InformListener, InformPacket, DEFAULT_AUTH_KEY, FLAG_ENCRYPTED,
+    FLAG_GCM, FLAG_SNAPPY, FLAG_ZLIB,
+};
+use app_lib::local_http::HttpResponse;
+use app_lib::mock_api::{MockApi, MockResponse};
+use app_lib::settings::NetworkSettings;
+use serde_json::{json, Value};
+
+const MAC: [u8; 6] = [0xfc, 0xec, 0xda, 0x11, 0x22, 0x33];
+const IV: [u8; 16] = *b"0123456789abcdef";
+
+fn device_state() -> Value {
+    json!({
+        "mac": "fc:ec:da:11:22:33",
+        "model": "U7LT",
+        "model_display": "UAP-AC-Lite",
+        "version": "6.5.28.14491",
+        "hostname": "UAP-AC-Lite",
+        "inform_url": "http://192.168.1.5:8080/inform",
+        "default": true,
+        "state": 1,
+    })
+}
+
+fn device_packet(flags: u16) -> Vec<u8> {
+    InformPacket::encode(
+        MAC,
+        flags,
+        IV,
+        device_state().to_string().as_bytes(),
+        &DEFAULT_AUTH_KEY,
+    )
+    .unwrap()
+}
+
+#[test]
+fn parses_header_fields() {
+    let mut bytes = b"TNBU".to_vec();
+    bytes.extend_from_slice(&0u32.to_be_bytes());
+    bytes.extend_from_slice(&MAC);
+    bytes.extend_from_slice(&(FLAG_ENCRYPTED | FLAG_ZLIB).to_be_bytes());
+    bytes.extend_from_slice(&IV);
+    bytes.extend_from_slice(&1u32.to_be_bytes());
+    bytes.extend_from_slice(&3u32.to_be_bytes());
+    bytes.extend_from_slice(&[1, 2, 3, 4, 5]);
+
+    let packet = InformPacket::parse(&bytes).unwrap();
+    assert_eq!(packet.mac_string(), "fc:ec:da:11:22:33");
+    assert_eq!(packet.flags, FLAG_ENCRYPTED | FLAG_ZLIB);
+    assert_eq!(packet.iv, IV);
+    assert_eq!(packet.payload_version, 1);
+    // Trailing bytes beyond the declared length are ignored
+    assert_eq!(packet.payload, vec![1, 2, 3]);
+    assert!(packet.is_encrypted());
+    assert!(!packet.is_gcm());
+}
+
+#[test]
+fn rejects_truncated_and_foreign_packets() {
+    let bytes = device_packet(FLAG_ENCRYPTED | FLAG_ZLIB);
+    assert!(matches!(
+        InformPacket::parse(&bytes[..20]),
+        Err(InformError::Malformed(_))
+    ));
+    assert!(matches!(
+        InformPacket::parse(&bytes[..bytes.len() - 1]),
+        Err(InformError::Malformed(_))
+    ));
+
+    let mut foreign = bytes.clone();
+    foreign[..4].copy_from_slice(b"HTTP");
+    assert!(matches!(
+        InformPacket::parse(&foreign),
+        Err(InformError::Malformed(_))
+    ));
+}
+
+#[test]
+fn decodes_cbc_zlib_payload_with_default_key() {
+    let packet = InformPacket::parse(&device_packet(FLAG_ENCRYPTED | FLAG_ZLIB)).unwrap();
+    assert_eq!(packet.payload.len() % 16, 0);
+    assert_eq!(
+        packet.decode_json(&DEFAULT_AUTH_KEY).unwrap(),
+        device_state()
+    );
+}
+
+#[test]
+fn decodes_gcm_payload_and_authenticates_header() {
+    let bytes = device_packet(FLAG_ENCRYPTED | FLAG_ZLIB | FLAG_GCM);
+    let packet = InformPacket::parse(&bytes).unwrap();
+    assert!(packet.is_gcm());
+    assert_eq!(
+        packet.decode_json(&DEFAULT_AUTH_KEY).unwrap(),
+        device_state()
+    );
+
+    // Flipping a MAC byte in the header breaks the tag
+    let mut tampered = bytes.clone();
+    tampered[13] ^= 0xff;
+    let packet = InformPacket::parse(&tampered).unwrap();
+    assert!(matches!(
+        packet.decrypt(&DEFAULT_AUTH_KEY),
+        Err(InformError::Decrypt(_))
+    ));
+}
+
+#[test]
+fn adopted_device_key_is_not_readable() {
+    let device_key = *b"per-device-key!!";
+    let bytes = InformPacket::encode(
+        MAC,
+        FLAG_ENCRYPTED | FLAG_ZLIB,
+        IV,
+        device_state().to_string().as_bytes(),
+        &device_key,
+    )
+    .unwrap();
+    let packet = InformPacket::parse(&bytes).unwrap();
+    assert!(matches!(
+        packet.decode_json(&DEFAULT_AUTH_KEY),
+        Err(InformError::Decrypt(_))
+    ));
+    assert_eq!(packet.decode_json(&device_key).unwrap(), device_state());
+}
+
+#[test]
+fn snappy_is_reported_as_unsupported() {
+    assert!(matches!(
+        InformPacket::encode(
+            MAC,
+            FLAG_ENCRYPTED | FLAG_SNAPPY,
+            IV,
+            b"{}",
+            &DEFAULT_AUTH_KEY
+        ),
+        Err(InformError::Unsupported(_))
+    ));
+}
+
+async fn post_inform(listener: &InformListener, body: Vec<u8>) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{}/inform", listener.local_addr()))
+        .header("Content-Type", "application/x-binary")
+        .header("User-Agent", "AirControl Agent v1.0")
+        .body(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn listener_records_device_and_replies_with_noop() {
+    let listener = InformListener::start("127.0.0.1:0").await.unwrap();
+
+    for _ in 0..2 {
+        let response = post_inform(&listener, device_packet(FLAG_ENCRYPTED | FLAG_ZLIB)).await;
+        assert_eq!(response.status(), 200);
+        let reply = InformPacket::parse(&response.bytes().await.unwrap()).unwrap();
+        assert_eq!(reply.mac_string(), "fc:ec:da:11:22:33");
+        let body = reply.decode_json(&DEFAULT_AUTH_KEY).unwrap();
+        assert_eq!(body["_type"], "noop");
+        assert_eq!(body["interval"], 10);
+    }
+
+    let record = listener.record("FC:EC:DA:11:22:33").unwrap();
+    assert_eq!(record.source_ip, "127.0.0.1");
+    assert_eq!(record.model.as_deref(), Some("UAP-AC-Lite"));
+    assert_eq!(record.version.as_deref(), Some("6.5.28.14491"));
+    assert_eq!(
+        record.inform_url.as_deref(),
+        Some("http://192.168.1.5:8080/inform")
+    );
+    assert_eq!(record.is_default, Some(true));
+    assert_eq!(record.inform_count, 2);
+    assert!(record.error.is_none());
+    assert_eq!(listener.records().len(), 1);
+}
+
+#[tokio::test]
+async fn listener_records_informs_it_cannot_decrypt() {
+    let listener = InformListener::start("127.0.0.1:0").await.unwrap();
+    let bytes = InformPacket::encode(
+        MAC,
+        FLAG_ENCRYPTED | FLAG_ZLIB,
+        IV,
+        b"{}",
+        b"per-device-key!!",
+    )
+    .unwrap();
+
+    let response = post_inform(&listener, bytes).await;
+    assert_eq!(response.status(), 400);
+
+    let record = listener.record("fc:ec:da:11:22:33").unwrap();
+    assert_eq!(record.inform_count, 1);
+    assert!(record.model.is_none());
+    assert!(record.error.unwrap().contains("decrypt"));
+
+    let garbage = post_inform(&listener, b"not an inform".to_vec()).await;
+    assert_eq!(garbage.status(), 400);
+    assert_eq!(listener.records().len(), 1);
+}
+
+#[tokio::test]
+async fn stopping_releases_the_port() {
+    let listener = InformListener::start("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr();
+    listener.stop().await;
+    let restarted = InformListener::start(&addr.to_string()).await.unwrap();
+    assert_eq!(restarted.local_addr(), addr);
+}
+
+#[test]
+fn inform_url_uses_address_towards_device() {
+    let ip = local_ip_towards("127.0.0.1".parse().unwrap()).unwrap();
+    assert_eq!(ip.to_string(), "127.0.0.1");
+}
+
+#[tokio::test]
+async fn relay_forwards_informs_and_returns_controller_reply() {
+    let controller = MockApi::start().await.unwrap();
+    controller.respond(
+        "/inform",
+        MockResponse {
+            response: HttpResponse::new(200, "application/x-binary", b"controller-reply".to_vec()),
+            delay: None,
+        },
+    );
+    let upstream = format!("{}/inform", controller.base_url());
+    let relay = InformListener::start_relay("127.0.0.1:0", &upstream, &NetworkSettings::default())
+        .await
+        .unwrap();
+    assert_eq!(relay.upstream(), Some(upstream.as_str()));
+
+    let packet = device_packet(FLAG_ENCRYPTED | FLAG_ZLIB);
+    let response = post_inform(&relay, packet.clone()).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.bytes().await.unwrap().as_ref(),
+        b"controller-reply"
+    );
+
+    let forwarded = controller.requests();
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0].body, packet);
+    assert_eq!(
+        forwarded[0].header("user-agent"),
+        Some("AirControl Agent v1.0")
+    );
+
+    let record = relay.record("fc:ec:da:11:22:33").unwrap();
+    assert_eq!(record.model.as_deref(), Some("UAP-AC-Lite"));
+    let status = record.relay.unwrap();
+    assert_eq!(status.forwarded, 1);
+    assert_eq!(status.last_status, Some(200));
+    assert!(status.last_error.is_none());
+}
+
+#[tokio::test]
+async fn relay_reports_unreachable_controller() {
+    // Bind and drop to get a port nothing listens on
+    let closed = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let relay = InformListener::start_relay(
+        "127.0.0.1:0",
+        &format!("http://{}/inform", closed),
+        &NetworkSettings::default(),
+    )
+    .await
+    .unwrap();
+
+    let response = post_inform(&relay, device_packet(FLAG_ENCRYPTED | FLAG_ZLIB)).await;
+    assert_eq!(response.status(), 502);
+
+    let status = relay.record("fc:ec:da:11:22:33").unwrap().relay.unwrap();
+    assert_eq!(status.forwarded, 0);
+    assert_eq!(status.failed, 1);
+    assert!(status.last_error.is_some());
+}
@@ -0,0 +1,85 @@
+//! Reconciling a site's planned devices with what a scan found.
+use app_lib::api::ExpectedDevice;
+use app_lib::discovery::DiscoveredDevice;
+use app_lib::inventory::reconcile;
+
+fn expected(mac: Option<&str>, model: Option<&str>, name: &str) -> ExpectedDevice {
+    ExpectedDevice {
+        mac: mac.map(str::to_string),
+        model: model.map(str::to_string),
+        name: Some(name.to_string()),
+        location: None,
+    }
+}
+
+fn scanned(mac: &str, model: &str) -> DiscoveredDevice {
+    DiscoveredDevice {
+        mac: mac.to_string(),
+        ip: "192.168.1.20".to_string(),
+        reported_ip: "192.168.1.20".to_string(),
+        model: model.to_string(),
+        firmware: "6.6.55".to_string(),
+        hostname: "UAP".to_string(),
+        is_managed: false,
+        is_default: true,
+        is_locating: false,
+    }
+}
+
+#[test]
+fn planned_devices_are_found_by_mac_then_model() {
+    let plan = [
+        expected(Some("fc-ec-da-11-22-33"), None, "Lobby AP"),
+        expected(None, Some("u7lt"), "Bar AP"),
+    ];
+    let scan = [
+        scanned("FC:EC:DA:44:55:66", "U7LT"),
+        scanned("FC:EC:DA:11:22:33", "U7LT"),
+    ];
+
+    let result = reconcile(&plan, &scan);
+
+    assert!(result.complete);
+    assert!(result.missing.is_empty() && result.unexpected.is_empty());
+    // The MAC entry claims its own device, leaving the other for the model entry
+    assert_eq!(result.found[0].device.mac, "FC:EC:DA:11:22:33");
+    assert_eq!(result.found[1].expected.name.as_deref(), Some("Bar AP"));
+    assert_eq!(result.found[1].device.mac, "FC:EC:DA:44:55:66");
+}
+
+#[test]
+fn devices_not_seen_are_missing() {
+    let plan = [
+        expected(Some("fcecda112233"), None, "Lobby AP"),
+        expected(None, Some("U6-Lite"), "Patio AP"),
+        expected(None, None, "Unplanned"),
+    ];
+    let scan = [scanned("FC:EC:DA:11:22:33", "U7LT")];
+
+    let result = reconcile(&plan, &scan);
+
+    assert!(!result.complete);
+    assert_eq!(result.found.len(), 1);
+    let missing: Vec<_> = result
+        .missing
+        .iter()
+        .map(|d| d.name.as_deref().unwrap())
+        .collect();
+    assert_eq!(missing, ["Patio AP", "Unplanned"]);
+}
+
+#[test]
+fn devices_not_planned_are_unexpected() {
+    let plan = [expected(None, Some("U7LT"), "Lobby AP")];
+    let scan = [
+        scanned("FC:EC:DA:11:22:33", "U7LT"),
+        scanned("FC:EC:DA:44:55:66", "U7LT"),
+        scanned("FC:EC:DA:77:88:99", "U6-Lite"),
+    ];
+
+    let result = reconcile(&plan, &scan);
+
+    assert!(result.complete);
+    let unexpected: Vec<_> = result.unexpected.iter().map(|d| d.mac.as_str()).collect();
+    assert_eq!(unexpected, ["FC:EC:DA:44:55:66", "FC:EC:DA:77:88:99"]);
+}
@@ -0,0 +1,48 @@
+//! Locate LED command generation and output parsing.
+use app_lib::locate::{
+    duration_secs, locate_command, parse_output, LocateMethod, DEFAULT_DURATION_SECS,
+    MAX_DURATION_SECS,
+};
+
+#[test]
+fn command_tries_each_tool_in_turn() {
+    let on = locate_command(true);
+    assert!(on.contains("syswrapper.sh set-locate"));
+    assert!(on.contains("mca-ctrl -t locate-on"));
+    assert!(on.find("syswrapper.sh").unwrap() < on.find("mca-ctrl").unwrap());
+
+    let off = locate_command(false);
+    assert!(off.contains("syswrapper.sh unset-locate"));
+    assert!(off.contains("mca-ctrl -t locate-off"));
+}
+
+#[test]
+fn reports_which_tool_worked() {
+    assert_eq!(
+        parse_output("VS_LOCATE=syswrapper\n"),
+        Ok(LocateMethod::Syswrapper)
+    );
+    // Login banners around the marker are ignored
+    assert_eq!(
+        parse_output("BusyBox v1.25.1 built-in shell (ash)\n\nVS_LOCATE=mca-ctrl\r\n"),
+        Ok(LocateMethod::McaCtrl)
+    );
+}
+
+#[test]
+fn firmware_without_locate_is_an_error() {
+    assert!(parse_output("VS_LOCATE=none")
+        .unwrap_err()
+        .contains("no locate command"));
+    assert!(parse_output("sh: syntax error")
+        .unwrap_err()
+        .contains("Unexpected output"));
+}
+
+#[test]
+fn duration_is_clamped() {
+    assert_eq!(duration_secs(None), DEFAULT_DURATION_SECS);
+    assert_eq!(duration_secs(Some(0)), 1);
+    assert_eq!(duration_secs(Some(120)), 120);
+    assert_eq!(duration_secs(Some(u64::MAX)), MAX_DURATION_SECS);
+}
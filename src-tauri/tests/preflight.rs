@@ -0,0 +1,279 @@
+//! Inform URL preflight checks against local listeners and canned AP output.
+use app_lib::local_http::{self, HttpResponse};
+use app_lib::preflight::{self, CheckStatus, PreflightReport};
+use app_lib::settings::NetworkSettings;
+use tokio::net::TcpListener;
+
+fn statuses(report: &PreflightReport) -> Vec<(&str, CheckStatus)> {
+    report
+        .checks
+        .iter()
+        .map(|c| (c.name.as_str(), c.status))
+        .collect()
+}
+
+/// Serve `status` for every request, like a controller's inform port.
+async fn inform_server(status: u16) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(local_http::serve(listener, move |_| async move {
+        HttpResponse::text(status, "")
+    }));
+    format!("http://{}/inform", addr)
+}
+
+#[tokio::test]
+async fn reachable_controller_passes() {
+    let url = inform_server(400).await;
+
+    let report = preflight::check_from_laptop(&url, &NetworkSettings::default()).await;
+
+    assert!(report.passed, "{:?}", report);
+    assert_eq!(
+        statuses(&report),
+        [
+            ("dns", CheckStatus::Pass),
+            ("tcp", CheckStatus::Pass),
+            ("http", CheckStatus::Pass)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn missing_inform_endpoint_fails_http() {
+    let url = inform_server(404).await;
+
+    let report = preflight::check_from_laptop(&url, &NetworkSettings::default()).await;
+
+    assert!(!report.passed);
+    assert_eq!(report.checks[2].status, CheckStatus::Fail);
+    assert!(report.summary().contains("http"));
+}
+
+#[tokio::test]
+async fn closed_port_fails_tcp_and_skips_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let report = preflight::check_from_laptop(
+        &format!("http://{}/inform", addr),
+        &NetworkSettings::default(),
+    )
+    .await;
+
+    assert_eq!(
+        statuses(&report),
+        [
+            ("dns", CheckStatus::Pass),
+            ("tcp", CheckStatus::Fail),
+            ("http", CheckStatus::Skipped)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn unresolvable_host_fails_dns() {
+    let report = preflight::check_from_laptop(
+        "http://controller.invalid:8080/inform",
+        &NetworkSettings::default(),
+    )
+    .await;
+
+    assert!(!report.passed);
+    assert_eq!(report.checks[0].status, CheckStatus::Fail);
+    assert_eq!(report.checks[1].status, CheckStatus::Skipped);
+}
+
+#[test]
+fn inform_target_uses_url_port() {
+    assert_eq!(
+        preflight::inform_target("http://unifi.example.com:8080/inform").unwrap(),
+        ("unifi.example.com".to_string(), 8080)
+    );
+    assert_eq!(
+        preflight::inform_target("http://unifi.example.com/inform").unwrap(),
+        ("unifi.example.com".to_string(), 80)
+    );
+    assert!(preflight::inform_target("not a url").is_err());
+}
+
+const URL: &str = "http://unifi.example.com:8080/inform";
+
+const NSLOOKUP_OK: &str = "VS_CHECK=dns
+Server:    192.168.1.1
+Address 1: 192.168.1.1 router.lan
+
+Name:      unifi.example.com
+Address 1: 203.0.113.7 unifi.example.com
+VS_EXIT=0";
+
+const PING_OK: &str = "VS_CHECK=ping
+PING unifi.example.com (203.0.113.7): 56 data bytes
+64 bytes from 203.0.113.7: seq=0 ttl=52 time=12.1 ms
+
+--- unifi.example.com ping statistics ---
+3 packets transmitted, 3 packets received, 0% packet loss
+round-trip min/avg/max = 11.8/12.0/12.3 ms
+VS_EXIT=0";
+
+fn device_report(http: &str) -> PreflightReport {
+    let output = format!("{}\n{}\nVS_CHECK=http\n{}", NSLOOKUP_OK, PING_OK, http);
+    preflight::parse_device_output(URL, &output)
+}
+
+#[test]
+fn healthy_device_passes_every_check() {
+    let report = device_report("wget: server returned error: HTTP/1.1 400 Bad Request\nVS_EXIT=1");
+
+    assert!(report.passed);
+    assert_eq!(report.source, "device");
+    assert_eq!(
+        statuses(&report),
+        [
+            ("dns", CheckStatus::Pass),
+            ("ping", CheckStatus::Pass),
+            ("tcp", CheckStatus::Pass),
+            ("http", CheckStatus::Pass)
+        ]
+    );
+    assert_eq!(report.checks[0].detail, "unifi.example.com → 203.0.113.7");
+    assert_eq!(report.checks[1].detail, "3/3 replies, avg 12.0 ms");
+}
+
+#[test]
+fn dns_failure_skips_the_rest() {
+    let output = "VS_CHECK=dns
+Server:    192.168.1.1
+Address 1: 192.168.1.1 router.lan
+
+nslookup: can't resolve 'unifi.example.com'
+VS_EXIT=1
+VS_CHECK=ping
+ping: bad address 'unifi.example.com'
+VS_EXIT=1
+VS_CHECK=http
+wget: bad address 'unifi.example.com:8080'
+VS_EXIT=1";
+
+    let report = preflight::parse_device_output(URL, output);
+
+    assert!(!report.passed);
+    assert_eq!(
+        statuses(&report),
+        [
+            ("dns", CheckStatus::Fail),
+            ("ping", CheckStatus::Skipped),
+            ("tcp", CheckStatus::Skipped),
+            ("http", CheckStatus::Skipped)
+        ]
+    );
+    assert!(report.summary().contains("can't resolve unifi.example.com"));
+}
+
+#[test]
+fn blocked_ping_only_warns() {
+    let output = format!(
+        "{}\nVS_CHECK=ping\n3 packets transmitted, 0 packets received, 100% packet loss\nVS_EXIT=1\nVS_CHECK=http\nVS_EXIT=0",
+        NSLOOKUP_OK
+    );
+
+    let report = preflight::parse_device_output(URL, &output);
+
+    assert!(report.passed);
+    assert_eq!(report.checks[1].status, CheckStatus::Warn);
+    assert!(report.checks[1].detail.contains("0/3 replies"));
+}
+
+#[test]
+fn only_inform_endpoint_answers_pass() {
+    let cases = [
+        (400, CheckStatus::Pass),
+        (405, CheckStatus::Pass),
+        (404, CheckStatus::Fail),
+        (200, CheckStatus::Warn),
+        (302, CheckStatus::Warn),
+        (307, CheckStatus::Warn),
+        (407, CheckStatus::Warn),
+        (502, CheckStatus::Warn),
+        (503, CheckStatus::Warn),
+    ];
+    for (http, expected) in cases {
+        let (status, detail) = preflight::classify_http_status(http);
+        assert_eq!(status, expected, "HTTP {}", http);
+        assert!(detail.contains(&http.to_string()), "{}", detail);
+    }
+}
+
+#[tokio::test]
+async fn captive_portal_redirect_only_warns() {
+    let url = inform_server(302).await;
+
+    let report = preflight::check_from_laptop(&url, &NetworkSettings::default()).await;
+
+    assert!(report.passed);
+    assert_eq!(report.checks[2].status, CheckStatus::Warn);
+}
+
+#[test]
+fn wget_failures_are_classified() {
+    let cases = [
+        (
+            "wget: server returned error: HTTP/1.1 404 Not Found\nVS_EXIT=1",
+            [CheckStatus::Pass, CheckStatus::Fail],
+        ),
+        (
+            "wget: can't connect to remote host (203.0.113.7): Connection refused\nVS_EXIT=1",
+            [CheckStatus::Fail, CheckStatus::Skipped],
+        ),
+        (
+            "wget: download timed out\nVS_EXIT=1",
+            [CheckStatus::Fail, CheckStatus::Skipped],
+        ),
+        (
+            "sh: wget: not found\nVS_EXIT=127",
+            [CheckStatus::Skipped, CheckStatus::Fail],
+        ),
+    ];
+
+    for (http, [tcp, status]) in cases {
+        let report = device_report(http);
+        assert!(!report.passed, "output: {}", http);
+        assert_eq!(
+            statuses(&report)[2..],
+            [("tcp", tcp), ("http", status)],
+            "output: {}",
+            http
+        );
+    }
+}
+
+#[test]
+fn ip_inform_url_skips_the_lookup() {
+    let url = "http://203.0.113.7:8080/inform";
+    let command = preflight::device_command(url).unwrap();
+    assert!(!command.contains("nslookup"));
+
+    let report =
+        preflight::parse_device_output(url, "VS_CHECK=ping\nVS_EXIT=0\nVS_CHECK=http\nVS_EXIT=0");
+
+    assert!(report.passed);
+    assert_eq!(report.checks[0].status, CheckStatus::Pass);
+}
+
+#[test]
+fn device_command_quotes_the_url() {
+    let command = preflight::device_command("http://host:8080/inform?a='b'").unwrap();
+
+    assert!(command.contains("nslookup 'host'"));
+    assert!(command.contains(r"'http://host:8080/inform?a='\''b'\'''"));
+    assert!(command.ends_with("echo \"VS_EXIT=$?\""));
+}
+
+#[test]
+fn unreadable_output_fails() {
+    let report = preflight::parse_device_output(URL, "");
+
+    assert!(!report.passed);
+    assert_eq!(report.checks[0].status, CheckStatus::Fail);
+}
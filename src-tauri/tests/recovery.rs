@@ -0,0 +1,266 @@
+//! TFTP recovery flashing against a local stand-in for an AP's recovery
+//! bootloader.
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use app_lib::recovery::{self, TftpError, TftpOptions, DEFAULT_BLOCK_SIZE, MAX_BLOCKS};
+use tokio::net::UdpSocket;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Options {
+    /// Answers `blksize` with an OACK
+    Negotiate,
+    /// Ignores options and ACKs block 0, like a bare RFC 1350 server
+    Ignore,
+    /// Refuses options with error 8
+    Refuse,
+}
+
+#[derive(Default)]
+struct Received {
+    filename: String,
+    data: Vec<u8>,
+    /// Block size the stand-in settled on
+    block_size: usize,
+    requests: u32,
+}
+
+/// Recovery-mode TFTP server: accepts one write per request and records
+/// what it got. Stays silent for the first DATA packet of `drop_block` to
+/// force a retransmit.
+struct RecoveryDevice {
+    addr: SocketAddr,
+    received: Arc<Mutex<Received>>,
+}
+
+impl RecoveryDevice {
+    async fn start(options: Options, drop_block: Option<u16>) -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(Received::default()));
+        let state = received.clone();
+
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 2048];
+            loop {
+                let (len, client) = socket.recv_from(&mut buf).await.unwrap();
+                let packet = buf[..len].to_vec();
+                let opcode = u16::from_be_bytes([packet[0], packet[1]]);
+                if opcode == 1 {
+                    // Probe: nothing to read here
+                    let mut error = vec![0, 5, 0, 1];
+                    error.extend_from_slice(b"File not found\0");
+                    socket.send_to(&error, client).await.unwrap();
+                    continue;
+                }
+                assert_eq!(opcode, 2, "expected a write request");
+
+                let fields: Vec<String> = packet[2..]
+                    .split(|&b| b == 0)
+                    .map(|f| String::from_utf8_lossy(f).to_string())
+                    .collect();
+                assert_eq!(fields[1], "octet");
+                let requested = fields
+                    .iter()
+                    .position(|f| f == "blksize")
+                    .map(|i| fields[i + 1].parse::<usize>().unwrap());
+                {
+                    let mut state = state.lock().unwrap();
+                    state.requests += 1;
+                    state.filename = fields[0].clone();
+                }
+
+                if requested.is_some() && options == Options::Refuse {
+                    let mut error = vec![0, 5, 0, 8];
+                    error.extend_from_slice(b"Options not supported\0");
+                    socket.send_to(&error, client).await.unwrap();
+                    continue;
+                }
+
+                // Transfers run from a fresh port, as real servers do
+                let transfer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+                let block_size = match (requested, options) {
+                    (Some(size), Options::Negotiate) => {
+                        let mut oack = vec![0, 6];
+                        oack.extend_from_slice(format!("blksize\0{}\0", size).as_bytes());
+                        transfer.send_to(&oack, client).await.unwrap();
+                        size
+                    }
+                    _ => {
+                        transfer.send_to(&[0, 4, 0, 0], client).await.unwrap();
+                        DEFAULT_BLOCK_SIZE as usize
+                    }
+                };
+                state.lock().unwrap().block_size = block_size;
+
+                let mut expected: u16 = 1;
+                let mut dropped = false;
+                let mut data_buf = vec![0u8; 4 + block_size];
+                loop {
+                    let (len, _) = transfer.recv_from(&mut data_buf).await.unwrap();
+                    let block = u16::from_be_bytes([data_buf[2], data_buf[3]]);
+                    if Some(block) == drop_block && !dropped {
+                        dropped = true;
+                        continue;
+                    }
+                    if block == expected {
+                        state
+                            .lock()
+                            .unwrap()
+                            .data
+                            .extend_from_slice(&data_buf[4..len]);
+                        expected = expected.wrapping_add(1);
+                    }
+                    let ack = [0, 4, data_buf[2], data_buf[3]];
+                    transfer.send_to(&ack, client).await.unwrap();
+                    if len - 4 < block_size {
+                        break;
+                    }
+                }
+            }
+        });
+
+        RecoveryDevice { addr, received }
+    }
+}
+
+fn image(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 % 256) as u8).collect()
+}
+
+fn fast() -> TftpOptions {
+    TftpOptions {
+        timeout: Duration::from_millis(200),
+        ..TftpOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn pushes_image_with_negotiated_block_size() {
+    let device = RecoveryDevice::start(Options::Negotiate, None).await;
+    let data = image(100_000);
+    let mut reported = Vec::new();
+
+    let stats = recovery::put(
+        device.addr,
+        "firmware.bin",
+        &data,
+        &fast(),
+        |sent, total| reported.push((sent, total)),
+    )
+    .await
+    .unwrap();
+
+    let received = device.received.lock().unwrap();
+    assert_eq!(received.filename, "firmware.bin");
+    assert_eq!(received.data, data);
+    assert_eq!(received.block_size, 1468);
+    assert_eq!(stats.block_size, 1468);
+    assert_eq!(stats.bytes, 100_000);
+    assert_eq!(stats.blocks, 100_000u64.div_ceil(1468));
+    assert_eq!(stats.retransmits, 0);
+    assert_eq!(reported.last(), Some(&(100_000, 100_000)));
+}
+
+#[tokio::test]
+async fn falls_back_to_512_byte_blocks() {
+    let ignoring = RecoveryDevice::start(Options::Ignore, None).await;
+    let data = image(5_000);
+    let stats = recovery::put(ignoring.addr, "fw.bin", &data, &fast(), |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(stats.block_size, DEFAULT_BLOCK_SIZE);
+    assert_eq!(ignoring.received.lock().unwrap().data, data);
+
+    let refusing = RecoveryDevice::start(Options::Refuse, None).await;
+    let stats = recovery::put(refusing.addr, "fw.bin", &data, &fast(), |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(stats.block_size, DEFAULT_BLOCK_SIZE);
+    let received = refusing.received.lock().unwrap();
+    assert_eq!(received.requests, 2);
+    assert_eq!(received.data, data);
+}
+
+#[tokio::test]
+async fn exact_multiple_ends_with_an_empty_block() {
+    let device = RecoveryDevice::start(Options::Ignore, None).await;
+    let data = image(512 * 4);
+    let stats = recovery::put(device.addr, "fw.bin", &data, &fast(), |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(stats.blocks, 5);
+    assert_eq!(device.received.lock().unwrap().data, data);
+}
+
+#[tokio::test]
+async fn resends_a_lost_block() {
+    let device = RecoveryDevice::start(Options::Negotiate, Some(3)).await;
+    let data = image(20_000);
+    let stats = recovery::put(device.addr, "fw.bin", &data, &fast(), |_, _| {})
+        .await
+        .unwrap();
+    assert_eq!(stats.retransmits, 1);
+    assert_eq!(device.received.lock().unwrap().data, data);
+}
+
+#[tokio::test]
+async fn gives_up_when_nothing_answers() {
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let options = TftpOptions {
+        timeout: Duration::from_millis(50),
+        retries: 2,
+        ..TftpOptions::default()
+    };
+    let result = recovery::put(
+        silent.local_addr().unwrap(),
+        "fw.bin",
+        b"x",
+        &options,
+        |_, _| {},
+    )
+    .await;
+    assert!(matches!(result, Err(TftpError::Timeout(_))));
+}
+
+#[tokio::test]
+async fn refuses_images_with_more_blocks_than_tftp_can_number() {
+    // Exactly 65535 full blocks need a 65536th, empty one
+    let data = image(DEFAULT_BLOCK_SIZE as usize * MAX_BLOCKS as usize);
+
+    // Too big even at the requested size: the device is never contacted
+    let device = RecoveryDevice::start(Options::Negotiate, None).await;
+    let small = TftpOptions {
+        block_size: DEFAULT_BLOCK_SIZE,
+        ..fast()
+    };
+    let result = recovery::put(device.addr, "fw.bin", &data, &small, |_, _| {}).await;
+    assert!(matches!(result, Err(TftpError::TooLarge(_))));
+    assert_eq!(device.received.lock().unwrap().requests, 0);
+
+    // Fits at the requested size, but the device only does 512
+    let ignoring = RecoveryDevice::start(Options::Ignore, None).await;
+    let result = recovery::put(ignoring.addr, "fw.bin", &data, &fast(), |_, _| {}).await;
+    assert!(matches!(result, Err(TftpError::TooLarge(_))));
+    assert!(ignoring.received.lock().unwrap().data.is_empty());
+}
+
+#[tokio::test]
+async fn probe_detects_a_tftp_server() {
+    let device = RecoveryDevice::start(Options::Negotiate, None).await;
+    assert!(recovery::probe(device.addr, Duration::from_millis(200)).await);
+
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    assert!(!recovery::probe(silent.local_addr().unwrap(), Duration::from_millis(50)).await);
+}
+
+#[test]
+fn recovery_needs_the_same_subnet() {
+    let device = recovery::DEFAULT_RECOVERY_IP.into();
+    assert!(recovery::same_subnet(
+        "192.168.1.25".parse().unwrap(),
+        device
+    ));
+    assert!(!recovery::same_subnet("10.0.0.5".parse().unwrap(), device));
+}
@@ -0,0 +1,141 @@
+//! Undelivered adoption reports are kept on disk and retried in order.
+use std::time::Duration;
+
+use app_lib::api::{AdoptionReport, ApiClient, RetryPolicy};
+use app_lib::mock_api::{MockApi, MockResponse};
+use app_lib::reporting::{self, AdoptionContext, ReportQueue, MAX_QUEUED_REPORTS};
+use app_lib::settings::NetworkSettings;
+use serde_json::json;
+
+const REPORT_PATH: &str = "/api/adoption-report";
+
+fn client_for(mock: &MockApi) -> ApiClient {
+    ApiClient::new(&mock.base_url(), &NetworkSettings::default())
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+        })
+}
+
+fn report(n: usize) -> AdoptionReport {
+    let mut report = AdoptionContext {
+        setup_code: "VS-TEST".to_string(),
+        site_id: "mock-site".to_string(),
+        mac: "fc:ec:da:11:22:33".to_string(),
+        model: "U7LT".to_string(),
+        firmware: "6.6.55".to_string(),
+    }
+    .report(Some("auth_failed"));
+    report.id = format!("report-{}", n);
+    report
+}
+
+fn ids(queue: &ReportQueue) -> Vec<String> {
+    queue.pending().into_iter().map(|r| r.id).collect()
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir()
+        .join(format!("vs-reports-{}-{}", name, std::process::id()))
+        .join(reporting::QUEUE_FILE_NAME)
+}
+
+#[test]
+fn oldest_reports_are_dropped_beyond_the_cap() {
+    let path = temp_path("cap");
+    let queue = ReportQueue::load(path.clone());
+    for n in 0..MAX_QUEUED_REPORTS + 3 {
+        queue.push(report(n));
+    }
+
+    let pending = ids(&queue);
+    assert_eq!(pending.len(), MAX_QUEUED_REPORTS);
+    assert_eq!(pending[0], "report-3");
+    assert_eq!(
+        pending.last().unwrap(),
+        &format!("report-{}", MAX_QUEUED_REPORTS + 2)
+    );
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn queued_reports_survive_a_restart() {
+    let path = temp_path("reload");
+    let queue = ReportQueue::load(path.clone());
+    queue.push(report(1));
+    queue.push(report(2));
+    drop(queue);
+
+    assert_eq!(
+        ids(&ReportQueue::load(path.clone())),
+        ["report-1", "report-2"]
+    );
+
+    // A corrupt file starts an empty queue rather than failing
+    std::fs::write(&path, "[{").unwrap();
+    assert!(ReportQueue::load(path.clone()).pending().is_empty());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn flush_drops_rejected_reports_and_stops_at_outages() {
+    let mock = MockApi::start().await.unwrap();
+    mock.enqueue(
+        REPORT_PATH,
+        MockResponse::json(422, json!({ "error": "Unknown site" })),
+    );
+    mock.enqueue(REPORT_PATH, MockResponse::json(200, json!({})));
+    mock.enqueue(
+        REPORT_PATH,
+        MockResponse::json(503, json!({ "error": "Cold start" })),
+    );
+    mock.enqueue(REPORT_PATH, MockResponse::json(200, json!({})));
+
+    let path = temp_path("flush");
+    let queue = ReportQueue::load(path.clone());
+    for n in 1..=4 {
+        queue.push(report(n));
+    }
+
+    // report-1 is rejected for good and dropped; report-2 still goes out
+    let client = client_for(&mock);
+    assert_eq!(queue.flush(&client).await, 1);
+    assert_eq!(ids(&queue), ["report-3", "report-4"]);
+    // Stopped at the outage instead of trying report-4
+    assert_eq!(mock.requests().len(), 3);
+    assert_eq!(
+        ids(&ReportQueue::load(path.clone())),
+        ["report-3", "report-4"]
+    );
+
+    assert_eq!(queue.flush(&client).await, 2);
+    assert!(queue.pending().is_empty());
+    assert!(ReportQueue::load(path.clone()).pending().is_empty());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn submit_queues_only_failed_deliveries() {
+    let mock = MockApi::start().await.unwrap();
+    mock.enqueue(REPORT_PATH, MockResponse::json(200, json!({})));
+    mock.enqueue(
+        REPORT_PATH,
+        MockResponse::json(500, json!({ "error": "Down" })),
+    );
+    mock.enqueue(
+        REPORT_PATH,
+        MockResponse::json(400, json!({ "error": "Bad report" })),
+    );
+
+    let path = temp_path("submit");
+    let queue = ReportQueue::load(path.clone());
+    let client = client_for(&mock);
+    reporting::submit(&client, &queue, report(1)).await;
+    reporting::submit(&client, &queue, report(2)).await;
+    reporting::submit(&client, &queue, report(3)).await;
+
+    assert_eq!(ids(&queue), ["report-2"]);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
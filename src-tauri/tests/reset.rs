@@ -0,0 +1,39 @@
+//! Factory reset command generation and output parsing.
+use app_lib::reset::{parse_output, reset_command, ResetMethod};
+
+#[test]
+fn command_backgrounds_the_reset() {
+    let command = reset_command();
+    assert!(command.contains("syswrapper.sh restore-default"));
+    assert!(command.contains("set-default"));
+    // The session must end before the device reboots
+    assert!(command.contains("sleep 2"));
+    assert!(command.contains("&"));
+}
+
+#[test]
+fn command_is_valid_shell() {
+    let status = std::process::Command::new("sh")
+        .arg("-n")
+        .arg("-c")
+        .arg(reset_command())
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn reports_which_tool_was_used() {
+    assert_eq!(
+        parse_output("VS_RESET=restore-default\n"),
+        Ok(ResetMethod::RestoreDefault)
+    );
+    assert_eq!(
+        parse_output("Welcome\r\nVS_RESET=set-default\r\n"),
+        Ok(ResetMethod::SetDefault)
+    );
+    assert!(parse_output("VS_RESET=none")
+        .unwrap_err()
+        .contains("no factory reset command"));
+    assert!(parse_output("").is_err());
+}
@@ -0,0 +1,71 @@
+//! API profile selection and the settings file.
+use app_lib::api::DEFAULT_API_BASE;
+use app_lib::settings::{self, Settings};
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir()
+        .join(format!("vs-settings-{}-{}", name, std::process::id()))
+        .join(settings::SETTINGS_FILE_NAME)
+}
+
+#[test]
+fn defaults_point_at_production() {
+    let settings = Settings::default();
+    assert_eq!(settings.active_profile, "production");
+    assert_eq!(settings.active().unwrap().api_base, DEFAULT_API_BASE);
+    let names: Vec<_> = settings.profiles.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["production", "staging", "local"]);
+}
+
+#[test]
+fn unknown_profiles_are_rejected_and_stale_names_fall_back() {
+    let mut settings = Settings::default();
+    assert!(settings.set_active("qa").is_err());
+    assert_eq!(settings.active_profile, "production");
+
+    settings.set_active("staging").unwrap();
+    assert_eq!(settings.active().unwrap().name, "staging");
+
+    // A profile removed from the file since it was selected
+    settings.active_profile = "removed".to_string();
+    assert_eq!(settings.active().unwrap().name, "production");
+}
+
+#[test]
+fn settings_round_trip_through_disk() {
+    let path = temp_path("round-trip");
+    let mut saved = Settings::default();
+    saved.set_active("local").unwrap();
+    saved.network.proxy_url = Some("http://proxy.example:3128".to_string());
+    settings::save(&path, &saved).unwrap();
+
+    let loaded = settings::load(&path);
+    assert_eq!(loaded.active_profile, "local");
+    assert_eq!(loaded.active().unwrap().api_base, "http://localhost:3000");
+    assert_eq!(
+        loaded.network.proxy_url.as_deref(),
+        Some("http://proxy.example:3128")
+    );
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn missing_or_corrupt_files_load_defaults() {
+    let path = temp_path("corrupt");
+    assert_eq!(settings::load(&path).active_profile, "production");
+
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "{ not json").unwrap();
+    assert_eq!(settings::load(&path).active_profile, "production");
+
+    // Files written before network settings existed still load
+    std::fs::write(
+        &path,
+        r#"{"activeProfile":"staging","profiles":[{"name":"staging","apiBase":"https://s.example"}]}"#,
+    )
+    .unwrap();
+    let loaded = settings::load(&path);
+    assert_eq!(loaded.active().unwrap().api_base, "https://s.example");
+    assert_eq!(loaded.network.connect_timeout_secs, 10);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,66 @@
+//! Setup code normalisation and local format checks.
+use app_lib::api::ApiError;
+use app_lib::setup_code::{luhn_mod36_valid, normalize};
+
+#[test]
+fn canonical_code_is_unchanged() {
+    assert_eq!(normalize("VS-7K2M").unwrap(), "VS-7K2M");
+}
+
+#[test]
+fn case_whitespace_and_dashes_are_normalised() {
+    assert_eq!(normalize("  vs-7k2m ").unwrap(), "VS-7K2M");
+    assert_eq!(normalize("VS 7K2M").unwrap(), "VS-7K2M");
+    assert_eq!(normalize("VS\u{2013}7K2M").unwrap(), "VS-7K2M");
+    assert_eq!(normalize("vs7k2m").unwrap(), "VS-7K2M");
+    assert_eq!(normalize("7K-2M").unwrap(), "VS-7K2M");
+}
+
+#[test]
+fn missing_prefix_is_added() {
+    assert_eq!(normalize("7k2m").unwrap(), "VS-7K2M");
+    // A body that happens to start with "VS" is still just a body
+    assert_eq!(normalize("vsab").unwrap(), "VS-VSAB");
+}
+
+#[test]
+fn malformed_codes_are_rejected() {
+    for raw in [
+        "",
+        "VS-",
+        "VS-7K2",
+        "VS-7K2MX9",
+        "VS-7K&M",
+        "VS-7K#M",
+        "VS_7K2M",
+        "hello world",
+    ] {
+        assert!(
+            matches!(normalize(raw), Err(ApiError::InvalidCode(_))),
+            "{:?} should be rejected",
+            raw
+        );
+    }
+}
+
+#[test]
+fn check_character_catches_typos() {
+    assert!(luhn_mod36_valid("7K2MD"));
+    assert!(luhn_mod36_valid("ZZZZ4"));
+    assert!(!luhn_mod36_valid("7K2MQ"));
+    // Every single-character substitution and adjacent swap is caught
+    let alphabet = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    for i in 0..5 {
+        for c in alphabet.chars() {
+            let mut typo: Vec<char> = "7K2MD".chars().collect();
+            if typo[i] == c {
+                continue;
+            }
+            typo[i] = c;
+            let typo: String = typo.into_iter().collect();
+            assert!(!luhn_mod36_valid(&typo), "{} should be rejected", typo);
+        }
+    }
+    assert!(!luhn_mod36_valid("K72MD"));
+    assert!(!luhn_mod36_valid("7K-2M"));
+}
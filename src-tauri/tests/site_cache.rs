@@ -0,0 +1,102 @@
+//! Validated setup codes kept on disk for offline restarts.
+use app_lib::api::SetupCodeResponse;
+use app_lib::site_cache::{self, SiteCache};
+use chrono::{Duration, Utc};
+
+const PRODUCTION: &str = "https://ubiquitywizard.onrender.com";
+const STAGING: &str = "https://ubiquitywizard-staging.onrender.com";
+
+fn site(name: &str, expires_in: Option<Duration>) -> SetupCodeResponse {
+    SetupCodeResponse {
+        inform_url: "http://10.0.0.2:8080/inform".to_string(),
+        site_id: name.to_lowercase(),
+        site_name: name.to_string(),
+        expires_at: expires_in.map(|d| Utc::now() + d),
+    }
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir()
+        .join(format!("vs-site-cache-{}-{}", name, std::process::id()))
+        .join(site_cache::CACHE_FILE_NAME)
+}
+
+#[test]
+fn entries_are_scoped_to_the_api_they_came_from() {
+    let path = temp_path("scope");
+    let cache = SiteCache::load(path.clone());
+    cache.insert(PRODUCTION, "VS-7K2M", &site("Harbour", None));
+
+    assert_eq!(
+        cache.get(PRODUCTION, "VS-7K2M").unwrap().site.site_name,
+        "Harbour"
+    );
+    assert!(cache.get(STAGING, "VS-7K2M").is_none());
+    assert!(cache.get(PRODUCTION, "VS-AAAA").is_none());
+
+    // Revalidating replaces the old entry
+    cache.insert(PRODUCTION, "VS-7K2M", &site("Harbour Hotel", None));
+    let listed = cache.list();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].site.site_name, "Harbour Hotel");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn expiry_comes_from_the_api_or_defaults_to_twelve_hours() {
+    let path = temp_path("expiry");
+    let cache = SiteCache::load(path.clone());
+
+    let entry = cache.insert(PRODUCTION, "VS-7K2M", &site("Harbour", None));
+    let ttl = entry.expires_at - entry.validated_at;
+    assert_eq!(ttl, Duration::hours(12));
+
+    let entry = cache.insert(
+        PRODUCTION,
+        "VS-AAAA",
+        &site("Pier", Some(Duration::days(3))),
+    );
+    assert!(entry.expires_at - entry.validated_at > Duration::days(2));
+
+    cache.insert(
+        PRODUCTION,
+        "VS-BBBB",
+        &site("Old", Some(Duration::seconds(-1))),
+    );
+    assert!(cache.get(PRODUCTION, "VS-BBBB").is_none());
+    assert!(cache.list().iter().all(|e| e.code != "VS-BBBB"));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn entries_survive_a_restart_newest_first() {
+    let path = temp_path("reload");
+    let cache = SiteCache::load(path.clone());
+    cache.insert(PRODUCTION, "VS-7K2M", &site("Harbour", None));
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    cache.insert(STAGING, "VS-AAAA", &site("Pier", None));
+    drop(cache);
+
+    let reloaded = SiteCache::load(path.clone());
+    let codes: Vec<_> = reloaded.list().into_iter().map(|e| e.code).collect();
+    assert_eq!(codes, ["VS-AAAA", "VS-7K2M"]);
+
+    std::fs::write(&path, "not json").unwrap();
+    assert!(SiteCache::load(path.clone()).list().is_empty());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn clear_forgets_one_code_or_all() {
+    let path = temp_path("clear");
+    let cache = SiteCache::load(path.clone());
+    cache.insert(PRODUCTION, "VS-7K2M", &site("Harbour", None));
+    cache.insert(PRODUCTION, "VS-AAAA", &site("Pier", None));
+    cache.insert(STAGING, "VS-AAAA", &site("Pier", None));
+
+    assert_eq!(cache.clear(Some("VS-AAAA")), 2);
+    assert_eq!(cache.clear(Some("VS-AAAA")), 0);
+    assert_eq!(cache.clear(None), 1);
+    assert!(SiteCache::load(path.clone()).list().is_empty());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -1,10 +1,16 @@
 import { useState, useEffect, useCallback } from "react";
 import CodeEntry from "./components/CodeEntry";
 import Scanner from "./components/Scanner";
-import DeviceCard from "./components/DeviceCard";
+import DeviceCard, { AdoptOptions } from "./components/DeviceCard";
+import ControllerLogin, { ControllerSession } from "./components/ControllerLogin";
+import InformRelay from "./components/InformRelay";
+import RecoveryPanel from "./components/RecoveryPanel";
+import ProfileBanner from "./components/ProfileBanner";
+import InventoryChecklist, { Reconciliation } from "./components/InventoryChecklist";
 import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { onOpenUrl } from "@tauri-apps/plugin-deep-link";
+import { errorCode, errorMessage } from "./errors";
 
 type AppState = "code-entry" | "scanning" | "results" | "complete";
 
@@ -22,10 +28,13 @@ interface Device {
   firmware: string;
   hostname: string;
   isManaged: boolean;
+  isDefault: boolean;
+  isLocating: boolean;
 }
 
 interface ScanResult {
   devices: Device[];
+  inventory: Reconciliation | null;
 }
 
 interface AdoptResult {
@@ -36,10 +45,17 @@ interface AdoptResult {
 export default function App() {
   const [state, setState] = useState<AppState>("code-entry");
   const [siteInfo, setSiteInfo] = useState<SiteInfo | null>(null);
+  const [setupCode, setSetupCode] = useState<string | null>(null);
   const [devices, setDevices] = useState<Device[]>([]);
+  const [inventory, setInventory] = useState<Reconciliation | null>(null);
+  const [inventorySiteId, setInventorySiteId] = useState<string | null>(null);
   const [error, setError] = useState<string | null>(null);
   const [appVersion, setAppVersion] = useState("");
   const [deepLinkCode, setDeepLinkCode] = useState<string | null>(null);
+  const [pendingBundle, setPendingBundle] = useState<string | null>(null);
+  const [requestedProfile, setRequestedProfile] = useState<string | null>(null);
+  const [controllerSession, setControllerSession] = useState<ControllerSession | null>(null);
+  const [relaying, setRelaying] = useState(false);
 
   useEffect(() => {
     invoke<string>("get_app_version").then(setAppVersion).catch(() => {});
@@ -57,23 +73,44 @@ export default function App() {
     }
   }, []);
 
+  /** API profile a deep link asks for, e.g. vivaspot://setup?code=VS-XXXX&profile=staging */
+  const parseDeepLinkProfile = useCallback((url: string): string | null => {
+    try {
+      const parsed = new URL(url.replace(/^vivaspot:\/\//, "https://dummy/"));
+      return parsed.searchParams.get("profile") || null;
+    } catch {
+      return null;
+    }
+  }, []);
+
+  const clearRequestedProfile = useCallback(() => setRequestedProfile(null), []);
+
+  /** Offline setup bundles arrive as vivaspot://bundle?payload=...&signature=... */
+  const isBundleLink = (url: string) => /^vivaspot:\/\/bundle\b/i.test(url);
+
   // Check for initial deep link URL (app launched via vivaspot:// URL).
   // This is stored in Rust state because the URL arrives before React mounts.
   useEffect(() => {
     invoke<string | null>("get_initial_deep_link").then((url) => {
-      if (url) {
+      if (url) setRequestedProfile(parseDeepLinkProfile(url));
+      if (url && isBundleLink(url)) {
+        setPendingBundle(url);
+      } else if (url) {
         const code = parseDeepLinkCode(url);
         if (code) {
           setDeepLinkCode(code);
         }
       }
     }).catch(() => {});
-  }, [parseDeepLinkCode]);
+  }, [parseDeepLinkCode, parseDeepLinkProfile]);
 
   // Also listen for runtime deep link events (app already running)
   useEffect(() => {
     const unlistenPromise = onOpenUrl((urls: string[]) => {
-      if (urls.length > 0) {
+      if (urls.length > 0) setRequestedProfile(parseDeepLinkProfile(urls[0]));
+      if (urls.length > 0 && isBundleLink(urls[0])) {
+        setPendingBundle(urls[0]);
+      } else if (urls.length > 0) {
         const code = parseDeepLinkCode(urls[0]);
         if (code) {
           setDeepLinkCode(code);
@@ -84,26 +121,44 @@ export default function App() {
     return () => {
       unlistenPromise.then((unlisten) => unlisten());
     };
-  }, [parseDeepLinkCode]);
+  }, [parseDeepLinkCode, parseDeepLinkProfile]);
 
   const handleCodeSubmit = async (code: string) => {
     setError(null);
     try {
       const result = await invoke<SiteInfo>("validate_code", { code });
       setSiteInfo(result);
+      setSetupCode(code);
       setState("scanning");
       // Small delay before first scan when app just launched (network may not be ready)
       await new Promise((r) => setTimeout(r, 1500));
       handleScan();
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
     }
   };
 
+  // Import a signed offline bundle in place of an online code check
+  useEffect(() => {
+    if (!pendingBundle) return;
+    setPendingBundle(null);
+    setError(null);
+    invoke<SiteInfo>("import_setup_bundle", { source: pendingBundle })
+      .then(async (result) => {
+        setSiteInfo(result);
+        setSetupCode(null);
+        setState("scanning");
+        await new Promise((r) => setTimeout(r, 1500));
+        handleScan();
+      })
+      .catch((err) => setError(errorMessage(err)));
+  }, [pendingBundle]);
+
   const doScan = async (retryCount: number): Promise<void> => {
     try {
       const result = await invoke<ScanResult>("scan_devices");
       setDevices(result.devices);
+      setInventory(result.inventory);
       setState("results");
     } catch (err) {
       // Auto-retry once on network errors (common on cold launch)
@@ -111,47 +166,81 @@ export default function App() {
         await new Promise((r) => setTimeout(r, 2000));
         return doScan(retryCount + 1);
       }
-      setError(String(err));
+      setError(errorMessage(err));
       setState("results");
     }
   };
 
+  // Fetch the site's planned devices once per site, after its first scan
+  useEffect(() => {
+    if (state !== "results" || !siteInfo || inventorySiteId === siteInfo.siteId) return;
+    setInventorySiteId(siteInfo.siteId);
+    invoke<Reconciliation>("load_site_inventory", { siteId: siteInfo.siteId })
+      .then(setInventory)
+      // Sites without a plan, or offline bundles, just skip the checklist
+      .catch(() => setInventory(null));
+  }, [state, siteInfo, inventorySiteId]);
+
   const handleScan = async () => {
     setState("scanning");
     setError(null);
     await doScan(0);
   };
 
-  const handleAdopt = async (ip: string) => {
-    if (!siteInfo) return;
-    setError(null);
-    try {
-      await invoke<AdoptResult>("adopt_device", {
-        ip,
-        informUrl: siteInfo.informUrl,
-        customPassword: null,
-      });
-      setState("complete");
-    } catch (err) {
-      setError(String(err));
-    }
-  };
+  /** Site and device details sent with each adoption for back-office reporting */
+  const adoptionContext = (device: Device) =>
+    siteInfo && setupCode
+      ? {
+          setupCode,
+          siteId: siteInfo.siteId,
+          mac: device.mac,
+          model: device.model,
+          firmware: device.firmware,
+        }
+      : null;
 
-  const handleAdoptWithPassword = async (ip: string, password: string) => {
+  /** Errors the device card handles itself (password prompt, adopt anyway) */
+  const cardErrors = ["auth_failed", "preflight_failed"];
+
+  const adopt = async (device: Device, customPassword: string | null, options: AdoptOptions) => {
     if (!siteInfo) return;
     setError(null);
     try {
+      let informUrl = siteInfo.informUrl;
+      // Point the AP at this computer, which forwards to the controller
+      if (options.relay) {
+        const relay = await invoke<{ informUrl: string }>("start_inform_listener", {
+          deviceIp: device.ip,
+          relayTo: siteInfo.informUrl,
+        });
+        informUrl = relay.informUrl;
+        setRelaying(true);
+      }
       await invoke<AdoptResult>("adopt_device", {
-        ip,
-        informUrl: siteInfo.informUrl,
-        customPassword: password,
+        ip: device.ip,
+        informUrl,
+        customPassword,
+        context: adoptionContext(device),
+        skipPreflight: options.skipPreflight ?? false,
+        twoPhase: options.twoPhase ?? false,
       });
+      // With a self-hosted controller, finish the adoption there too
+      if (controllerSession) {
+        await invoke("controller_adopt_device", { mac: device.mac });
+      }
       setState("complete");
     } catch (err) {
-      setError(String(err));
+      if (cardErrors.includes(errorCode(err) ?? "")) throw err;
+      setError(errorMessage(err));
     }
   };
 
+  const handleAdopt = (device: Device, options: AdoptOptions) =>
+    adopt(device, null, options);
+
+  const handleAdoptWithPassword = (device: Device, password: string, options: AdoptOptions) =>
+    adopt(device, password, options);
+
   return (
     <div className="min-h-screen bg-gray-50 flex flex-col">
       {/* Header */}
@@ -178,6 +267,8 @@ export default function App() {
         </div>
       </header>
 
+      <ProfileBanner requested={requestedProfile} onResolved={clearRequestedProfile} />
+
       {/* Main content */}
       <main className="flex-1 flex items-center justify-center p-6">
         <div className="w-full max-w-lg">
@@ -186,6 +277,7 @@ export default function App() {
               onSubmit={handleCodeSubmit}
               error={error}
               initialCode={deepLinkCode}
+              onBundle={setPendingBundle}
             />
           )}
 
@@ -213,6 +305,12 @@ export default function App() {
                 </div>
               )}
 
+              {inventory && <InventoryChecklist inventory={inventory} />}
+
+              <ControllerLogin session={controllerSession} onChange={setControllerSession} />
+
+              {relaying && <InformRelay onStopped={() => setRelaying(false)} />}
+
               {/* Device list */}
               {devices.length > 0 ? (
                 <div className="space-y-3">
@@ -220,10 +318,12 @@ export default function App() {
                     <DeviceCard
                       key={device.mac}
                       device={device}
-                      onAdopt={() => handleAdopt(device.ip)}
-                      onAdoptWithPassword={(password) =>
-                        handleAdoptWithPassword(device.ip, password)
+                      informUrl={siteInfo?.informUrl ?? null}
+                      onAdopt={(options) => handleAdopt(device, options)}
+                      onAdoptWithPassword={(password, options) =>
+                        handleAdoptWithPassword(device, password, options)
                       }
+                      onReset={handleScan}
                     />
                   ))}
                 </div>
@@ -231,6 +331,8 @@ export default function App() {
                 <NoDevicesFound onRescan={handleScan} />
               )}
 
+              <RecoveryPanel onRecovered={handleScan} />
+
               {/* Rescan button */}
               {devices.length > 0 && (
                 <button
@@ -243,7 +345,12 @@ export default function App() {
             </div>
           )}
 
-          {state === "complete" && <SuccessScreen siteName={siteInfo?.siteName} />}
+          {state === "complete" && (
+            <div className="space-y-4">
+              <SuccessScreen siteName={siteInfo?.siteName} />
+              {relaying && <InformRelay onStopped={() => setRelaying(false)} />}
+            </div>
+          )}
         </div>
       </main>
     </div>
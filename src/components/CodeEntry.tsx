@@ -1,16 +1,47 @@
 import { useState, useEffect, useRef } from "react";
-import { KeyRound } from "lucide-react";
+import { KeyRound, FileKey } from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
+
+interface CachedSite {
+  code: string;
+  site: { siteName: string };
+  expiresAt: string;
+}
 
 interface CodeEntryProps {
   onSubmit: (code: string) => void | Promise<void>;
   error: string | null;
   initialCode?: string | null;
+  /** Contents of a `.vivaspot` offline setup bundle the user picked */
+  onBundle: (contents: string) => void;
 }
 
-export default function CodeEntry({ onSubmit, error, initialCode }: CodeEntryProps) {
+export default function CodeEntry({ onSubmit, error, initialCode, onBundle }: CodeEntryProps) {
   const [code, setCode] = useState("");
   const [loading, setLoading] = useState(false);
   const autoSubmittedRef = useRef(false);
+  const bundleInputRef = useRef<HTMLInputElement>(null);
+  const [recentSites, setRecentSites] = useState<CachedSite[]>([]);
+
+  // Codes validated earlier can be resumed without retyping (or internet)
+  useEffect(() => {
+    invoke<CachedSite[]>("list_cached_sites").then(setRecentSites).catch(() => {});
+  }, []);
+
+  const handleForgetSites = async () => {
+    await invoke("clear_cached_sites").catch(() => {});
+    setRecentSites([]);
+  };
+
+  const handleResume = async (cachedCode: string) => {
+    setCode(cachedCode);
+    setLoading(true);
+    try {
+      await onSubmit(cachedCode);
+    } finally {
+      setLoading(false);
+    }
+  };
 
   // Auto-fill and auto-submit when launched via deep link
   useEffect(() => {
@@ -39,6 +70,13 @@ export default function CodeEntry({ onSubmit, error, initialCode }: CodeEntryPro
     }
   };
 
+  // Sites with no internet on install day get a signed bundle file instead
+  const handleBundleFile = async (e: React.ChangeEvent<HTMLInputElement>) => {
+    const file = e.target.files?.[0];
+    e.target.value = "";
+    if (file) onBundle(await file.text());
+  };
+
   const handleChange = (e: React.ChangeEvent<HTMLInputElement>) => {
     // Auto-format: uppercase, allow VS- prefix
     let value = e.target.value.toUpperCase();
@@ -127,6 +165,53 @@ export default function CodeEntry({ onSubmit, error, initialCode }: CodeEntryPro
           )}
         </button>
       </form>
+
+      {!initialCode && (
+        <div>
+          <input
+            ref={bundleInputRef}
+            type="file"
+            accept=".vivaspot,.json"
+            onChange={handleBundleFile}
+            className="hidden"
+          />
+          <button
+            onClick={() => bundleInputRef.current?.click()}
+            disabled={loading}
+            className="inline-flex items-center gap-1.5 text-xs text-gray-500 hover:text-vivaspot-primary"
+          >
+            <FileKey className="w-3.5 h-3.5" />
+            No internet on site? Open a setup bundle file
+          </button>
+        </div>
+      )}
+
+      {!initialCode && recentSites.length > 0 && (
+        <div className="text-left space-y-2">
+          <div className="flex items-center justify-between">
+            <h3 className="text-xs font-medium text-gray-500 uppercase tracking-wide">
+              Recent sites
+            </h3>
+            <button
+              onClick={handleForgetSites}
+              className="text-xs text-gray-400 hover:text-gray-600"
+            >
+              Forget all
+            </button>
+          </div>
+          {recentSites.map((entry) => (
+            <button
+              key={entry.code}
+              onClick={() => handleResume(entry.code)}
+              disabled={loading}
+              className="w-full flex items-center justify-between py-2 px-3 bg-white border border-gray-200 rounded-lg text-sm hover:border-vivaspot-primary transition-colors"
+            >
+              <span className="font-medium text-vivaspot-dark">{entry.site.siteName}</span>
+              <span className="font-mono text-xs text-gray-500">{entry.code}</span>
+            </button>
+          ))}
+        </div>
+      )}
     </div>
   );
 }
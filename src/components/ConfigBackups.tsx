@@ -0,0 +1,115 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../errors";
+
+interface BackupSummary {
+  id: string;
+  reason: string;
+  createdAt: string;
+}
+
+interface RestoreConfigResult {
+  restored: BackupSummary;
+  previous: BackupSummary;
+}
+
+interface ConfigBackupsProps {
+  ip: string;
+  mac: string;
+  password: string;
+}
+
+const REASON_LABELS: Record<string, string> = {
+  "set-inform": "before adoption",
+  "factory-reset": "before factory reset",
+  upgrade: "before firmware upgrade",
+  restore: "before a restore",
+};
+
+/** Config snapshots taken before changes, with a way to put one back */
+export default function ConfigBackups({ ip, mac, password }: ConfigBackupsProps) {
+  const [backups, setBackups] = useState<BackupSummary[] | null>(null);
+  const [confirming, setConfirming] = useState<string | null>(null);
+  const [restoring, setRestoring] = useState(false);
+  const [message, setMessage] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const handleToggle = async () => {
+    if (backups) {
+      setBackups(null);
+      return;
+    }
+    setError(null);
+    try {
+      setBackups(await invoke<BackupSummary[]>("list_config_backups", { mac }));
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
+  const handleRestore = async (backupId: string) => {
+    setRestoring(true);
+    setError(null);
+    setMessage(null);
+    try {
+      const result = await invoke<RestoreConfigResult>("restore_device_config", {
+        ip,
+        backupId,
+        customPassword: password.trim() || null,
+      });
+      setConfirming(null);
+      setBackups(null);
+      setMessage(
+        `Restored ${result.restored.id}; the access point is rebooting. The replaced config was saved as ${result.previous.id}.`
+      );
+    } catch (err) {
+      setError(errorMessage(err));
+    } finally {
+      setRestoring(false);
+    }
+  };
+
+  return (
+    <div className="mt-2 text-xs">
+      <button onClick={handleToggle} className="text-gray-500 underline">
+        {backups ? "Hide saved configs" : "Saved configs"}
+      </button>
+
+      {backups && backups.length === 0 && (
+        <p className="mt-1 text-gray-400">No configs saved for this access point yet.</p>
+      )}
+      {backups && backups.length > 0 && (
+        <ul className="mt-1 space-y-1">
+          {backups.map((backup) => (
+            <li key={backup.id} className="flex items-center gap-2">
+              <span className="flex-1 text-gray-600">
+                {new Date(backup.createdAt).toLocaleString()}{" "}
+                {REASON_LABELS[backup.reason] ?? backup.reason}
+              </span>
+              {confirming === backup.id ? (
+                <button
+                  onClick={() => handleRestore(backup.id)}
+                  disabled={restoring}
+                  className="text-red-700 underline"
+                >
+                  {restoring ? "Restoring..." : "Restore and reboot?"}
+                </button>
+              ) : (
+                <button
+                  onClick={() => setConfirming(backup.id)}
+                  disabled={restoring}
+                  className="text-vivaspot-primary underline"
+                >
+                  Restore
+                </button>
+              )}
+            </li>
+          ))}
+        </ul>
+      )}
+
+      {message && <p className="mt-1 text-green-700">{message}</p>}
+      {error && <p className="mt-1 text-red-700">{error}</p>}
+    </div>
+  );
+}
@@ -0,0 +1,142 @@
+import { useState } from "react";
+import { Server, ChevronDown, ChevronUp } from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../errors";
+
+export interface ControllerDevice {
+  mac: string;
+  model: string;
+  name: string | null;
+  state: number;
+  adopted: boolean;
+}
+
+export interface ControllerSession {
+  kind: "classic" | "unifiOs";
+  site: string;
+  pending: ControllerDevice[];
+}
+
+interface ControllerLoginProps {
+  session: ControllerSession | null;
+  onChange: (session: ControllerSession | null) => void;
+}
+
+/** Optional sign-in for customers running their own UniFi Network controller */
+export default function ControllerLogin({ session, onChange }: ControllerLoginProps) {
+  const [open, setOpen] = useState(false);
+  const [url, setUrl] = useState("");
+  const [username, setUsername] = useState("");
+  const [password, setPassword] = useState("");
+  const [site, setSite] = useState("default");
+  const [acceptInvalidCerts, setAcceptInvalidCerts] = useState(false);
+  const [loading, setLoading] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const handleLogin = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setLoading(true);
+    setError(null);
+    try {
+      const result = await invoke<ControllerSession>("controller_login", {
+        url: url.trim(),
+        username,
+        password,
+        site,
+        acceptInvalidCerts,
+      });
+      setPassword("");
+      onChange(result);
+    } catch (err) {
+      setError(errorMessage(err));
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const handleLogout = async () => {
+    await invoke("controller_logout").catch(() => {});
+    onChange(null);
+  };
+
+  if (session) {
+    return (
+      <div className="flex items-center justify-between text-xs text-gray-600 bg-white border border-gray-200 rounded-lg px-3 py-2">
+        <span className="flex items-center gap-2">
+          <Server className="w-3 h-3" />
+          Signed in to controller (site {session.site}) &middot; {session.pending.length} pending
+        </span>
+        <button onClick={handleLogout} className="text-gray-400 hover:text-gray-600">
+          Sign out
+        </button>
+      </div>
+    );
+  }
+
+  return (
+    <div className="text-xs">
+      <button
+        onClick={() => setOpen(!open)}
+        className="text-gray-500 hover:text-vivaspot-primary flex items-center gap-1"
+      >
+        Using your own UniFi controller?
+        {open ? <ChevronUp className="w-3 h-3" /> : <ChevronDown className="w-3 h-3" />}
+      </button>
+      {open && (
+        <form onSubmit={handleLogin} className="mt-2 space-y-2">
+          <input
+            value={url}
+            onChange={(e) => setUrl(e.target.value)}
+            placeholder="https://192.168.1.2:8443"
+            className="w-full border border-gray-300 rounded-lg px-3 py-1.5 focus:border-vivaspot-primary focus:outline-none"
+          />
+          <div className="flex gap-2">
+            <input
+              value={username}
+              onChange={(e) => setUsername(e.target.value)}
+              placeholder="Local username"
+              className="flex-1 border border-gray-300 rounded-lg px-3 py-1.5 focus:border-vivaspot-primary focus:outline-none"
+            />
+            <input
+              type="password"
+              value={password}
+              onChange={(e) => setPassword(e.target.value)}
+              placeholder="Password"
+              className="flex-1 border border-gray-300 rounded-lg px-3 py-1.5 focus:border-vivaspot-primary focus:outline-none"
+            />
+          </div>
+          <div className="flex gap-2">
+            <input
+              value={site}
+              onChange={(e) => setSite(e.target.value)}
+              placeholder="Site ID"
+              className="flex-1 border border-gray-300 rounded-lg px-3 py-1.5 focus:border-vivaspot-primary focus:outline-none"
+            />
+            <button
+              type="submit"
+              disabled={!url.trim() || !username || !password || loading}
+              className="px-4 py-1.5 bg-vivaspot-primary text-white rounded-lg hover:bg-vivaspot-primary-dark disabled:bg-gray-200 disabled:text-gray-400 transition-colors"
+            >
+              {loading ? "Signing in..." : "Sign in"}
+            </button>
+          </div>
+          <label className="flex items-center gap-2 text-gray-600">
+            <input
+              type="checkbox"
+              checked={acceptInvalidCerts}
+              onChange={(e) => setAcceptInvalidCerts(e.target.checked)}
+            />
+            Trust a self-signed certificate (only on a network you control)
+          </label>
+          {error && <p className="text-red-700">{error}</p>}
+          {error && !acceptInvalidCerts && /certificate/i.test(error) && (
+            <p className="text-gray-500">
+              Controllers often use a self-signed certificate. If this is your controller, tick
+              the box above and sign in again.
+            </p>
+          )}
+        </form>
+      )}
+    </div>
+  );
+}
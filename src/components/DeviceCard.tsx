@@ -1,5 +1,10 @@
-import { useState } from "react";
-import { Wifi, Lock, ChevronDown, ChevronUp } from "lucide-react";
+import { useState, useEffect } from "react";
+import { Wifi, Lock, ChevronDown, ChevronUp, Lightbulb, FileText, SquareTerminal } from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { errorCode, errorMessage } from "../errors";
+import ConfigBackups from "./ConfigBackups";
+import TerminalPanel from "./TerminalPanel";
 
 interface Device {
   mac: string;
@@ -9,55 +14,364 @@ interface Device {
   firmware: string;
   hostname: string;
   isManaged: boolean;
+  isDefault: boolean;
+  isLocating: boolean;
+}
+
+interface FirmwareProgress {
+  stage: "serving" | "flashing" | "upgraded";
+  detail: string;
+}
+
+const FIRMWARE_STAGE_LABELS: Record<FirmwareProgress["stage"], string> = {
+  serving: "Waiting for the access point to download the firmware",
+  flashing: "Installing firmware and restarting (this takes a few minutes)",
+  upgraded: "Upgraded to",
+};
+
+interface FactoryResetResult {
+  elapsedSecs: number;
+}
+
+type SshOutputEvent = { jobId: string; ip: string } & (
+  | { kind: "stdout" | "stderr"; data: string }
+  | { kind: "exitStatus"; data: number }
+  | { kind: "failed"; data: string }
+);
+
+interface DeviceDiagnosticsResult {
+  path: string;
+  commands: { name: string; exitCode: number | null }[];
+}
+
+interface LocateResult {
+  enabled: boolean;
+  offAt: string | null;
+}
+
+interface PreflightCheck {
+  name: string;
+  status: "pass" | "warn" | "fail" | "skipped";
+  detail: string;
+}
+
+interface PreflightReport {
+  source: "laptop" | "device";
+  checks: PreflightCheck[];
+  passed: boolean;
+}
+
+interface AdoptionPhase {
+  name: string;
+  success: boolean;
+  warning: boolean;
+  detail: string;
+}
+
+interface InformRecord {
+  mac: string;
+  model: string | null;
+  version: string | null;
+  informCount: number;
+  error: string | null;
+}
+
+interface InformListenerStatus {
+  informUrl: string | null;
+  devices: InformRecord[];
+}
+
+/** How long to wait for the AP to inform this computer */
+const INFORM_TEST_TIMEOUT_MS = 60_000;
+
+/** One label per `AdoptionPhase.name` the backend emits, in emission order */
+const PHASE_LABELS: Record<string, string> = {
+  preflight: "Inform URL check from this computer",
+  "backup-config": "Backup of the AP's current config",
+  "set-inform": "Sent inform URL",
+  "wait-for-controller": "Controller accepted the AP",
+  "set-inform-again": "Re-sent inform URL",
+};
+
+export interface AdoptOptions {
+  skipPreflight?: boolean;
+  /** Re-send set-inform once the controller accepts (older firmware) */
+  twoPhase?: boolean;
+  /** Inform via this computer, for APs that can't reach the controller yet */
+  relay?: boolean;
 }
 
 interface DeviceCardProps {
   device: Device;
-  onAdopt: () => void;
-  onAdoptWithPassword: (password: string) => void;
+  informUrl: string | null;
+  onAdopt: (options: AdoptOptions) => Promise<void> | void;
+  onAdoptWithPassword: (password: string, options: AdoptOptions) => Promise<void> | void;
+  /** Called once a factory reset has finished and the device is back */
+  onReset?: () => void;
 }
 
 export default function DeviceCard({
   device,
+  informUrl,
   onAdopt,
   onAdoptWithPassword,
+  onReset,
 }: DeviceCardProps) {
   const [adopting, setAdopting] = useState(false);
   const [showPassword, setShowPassword] = useState(false);
   const [password, setPassword] = useState("");
   const [error, setError] = useState<string | null>(null);
+  const [preflightFailed, setPreflightFailed] = useState(false);
+  const [checking, setChecking] = useState(false);
+  const [preflight, setPreflight] = useState<PreflightReport[] | null>(null);
+  const [twoPhase, setTwoPhase] = useState(false);
+  const [relay, setRelay] = useState(false);
+  const [phases, setPhases] = useState<AdoptionPhase[]>([]);
+  const [testingInform, setTestingInform] = useState(false);
+  const [locating, setLocating] = useState(device.isLocating);
+  const [confirmingReset, setConfirmingReset] = useState(false);
+  const [resetMac, setResetMac] = useState("");
+  const [resetting, setResetting] = useState(false);
+  const [resetPassword, setResetPassword] = useState("");
+  const [showFirmware, setShowFirmware] = useState(false);
+  const [firmwarePath, setFirmwarePath] = useState("");
+  const [upgrading, setUpgrading] = useState(false);
+  const [firmwareProgress, setFirmwareProgress] = useState<FirmwareProgress | null>(null);
+  const [informResult, setInformResult] = useState<string | null>(null);
+  const [collecting, setCollecting] = useState(false);
+  const [showTerminal, setShowTerminal] = useState(false);
+  const [diagnosticsResult, setDiagnosticsResult] = useState<string | null>(null);
+  const [diagnosticsStep, setDiagnosticsStep] = useState<string | null>(null);
 
-  const handleAdopt = async () => {
-    setAdopting(true);
+  // Adoption steps are reported as they finish
+  useEffect(() => {
+    const unlistenPromise = listen<{ ip: string; phase: AdoptionPhase }>(
+      "adoption-phase",
+      (event) => {
+        if (event.payload.ip === device.ip) {
+          setPhases((current) => [...current, event.payload.phase]);
+        }
+      }
+    );
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [device.ip]);
+
+  // The LED switches itself off; follow along so the button stays accurate
+  useEffect(() => {
+    setLocating(device.isLocating);
+  }, [device.isLocating]);
+
+  const handleLocate = async () => {
     setError(null);
     try {
-      await onAdopt();
+      const result = await invoke<LocateResult>("locate_device", {
+        ip: device.ip,
+        enabled: !locating,
+        customPassword: password.trim() || null,
+      });
+      setLocating(result.enabled);
+      if (result.offAt) {
+        const remaining = new Date(result.offAt).getTime() - Date.now();
+        setTimeout(() => setLocating(false), Math.max(remaining, 0));
+      }
     } catch (err) {
-      const errStr = String(err);
-      if (errStr.toLowerCase().includes("authentication")) {
-        setError("Your access point has a custom password. Factory reset it or enter the password below.");
-        setShowPassword(true);
-      } else {
-        setError(errStr);
+      handleError(err);
+    }
+  };
+
+  /** Save the AP's logs and network state to an archive for support */
+  const handleCollectDiagnostics = async () => {
+    setCollecting(true);
+    setError(null);
+    setDiagnosticsResult(null);
+    // Follow along with the commands as their output streams in
+    const jobId = crypto.randomUUID();
+    const unlisten = await listen<SshOutputEvent>("ssh-output", (event) => {
+      const { payload } = event;
+      if (payload.jobId !== jobId || payload.kind !== "stdout") return;
+      const section = /VS_SECTION=(\S+)/.exec(payload.data);
+      if (section) setDiagnosticsStep(section[1]);
+    });
+    try {
+      const result = await invoke<DeviceDiagnosticsResult>("collect_device_diagnostics", {
+        ip: device.ip,
+        customPassword: password.trim() || null,
+        jobId,
+      });
+      const failed = result.commands.filter((c) => c.exitCode !== 0).map((c) => c.name);
+      setDiagnosticsResult(
+        `Device logs saved to ${result.path}` +
+          (failed.length ? ` (no output from ${failed.join(", ")})` : "")
+      );
+    } catch (err) {
+      handleError(err);
+    } finally {
+      unlisten();
+      setDiagnosticsStep(null);
+      setCollecting(false);
+    }
+  };
+
+  useEffect(() => {
+    const unlistenPromise = listen<{ ip: string } & FirmwareProgress>(
+      "firmware-progress",
+      (event) => {
+        if (event.payload.ip === device.ip) {
+          setFirmwareProgress(event.payload);
+        }
       }
+    );
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [device.ip]);
+
+  /** Serve a firmware image from this computer and upgrade the AP with it */
+  const handleUpgrade = async () => {
+    if (!firmwarePath.trim()) return;
+    setUpgrading(true);
+    setError(null);
+    setFirmwareProgress(null);
+    try {
+      await invoke("upgrade_firmware", {
+        ip: device.ip,
+        firmwarePath: firmwarePath.trim(),
+        customPassword: password.trim() || null,
+      });
+    } catch (err) {
+      handleError(err);
+    } finally {
+      setUpgrading(false);
+    }
+  };
+
+  const handleError = (err: unknown) => {
+    if (errorCode(err) === "auth_failed") {
+      setError("Your access point has a custom password. Factory reset it or enter the password below.");
+      setShowPassword(true);
+    } else if (errorCode(err) === "preflight_failed") {
+      setError(errorMessage(err));
+      setPreflightFailed(true);
+    } else {
+      setError(errorMessage(err));
+    }
+  };
+
+  const handleAdopt = async (skipPreflight = false) => {
+    setAdopting(true);
+    setError(null);
+    setPreflightFailed(false);
+    setPhases([]);
+    try {
+      await onAdopt({ skipPreflight, twoPhase, relay });
+    } catch (err) {
+      handleError(err);
     } finally {
       setAdopting(false);
     }
   };
 
-  const handleAdoptWithPassword = async () => {
+  const handleAdoptWithPassword = async (skipPreflight = false) => {
     if (!password.trim()) return;
     setAdopting(true);
     setError(null);
+    setPreflightFailed(false);
+    setPhases([]);
     try {
-      await onAdoptWithPassword(password);
+      await onAdoptWithPassword(password, { skipPreflight, twoPhase, relay });
     } catch (err) {
-      setError(String(err));
+      handleError(err);
     } finally {
       setAdopting(false);
     }
   };
 
+  /** Check the inform URL from this computer and from the AP itself */
+  const handleCheckConnection = async () => {
+    if (!informUrl) return;
+    setChecking(true);
+    setError(null);
+    try {
+      const reports = await invoke<PreflightReport[]>("preflight_inform", {
+        informUrl,
+        ip: device.ip,
+        customPassword: password.trim() || null,
+      });
+      setPreflight(reports);
+    } catch (err) {
+      handleError(err);
+    } finally {
+      setChecking(false);
+    }
+  };
+
+  /**
+   * Point the AP at a listener on this computer and wait for its first
+   * inform, to prove it informs before using the real controller.
+   */
+  const handleTestInform = async () => {
+    setTestingInform(true);
+    setError(null);
+    setInformResult(null);
+    try {
+      const listener = await invoke<InformListenerStatus>("start_inform_listener", {
+        deviceIp: device.ip,
+      });
+      await invoke("adopt_device", {
+        ip: device.ip,
+        informUrl: listener.informUrl,
+        customPassword: password.trim() || null,
+        context: null,
+        skipPreflight: true,
+        twoPhase: false,
+      });
+
+      const deadline = Date.now() + INFORM_TEST_TIMEOUT_MS;
+      let record: InformRecord | undefined;
+      while (!record && Date.now() < deadline) {
+        await new Promise((r) => setTimeout(r, 2000));
+        const status = await invoke<InformListenerStatus>("get_inform_status");
+        record = status.devices.find((d) => d.mac.toLowerCase() === device.mac.toLowerCase());
+      }
+      if (!record) {
+        setInformResult("No inform received from this access point within a minute.");
+      } else if (record.error) {
+        setInformResult(`The access point informed, but: ${record.error}`);
+      } else {
+        setInformResult(
+          `The access point informed this computer (${record.model ?? "unknown model"}, firmware ${record.version ?? "unknown"}).`
+        );
+      }
+    } catch (err) {
+      handleError(err);
+    } finally {
+      await invoke("stop_inform_listener").catch(() => undefined);
+      setTestingInform(false);
+    }
+  };
+
+  /** Wipe a device managed elsewhere so it can be adopted */
+  const handleFactoryReset = async () => {
+    setResetting(true);
+    setError(null);
+    try {
+      const result = await invoke<FactoryResetResult>("factory_reset_device", {
+        ip: device.ip,
+        confirmMac: resetMac.trim(),
+        customPassword: resetPassword.trim() || null,
+      });
+      setConfirmingReset(false);
+      setInformResult(`Factory reset finished in ${result.elapsedSecs}s.`);
+      onReset?.();
+    } catch (err) {
+      setError(errorMessage(err));
+    } finally {
+      setResetting(false);
+    }
+  };
+
   if (device.isManaged) {
     return (
       <div className="border border-gray-200 rounded-lg p-4 bg-gray-50 opacity-60">
@@ -77,6 +391,60 @@ export default function DeviceCard({
             </span>
           </div>
         </div>
+
+        {error && (
+          <div className="mt-3 bg-amber-50 border border-amber-200 rounded-lg p-3 text-xs text-amber-700">
+            {error}
+          </div>
+        )}
+
+        {confirmingReset ? (
+          <div className="mt-3 space-y-2">
+            <p className="text-xs text-red-700">
+              This erases all settings on the access point. Type its MAC address ({device.mac}) to
+              confirm.
+            </p>
+            <input
+              type="text"
+              value={resetMac}
+              onChange={(e) => setResetMac(e.target.value)}
+              placeholder={device.mac}
+              className="w-full text-sm border border-gray-300 rounded-lg px-3 py-1.5 focus:border-red-500 focus:outline-none"
+            />
+            <input
+              type="password"
+              value={resetPassword}
+              onChange={(e) => setResetPassword(e.target.value)}
+              placeholder="SSH password from the other controller"
+              className="w-full text-sm border border-gray-300 rounded-lg px-3 py-1.5 focus:border-red-500 focus:outline-none"
+            />
+            <div className="flex gap-2">
+              <button
+                onClick={handleFactoryReset}
+                disabled={resetting || !resetMac.trim()}
+                className="flex-1 py-1.5 bg-red-600 text-white text-sm rounded-lg hover:bg-red-700 disabled:bg-gray-200 disabled:text-gray-400 transition-colors"
+              >
+                {resetting ? "Resetting, waiting for the AP to come back..." : "Factory reset"}
+              </button>
+              <button
+                onClick={() => setConfirmingReset(false)}
+                disabled={resetting}
+                className="px-3 py-1.5 text-sm text-gray-600"
+              >
+                Cancel
+              </button>
+            </div>
+          </div>
+        ) : (
+          <button
+            onClick={() => setConfirmingReset(true)}
+            className="mt-2 text-xs text-red-700 underline"
+          >
+            Factory reset this access point
+          </button>
+        )}
+
+        <ConfigBackups ip={device.ip} mac={device.mac} password={resetPassword} />
       </div>
     );
   }
@@ -100,6 +468,32 @@ export default function DeviceCard({
             </p>
           )}
         </div>
+        <button
+          onClick={handleLocate}
+          title={locating ? "Stop blinking the LED" : "Blink the LED to find this access point"}
+          className={`p-1.5 rounded-lg transition-colors ${
+            locating ? "bg-amber-100 text-amber-600" : "text-gray-400 hover:text-vivaspot-primary"
+          }`}
+        >
+          <Lightbulb className={`w-4 h-4 ${locating ? "animate-pulse" : ""}`} />
+        </button>
+        <button
+          onClick={handleCollectDiagnostics}
+          disabled={collecting}
+          title="Save this access point's logs for support"
+          className="p-1.5 rounded-lg text-gray-400 hover:text-vivaspot-primary disabled:text-gray-300 transition-colors"
+        >
+          <FileText className={`w-4 h-4 ${collecting ? "animate-pulse" : ""}`} />
+        </button>
+        <button
+          onClick={() => setShowTerminal(!showTerminal)}
+          title={showTerminal ? "Close the terminal" : "Open a terminal on this access point"}
+          className={`p-1.5 rounded-lg transition-colors ${
+            showTerminal ? "bg-gray-100 text-vivaspot-primary" : "text-gray-400 hover:text-vivaspot-primary"
+          }`}
+        >
+          <SquareTerminal className="w-4 h-4" />
+        </button>
       </div>
 
       {/* Error */}
@@ -109,6 +503,78 @@ export default function DeviceCard({
         </div>
       )}
 
+      {/* Adoption progress */}
+      {phases.length > 0 && (
+        <div className="mt-3 space-y-1 text-xs">
+          {phases.map((phase) => (
+            <p
+              key={phase.name}
+              className={
+                phase.warning ? "text-amber-700" : phase.success ? "text-green-700" : "text-red-700"
+              }
+            >
+              {phase.warning ? "!" : phase.success ? "✓" : "✗"}{" "}
+              {PHASE_LABELS[phase.name] ?? phase.name}
+              {phase.warning && `: ${phase.detail}`}
+            </p>
+          ))}
+        </div>
+      )}
+
+      {informResult && (
+        <p className="mt-3 text-xs text-gray-600">{informResult}</p>
+      )}
+
+      {showTerminal && <TerminalPanel ip={device.ip} password={password} />}
+
+      {diagnosticsStep && (
+        <p className="mt-3 text-xs text-gray-600">Collecting {diagnosticsStep}...</p>
+      )}
+
+      {diagnosticsResult && (
+        <p className="mt-3 text-xs text-gray-600 break-all">{diagnosticsResult}</p>
+      )}
+
+      {/* Preflight results */}
+      {preflight && (
+        <div className="mt-3 space-y-1">
+          {preflight.map((report) => (
+            <div key={report.source} className="text-xs">
+              <p className="font-medium text-gray-600">
+                From {report.source === "laptop" ? "this computer" : "the access point"}
+              </p>
+              {report.checks.map((check) => (
+                <p
+                  key={check.name}
+                  className={
+                    check.status === "pass"
+                      ? "text-green-700"
+                      : check.status === "warn"
+                        ? "text-amber-700"
+                        : check.status === "fail"
+                          ? "text-red-700"
+                          : "text-gray-400"
+                  }
+                >
+                  {{ pass: "✓", warn: "!", fail: "✗", skipped: "–" }[check.status]}{" "}
+                  {check.name.toUpperCase()}: {check.detail}
+                </p>
+              ))}
+            </div>
+          ))}
+        </div>
+      )}
+
+      {preflightFailed && (
+        <button
+          onClick={() => (password.trim() ? handleAdoptWithPassword(true) : handleAdopt(true))}
+          disabled={adopting}
+          className="mt-2 text-xs text-amber-700 underline"
+        >
+          Adopt anyway
+        </button>
+      )}
+
       {/* Password entry (shown when auth fails) */}
       {showPassword && (
         <div className="mt-3 space-y-2">
@@ -132,7 +598,7 @@ export default function DeviceCard({
               className="flex-1 text-sm border border-gray-300 rounded-lg px-3 py-1.5 focus:border-vivaspot-primary focus:outline-none"
             />
             <button
-              onClick={handleAdoptWithPassword}
+              onClick={() => handleAdoptWithPassword()}
               disabled={!password.trim() || adopting}
               className="px-4 py-1.5 bg-vivaspot-primary text-white text-sm rounded-lg hover:bg-vivaspot-primary-dark disabled:bg-gray-200 disabled:text-gray-400 transition-colors"
             >
@@ -144,7 +610,7 @@ export default function DeviceCard({
 
       {/* Adopt button */}
       <button
-        onClick={handleAdopt}
+        onClick={() => handleAdopt()}
         disabled={adopting}
         className={`mt-3 w-full py-2 px-4 rounded-lg text-sm font-medium transition-colors ${
           adopting
@@ -179,6 +645,80 @@ export default function DeviceCard({
           "Connect to VivaSpot"
         )}
       </button>
+
+      <label className="mt-2 flex items-center gap-2 text-xs text-gray-500">
+        <input
+          type="checkbox"
+          checked={twoPhase}
+          onChange={(e) => setTwoPhase(e.target.checked)}
+          disabled={adopting}
+        />
+        Re-send after the controller accepts (older firmware)
+      </label>
+
+      <label className="mt-1 flex items-center gap-2 text-xs text-gray-500">
+        <input
+          type="checkbox"
+          checked={relay}
+          onChange={(e) => setRelay(e.target.checked)}
+          disabled={adopting}
+        />
+        Relay through this computer (AP can't reach the internet yet)
+      </label>
+
+      {informUrl && (
+        <button
+          onClick={handleCheckConnection}
+          disabled={checking || adopting}
+          className="mt-2 w-full py-1 text-xs text-gray-500 hover:text-vivaspot-primary transition-colors"
+        >
+          {checking ? "Checking connection..." : "Check connection to controller"}
+        </button>
+      )}
+
+      {firmwareProgress && (
+        <p className="mt-2 text-xs text-gray-600">
+          {FIRMWARE_STAGE_LABELS[firmwareProgress.stage]}
+          {firmwareProgress.stage === "upgraded" && ` ${firmwareProgress.detail}`}
+        </p>
+      )}
+
+      {showFirmware ? (
+        <div className="mt-2 flex gap-2">
+          <input
+            type="text"
+            value={firmwarePath}
+            onChange={(e) => setFirmwarePath(e.target.value)}
+            placeholder="Path to firmware .bin file"
+            disabled={upgrading}
+            className="flex-1 text-xs border border-gray-300 rounded-lg px-3 py-1.5 focus:border-vivaspot-primary focus:outline-none"
+          />
+          <button
+            onClick={handleUpgrade}
+            disabled={upgrading || adopting || !firmwarePath.trim()}
+            className="px-3 py-1.5 bg-vivaspot-primary text-white text-xs rounded-lg hover:bg-vivaspot-primary-dark disabled:bg-gray-200 disabled:text-gray-400 transition-colors"
+          >
+            {upgrading ? "Upgrading..." : "Upgrade"}
+          </button>
+        </div>
+      ) : (
+        <button
+          onClick={() => setShowFirmware(true)}
+          className="mt-1 w-full py-1 text-xs text-gray-500 hover:text-vivaspot-primary transition-colors"
+        >
+          Upgrade firmware from a file
+        </button>
+      )}
+
+      <button
+        onClick={handleTestInform}
+        disabled={testingInform || adopting}
+        className="mt-1 w-full py-1 text-xs text-gray-500 hover:text-vivaspot-primary transition-colors"
+      >
+        {testingInform ? "Waiting for the access point..." : "Test inform with this computer"}
+      </button>
+
+      <ConfigBackups ip={device.ip} mac={device.mac} password={password} />
     </div>
   );
 }
@@ -0,0 +1,78 @@
+import { useEffect, useState } from "react";
+import { ArrowLeftRight } from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
+
+interface RelayStatus {
+  forwarded: number;
+  failed: number;
+  lastStatus: number | null;
+  lastError: string | null;
+}
+
+interface InformRecord {
+  mac: string;
+  model: string | null;
+  relay: RelayStatus | null;
+}
+
+interface InformListenerStatus {
+  running: boolean;
+  relayTo: string | null;
+  devices: InformRecord[];
+}
+
+const POLL_INTERVAL_MS = 3000;
+
+interface InformRelayProps {
+  onStopped: () => void;
+}
+
+/** Status of informs relayed through this computer to the controller */
+export default function InformRelay({ onStopped }: InformRelayProps) {
+  const [status, setStatus] = useState<InformListenerStatus | null>(null);
+
+  useEffect(() => {
+    const poll = () =>
+      invoke<InformListenerStatus>("get_inform_status").then(setStatus).catch(() => {});
+    poll();
+    const timer = setInterval(poll, POLL_INTERVAL_MS);
+    return () => clearInterval(timer);
+  }, []);
+
+  const handleStop = async () => {
+    await invoke("stop_inform_listener").catch(() => {});
+    onStopped();
+  };
+
+  if (!status?.running) return null;
+
+  return (
+    <div className="border border-blue-200 bg-blue-50 rounded-lg p-4 text-left space-y-2">
+      <div className="flex items-center gap-2">
+        <ArrowLeftRight className="w-4 h-4 text-blue-700" />
+        <p className="text-sm font-medium text-blue-800 flex-1">
+          Relaying informs to {status.relayTo}
+        </p>
+        <button onClick={handleStop} className="text-xs text-blue-700 underline">
+          Stop relay
+        </button>
+      </div>
+      <p className="text-xs text-blue-700">
+        Keep this app open until the access point can reach the controller directly.
+      </p>
+      {status.devices.length === 0 ? (
+        <p className="text-xs text-gray-500">Waiting for the first inform...</p>
+      ) : (
+        status.devices.map((device) => (
+          <p key={device.mac} className="text-xs text-gray-700">
+            {device.model ?? device.mac}: {device.relay?.forwarded ?? 0} forwarded
+            {device.relay?.failed ? `, ${device.relay.failed} failed` : ""}
+            {device.relay?.lastError && (
+              <span className="text-red-700"> ({device.relay.lastError})</span>
+            )}
+          </p>
+        ))
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,66 @@
+import { ClipboardCheck } from "lucide-react";
+
+interface ExpectedDevice {
+  mac: string | null;
+  model: string | null;
+  name: string | null;
+  location: string | null;
+}
+
+interface MatchedDevice {
+  expected: ExpectedDevice;
+  device: { mac: string; ip: string };
+}
+
+export interface Reconciliation {
+  found: MatchedDevice[];
+  missing: ExpectedDevice[];
+  unexpected: { mac: string; model: string }[];
+  complete: boolean;
+}
+
+function label(device: ExpectedDevice): string {
+  const name = device.name ?? device.model ?? device.mac ?? "Access point";
+  return device.location ? `${name} (${device.location})` : name;
+}
+
+/** The site's planned access points checked off against the last scan */
+export default function InventoryChecklist({ inventory }: { inventory: Reconciliation }) {
+  const planned = inventory.found.length + inventory.missing.length;
+  if (planned === 0) return null;
+
+  return (
+    <div
+      className={`border rounded-lg p-4 text-left space-y-2 ${
+        inventory.complete ? "border-green-200 bg-green-50" : "border-amber-200 bg-amber-50"
+      }`}
+    >
+      <div className="flex items-center gap-2">
+        <ClipboardCheck
+          className={`w-4 h-4 ${inventory.complete ? "text-green-700" : "text-amber-700"}`}
+        />
+        <p
+          className={`text-sm font-medium ${
+            inventory.complete ? "text-green-800" : "text-amber-800"
+          }`}
+        >
+          {inventory.found.length} of {planned} planned access point{planned > 1 ? "s" : ""} found
+        </p>
+      </div>
+      {inventory.missing.length > 0 && (
+        <ul className="text-xs text-amber-700 space-y-0.5">
+          {inventory.missing.map((device, i) => (
+            <li key={i}>Not found: {label(device)}</li>
+          ))}
+        </ul>
+      )}
+      {inventory.unexpected.length > 0 && (
+        <p className="text-xs text-gray-600">
+          {inventory.unexpected.length} access point
+          {inventory.unexpected.length > 1 ? "s aren't" : " isn't"} in this site's plan:{" "}
+          {inventory.unexpected.map((d) => `${d.model} ${d.mac}`).join(", ")}
+        </p>
+      )}
+    </div>
+  );
+}
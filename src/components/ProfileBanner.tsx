@@ -0,0 +1,93 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../errors";
+
+interface Profile {
+  name: string;
+  apiBase: string;
+}
+
+interface Settings {
+  activeProfile: string;
+  profiles: Profile[];
+}
+
+const DEFAULT_PROFILE = "production";
+
+interface ProfileBannerProps {
+  /** Profile a deep link asked for, waiting on the user's answer */
+  requested: string | null;
+  onResolved: () => void;
+}
+
+/**
+ * Shows which API the app is talking to when it isn't production, and asks
+ * before a deep link switches it — any web page can open a vivaspot:// link.
+ */
+export default function ProfileBanner({ requested, onResolved }: ProfileBannerProps) {
+  const [settings, setSettings] = useState<Settings | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<Settings>("get_settings").then(setSettings).catch(() => {});
+  }, []);
+
+  const active = settings?.profiles.find((p) => p.name === settings.activeProfile);
+  const target = settings?.profiles.find((p) => p.name === requested);
+
+  // Nothing to ask about if the link names an unknown or the current profile
+  useEffect(() => {
+    if (requested && settings && (!target || target.name === settings.activeProfile)) {
+      onResolved();
+    }
+  }, [requested, settings, target, onResolved]);
+
+  const switchTo = async (name: string) => {
+    setError(null);
+    try {
+      setSettings(await invoke<Settings>("set_active_profile", { name }));
+      onResolved();
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
+  if (!settings) return null;
+
+  if (target && target.name !== settings.activeProfile) {
+    return (
+      <div className="bg-amber-50 border-b border-amber-200 px-6 py-3 text-sm text-amber-800">
+        <p>
+          A link asked to switch to the <strong>{target.name}</strong> API ({target.apiBase}).
+          Only switch if you're testing the app.
+        </p>
+        <div className="flex gap-3 mt-2">
+          <button
+            onClick={() => switchTo(target.name)}
+            className="py-1 px-3 bg-amber-600 text-white rounded text-xs font-medium hover:bg-amber-700"
+          >
+            Switch to {target.name}
+          </button>
+          <button onClick={onResolved} className="text-xs underline">
+            Keep {settings.activeProfile}
+          </button>
+        </div>
+        {error && <p className="mt-1 text-xs text-red-700">{error}</p>}
+      </div>
+    );
+  }
+
+  if (!active || active.name === DEFAULT_PROFILE) return null;
+
+  return (
+    <div className="bg-amber-50 border-b border-amber-200 px-6 py-2 text-xs text-amber-800 flex items-center gap-3">
+      <span className="flex-1">
+        Using the <strong>{active.name}</strong> API ({active.apiBase})
+      </span>
+      <button onClick={() => switchTo(DEFAULT_PROFILE)} className="underline">
+        Switch to {DEFAULT_PROFILE}
+      </button>
+      {error && <span className="text-red-700">{error}</span>}
+    </div>
+  );
+}
@@ -0,0 +1,148 @@
+import { useEffect, useState } from "react";
+import { LifeBuoy, ChevronDown, ChevronUp } from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { errorMessage } from "../errors";
+
+interface RecoveryStatus {
+  ip: string;
+  inRecovery: boolean;
+  localIp: string | null;
+  sameSubnet: boolean;
+}
+
+interface RecoveryResult {
+  device: { mac: string; ip: string; firmware: string };
+  elapsedSecs: number;
+}
+
+interface RecoveryPanelProps {
+  /** Called once the recovered device is back on the network */
+  onRecovered: () => void;
+}
+
+/** Reflash an AP stuck in TFTP recovery mode */
+export default function RecoveryPanel({ onRecovered }: RecoveryPanelProps) {
+  const [open, setOpen] = useState(false);
+  const [status, setStatus] = useState<RecoveryStatus | null>(null);
+  const [checking, setChecking] = useState(false);
+  const [firmwarePath, setFirmwarePath] = useState("");
+  const [flashing, setFlashing] = useState(false);
+  const [percent, setPercent] = useState<number | null>(null);
+  const [message, setMessage] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    const unlistenPromise = listen<{ sentBytes: number; totalBytes: number }>(
+      "recovery-progress",
+      (event) => {
+        const { sentBytes, totalBytes } = event.payload;
+        setPercent(totalBytes ? Math.round((sentBytes * 100) / totalBytes) : 100);
+      }
+    );
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  const handleCheck = async () => {
+    setChecking(true);
+    setError(null);
+    try {
+      setStatus(await invoke<RecoveryStatus>("detect_recovery_device"));
+    } catch (err) {
+      setError(errorMessage(err));
+    } finally {
+      setChecking(false);
+    }
+  };
+
+  const handleFlash = async () => {
+    setFlashing(true);
+    setError(null);
+    setMessage(null);
+    setPercent(0);
+    try {
+      const result = await invoke<RecoveryResult>("recovery_flash", {
+        firmwarePath: firmwarePath.trim(),
+        ip: status?.ip ?? null,
+      });
+      setMessage(
+        `Recovered ${result.device.mac} at ${result.device.ip} (${result.device.firmware}) in ${result.elapsedSecs}s.`
+      );
+      onRecovered();
+    } catch (err) {
+      setError(errorMessage(err));
+    } finally {
+      setFlashing(false);
+    }
+  };
+
+  return (
+    <div className="border border-gray-200 rounded-lg bg-white">
+      <button
+        onClick={() => setOpen(!open)}
+        className="w-full flex items-center gap-2 px-4 py-3 text-sm text-gray-700"
+      >
+        <LifeBuoy className="w-4 h-4 text-gray-400" />
+        <span className="flex-1 text-left">Recover a bricked access point</span>
+        {open ? <ChevronUp className="w-4 h-4" /> : <ChevronDown className="w-4 h-4" />}
+      </button>
+
+      {open && (
+        <div className="px-4 pb-4 space-y-2 text-xs">
+          <p className="text-gray-500">
+            Hold the reset button while powering the AP on until its LED alternates colours,
+            then give this computer a fixed address such as 192.168.1.25.
+          </p>
+
+          <button
+            onClick={handleCheck}
+            disabled={checking || flashing}
+            className="text-vivaspot-primary underline"
+          >
+            {checking ? "Looking for the access point..." : "Check for an AP in recovery mode"}
+          </button>
+
+          {status && (
+            <p className={status.inRecovery ? "text-green-700" : "text-amber-700"}>
+              {status.inRecovery
+                ? `Found an access point in recovery mode at ${status.ip}.`
+                : `Nothing answered at ${status.ip}.`}
+              {!status.sameSubnet &&
+                ` This computer (${status.localIp ?? "no address"}) isn't on the same network.`}
+            </p>
+          )}
+
+          <div className="flex gap-2">
+            <input
+              type="text"
+              value={firmwarePath}
+              onChange={(e) => setFirmwarePath(e.target.value)}
+              placeholder="Path to firmware .bin file"
+              disabled={flashing}
+              className="flex-1 border border-gray-300 rounded-lg px-3 py-1.5 focus:border-vivaspot-primary focus:outline-none"
+            />
+            <button
+              onClick={handleFlash}
+              disabled={flashing || !firmwarePath.trim()}
+              className="px-3 py-1.5 bg-vivaspot-primary text-white rounded-lg hover:bg-vivaspot-primary-dark disabled:bg-gray-200 disabled:text-gray-400 transition-colors"
+            >
+              {flashing ? "Flashing..." : "Flash"}
+            </button>
+          </div>
+
+          {flashing && percent !== null && (
+            <p className="text-gray-600">
+              {percent < 100
+                ? `Sending firmware: ${percent}%`
+                : "Firmware sent, waiting for the access point to restart..."}
+            </p>
+          )}
+          {message && <p className="text-green-700">{message}</p>}
+          {error && <p className="text-red-700">{error}</p>}
+        </div>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,188 @@
+import { useEffect, useRef, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { errorMessage } from "../errors";
+
+interface TerminalPanelProps {
+  ip: string;
+  password: string;
+}
+
+/** Keep the scrollback bounded on chatty commands like `logread -f` */
+const MAX_SCROLLBACK = 200_000;
+const CHAR_WIDTH_PX = 7.2;
+const LINE_HEIGHT_PX = 16;
+
+const KEY_SEQUENCES: Record<string, string> = {
+  Enter: "\r",
+  Backspace: "\x7f",
+  Tab: "\t",
+  Escape: "\x1b",
+  ArrowUp: "\x1b[A",
+  ArrowDown: "\x1b[B",
+  ArrowRight: "\x1b[C",
+  ArrowLeft: "\x1b[D",
+  Home: "\x1b[H",
+  End: "\x1b[F",
+  Delete: "\x1b[3~",
+};
+
+/** Colours, cursor movement and title changes aren't rendered */
+const ANSI_ESCAPE = /\x1b(\[[0-9;?]*[A-Za-z]|\][^\x07]*\x07|[()][A-Z0-9]|[=>])/g;
+
+function appendOutput(screen: string, chunk: string): string {
+  let text = screen;
+  for (const ch of chunk.replace(ANSI_ESCAPE, "").replace(/\r\n/g, "\n")) {
+    if (ch === "\b") {
+      text = text.endsWith("\n") ? text : text.slice(0, -1);
+    } else if (ch !== "\r" && ch !== "\x07") {
+      text += ch;
+    }
+  }
+  return text.length > MAX_SCROLLBACK ? text.slice(-MAX_SCROLLBACK) : text;
+}
+
+function keySequence(e: React.KeyboardEvent): string | null {
+  if (e.ctrlKey && e.key.length === 1) {
+    const code = e.key.toUpperCase().charCodeAt(0);
+    return code >= 64 && code <= 95 ? String.fromCharCode(code - 64) : null;
+  }
+  if (e.metaKey || e.altKey) return null;
+  return KEY_SEQUENCES[e.key] ?? (e.key.length === 1 ? e.key : null);
+}
+
+function decodeBase64(data: string): Uint8Array {
+  return Uint8Array.from(atob(data), (c) => c.charCodeAt(0));
+}
+
+/** Interactive shell on an access point */
+export default function TerminalPanel({ ip, password }: TerminalPanelProps) {
+  const [screen, setScreen] = useState("");
+  const [status, setStatus] = useState<"connecting" | "open" | "closed">("connecting");
+  const [error, setError] = useState<string | null>(null);
+  const sessionRef = useRef<string | null>(null);
+  const screenRef = useRef<HTMLPreElement>(null);
+  // Typing in the card's password field shouldn't reconnect
+  const passwordRef = useRef(password);
+
+  useEffect(() => {
+    let cancelled = false;
+    const decoder = new TextDecoder();
+    // Chosen here so the banner and first prompt, which can arrive before
+    // open_terminal returns, are already ours
+    const sessionId = crypto.randomUUID();
+    const unlistenOutput = listen<{ sessionId: string; data: string }>(
+      "terminal-output",
+      (event) => {
+        if (event.payload.sessionId !== sessionId) return;
+        const text = decoder.decode(decodeBase64(event.payload.data), { stream: true });
+        setScreen((current) => appendOutput(current, text));
+      }
+    );
+    const unlistenClosed = listen<{ sessionId: string; exitStatus: number | null }>(
+      "terminal-closed",
+      (event) => {
+        if (event.payload.sessionId === sessionId) setStatus("closed");
+      }
+    );
+
+    const { cols, rows } = terminalSize(screenRef.current);
+    Promise.all([unlistenOutput, unlistenClosed])
+      .then(() =>
+        invoke("open_terminal", {
+          sessionId,
+          ip,
+          customPassword: passwordRef.current.trim() || null,
+          cols,
+          rows,
+        })
+      )
+      .then(() => {
+        if (cancelled) {
+          invoke("close_terminal", { sessionId }).catch(() => undefined);
+          return;
+        }
+        sessionRef.current = sessionId;
+        setStatus("open");
+        screenRef.current?.focus();
+      })
+      .catch((err) => {
+        setError(errorMessage(err));
+        setStatus("closed");
+      });
+
+    return () => {
+      cancelled = true;
+      unlistenOutput.then((unlisten) => unlisten());
+      unlistenClosed.then((unlisten) => unlisten());
+      if (sessionRef.current) {
+        invoke("close_terminal", { sessionId: sessionRef.current }).catch(() => undefined);
+        sessionRef.current = null;
+      }
+    };
+  }, [ip]);
+
+  // Follow the view's size so full-screen tools like `top` fit
+  useEffect(() => {
+    const element = screenRef.current;
+    if (!element) return;
+    const observer = new ResizeObserver(() => {
+      if (!sessionRef.current) return;
+      const { cols, rows } = terminalSize(element);
+      invoke("terminal_resize", { sessionId: sessionRef.current, cols, rows }).catch(
+        () => undefined
+      );
+    });
+    observer.observe(element);
+    return () => observer.disconnect();
+  }, []);
+
+  useEffect(() => {
+    const element = screenRef.current;
+    if (element) element.scrollTop = element.scrollHeight;
+  }, [screen]);
+
+  const handleKeyDown = (e: React.KeyboardEvent) => {
+    const data = keySequence(e);
+    if (data === null || !sessionRef.current || status !== "open") return;
+    e.preventDefault();
+    invoke("terminal_input", { sessionId: sessionRef.current, data }).catch((err) =>
+      setError(errorMessage(err))
+    );
+  };
+
+  const handlePaste = (e: React.ClipboardEvent) => {
+    const data = e.clipboardData.getData("text");
+    if (!data || !sessionRef.current || status !== "open") return;
+    e.preventDefault();
+    invoke("terminal_input", { sessionId: sessionRef.current, data }).catch((err) =>
+      setError(errorMessage(err))
+    );
+  };
+
+  return (
+    <div className="mt-3 text-xs">
+      <pre
+        ref={screenRef}
+        tabIndex={0}
+        onKeyDown={handleKeyDown}
+        onPaste={handlePaste}
+        className="h-64 overflow-auto whitespace-pre-wrap break-all rounded-lg bg-gray-900 p-2 font-mono text-gray-100 focus:outline-none focus:ring-2 focus:ring-vivaspot-primary"
+      >
+        {screen}
+        {status === "open" && <span className="animate-pulse">▌</span>}
+      </pre>
+      {status === "connecting" && <p className="mt-1 text-gray-500">Connecting to {ip}...</p>}
+      {status === "closed" && !error && <p className="mt-1 text-gray-500">Session ended.</p>}
+      {error && <p className="mt-1 text-red-700">{error}</p>}
+    </div>
+  );
+}
+
+function terminalSize(element: HTMLElement | null): { cols: number; rows: number } {
+  if (!element) return { cols: 80, rows: 24 };
+  return {
+    cols: Math.max(20, Math.floor((element.clientWidth - 16) / CHAR_WIDTH_PX)),
+    rows: Math.max(5, Math.floor((element.clientHeight - 16) / LINE_HEIGHT_PX)),
+  };
+}
@@ -0,0 +1,37 @@
+/** Error shape returned by every Tauri command (see src-tauri/src/error.rs). */
+export interface AppError {
+  code: string;
+  message: string;
+  hint: string;
+  retryable: boolean;
+  /** Seconds the API asked us to wait; set on rate_limited errors only */
+  retryAfterSecs: number | null;
+}
+
+export function isAppError(err: unknown): err is AppError {
+  return (
+    typeof err === "object" &&
+    err !== null &&
+    "code" in err &&
+    "message" in err
+  );
+}
+
+/** Stable error code, or null for errors that didn't come from a command. */
+export function errorCode(err: unknown): string | null {
+  return isAppError(err) ? err.code : null;
+}
+
+/** Human-readable message for display. */
+export function errorMessage(err: unknown): string {
+  if (!isAppError(err)) return String(err);
+  if (err.retryAfterSecs) {
+    return `${err.message} Try again in ${formatWait(err.retryAfterSecs)}.`;
+  }
+  return err.message;
+}
+
+function formatWait(secs: number): string {
+  if (secs < 90) return `${secs} second${secs === 1 ? "" : "s"}`;
+  return `${Math.ceil(secs / 60)} minutes`;
+}